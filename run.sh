@@ -7,4 +7,4 @@ if [ -z "$DAY" ]
     exit 1
 fi
 
-RUST_BACKTRACE=1 cargo run --features="$2" --bin "day$DAY" --release "${@:2}"
+RUST_BACKTRACE=1 cargo run --bin aoc --release -- run "$DAY" "${@:2}"
@@ -0,0 +1,194 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use days::Part;
+use ParseError;
+
+pub const ANSWERS_FILE: &str = "answers.toml";
+
+// Outcome of checking an answer against the stored one
+#[derive(PartialEq, Debug)]
+pub enum Outcome {
+    Pass,
+    Fail { expected: String },
+    Missing
+}
+
+// Known answers for the real inputs, stored in a small subset of TOML:
+//
+//   [day01]
+//   part1 = 416
+//   part2 = "56752"
+//
+// Values are integers or basic strings (with \n, \" and \\ escapes for multi-line answers)
+#[derive(Debug, Default)]
+pub struct Answers {
+    answers: BTreeMap<(u32, u8), String>
+}
+
+fn part_key(part: Part) -> u8 {
+    match part {
+        Part::One => 1,
+        Part::Two => 2
+    }
+}
+
+fn parse_value(value: &str) -> Result<String, String> {
+    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        let mut unescaped = String::new();
+        let mut chars = value[1..(value.len() - 1)].chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some('n') => unescaped.push('\n'),
+                    Some('"') => unescaped.push('"'),
+                    Some('\\') => unescaped.push('\\'),
+                    e => return Err(format!("Invalid escape sequence '\\{}'", e.map(|c| c.to_string()).unwrap_or_default()))
+                },
+                '"' => return Err("Unescaped '\"' in string".to_string()),
+                c => unescaped.push(c)
+            }
+        }
+        Ok(unescaped)
+    } else if !value.is_empty() && value.trim_start_matches('-').chars().all(|c| c.is_ascii_digit()) {
+        Ok(value.to_string())
+    } else {
+        Err(format!("Expected a quoted string or an integer, got '{}'", value))
+    }
+}
+
+fn escape_value(value: &str) -> String {
+    let escaped = value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+    format!("\"{}\"", escaped)
+}
+
+impl Answers {
+    pub fn default_path() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(ANSWERS_FILE)
+    }
+
+    pub fn load(path: &Path) -> Result<Answers, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read answers from '{}': {}", path.display(), e))?;
+        Answers::parse(&content)
+            .map_err(|e| format!("Invalid answers file '{}': {}", path.display(), e))
+    }
+
+    pub fn parse(input: &str) -> Result<Answers, ParseError> {
+        let mut answers = Answers::default();
+        let mut day = None;
+
+        for (ln, line) in input.lines().enumerate() {
+            let err = |msg: String| ParseError::new(msg).at_line(ln + 1);
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if line.starts_with('[') {
+                let num = line.strip_prefix("[day")
+                    .and_then(|l| l.strip_suffix(']'))
+                    .and_then(|n| n.parse::<u32>().ok())
+                    .ok_or_else(|| err(format!("Expected a table like [day01], got '{}'", line)))?;
+                day = Some(num);
+                continue;
+            }
+
+            let mut kv = line.splitn(2, '=').map(|s| s.trim());
+            let (key, value) = match (kv.next(), kv.next()) {
+                (Some(k), Some(v)) => (k, v),
+                _ => return Err(err(format!("Expected key = value, got '{}'", line)))
+            };
+            let part = match key {
+                "part1" => 1,
+                "part2" => 2,
+                _ => return Err(err(format!("Unknown key '{}', expected part1 or part2", key)))
+            };
+            let day = day.ok_or_else(|| err("Answer outside of a [dayNN] table".to_string()))?;
+            answers.answers.insert((day, part), parse_value(value).map_err(err)?);
+        }
+
+        Ok(answers)
+    }
+
+    pub fn get(&self, day: u32, part: Part) -> Option<&str> {
+        self.answers.get(&(day, part_key(part))).map(|a| a.as_str())
+    }
+
+    pub fn insert(&mut self, day: u32, part: Part, answer: String) {
+        self.answers.insert((day, part_key(part)), answer);
+    }
+
+    pub fn check(&self, day: u32, part: Part, answer: &str) -> Outcome {
+        match self.get(day, part) {
+            Some(expected) if expected == answer => Outcome::Pass,
+            Some(expected) => Outcome::Fail { expected: expected.to_string() },
+            None => Outcome::Missing
+        }
+    }
+
+    pub fn to_toml(&self) -> String {
+        let mut out = String::new();
+        let mut last_day = None;
+        for (&(day, part), answer) in self.answers.iter() {
+            if last_day != Some(day) {
+                if last_day.is_some() {
+                    out.push('\n');
+                }
+                out.push_str(&format!("[day{:02}]\n", day));
+                last_day = Some(day);
+            }
+            out.push_str(&format!("part{} = {}\n", part, escape_value(answer)));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str =
+       "# Answers for the real inputs
+        [day01]
+        part1 = 416
+        part2 = \"56752\"
+
+        [day10]
+        part1 = \"#..#\\n#..#\"";
+
+    #[test]
+    fn test_parse() {
+        let answers = Answers::parse(INPUT).unwrap();
+        assert_eq!(answers.get(1, Part::One), Some("416"));
+        assert_eq!(answers.get(1, Part::Two), Some("56752"));
+        assert_eq!(answers.get(10, Part::One), Some("#..#\n#..#"));
+        assert_eq!(answers.get(10, Part::Two), None);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(Answers::parse("part1 = 1").unwrap_err(),
+                   ParseError::new("Answer outside of a [dayNN] table").at_line(1));
+        assert_eq!(Answers::parse("[day01]\npart3 = 1").unwrap_err(),
+                   ParseError::new("Unknown key 'part3', expected part1 or part2").at_line(2));
+        assert!(Answers::parse("[day01]\npart1 = abc").is_err());
+        assert!(Answers::parse("[dayX]").is_err());
+    }
+
+    #[test]
+    fn test_check() {
+        let answers = Answers::parse(INPUT).unwrap();
+        assert_eq!(answers.check(1, Part::One, "416"), Outcome::Pass);
+        assert_eq!(answers.check(1, Part::One, "417"), Outcome::Fail { expected: "416".to_string() });
+        assert_eq!(answers.check(2, Part::One, "1"), Outcome::Missing);
+    }
+
+    #[test]
+    fn test_to_toml_roundtrip() {
+        let answers = Answers::parse(INPUT).unwrap();
+        let toml = answers.to_toml();
+        assert_eq!(toml, "[day01]\npart1 = \"416\"\npart2 = \"56752\"\n\n[day10]\npart1 = \"#..#\\n#..#\"\n");
+        assert_eq!(Answers::parse(&toml).unwrap().get(10, Part::One), Some("#..#\n#..#"));
+    }
+}
@@ -0,0 +1,244 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+use time::precise_time_ns;
+use days::{Day, InputSource, Part};
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Stage {
+    Load, Parse, Part1, Part2
+}
+
+impl Stage {
+    pub fn of(part: Part) -> Stage {
+        match part {
+            Part::One => Stage::Part1,
+            Part::Two => Stage::Part2
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Stage::Load => "load",
+            Stage::Parse => "parse",
+            Stage::Part1 => "part1",
+            Stage::Part2 => "part2"
+        };
+        // Padding is applied to the name so that stages line up in tables
+        f.pad(name)
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Format {
+    Text, Json, Csv
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            _ => Err(format!("Invalid format '{}', expected text, json or csv", s))
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Options {
+    pub iterations: usize,
+    pub warmup: usize
+}
+
+impl Options {
+    // A single measured run, as done when just running the solutions
+    pub fn once() -> Options {
+        Options { iterations: 1, warmup: 0 }
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Stats {
+    pub min_us: f64,
+    pub median_us: f64,
+    pub p95_us: f64
+}
+
+impl Stats {
+    pub fn from_samples(samples_ns: &[u64]) -> Stats {
+        let mut sorted = samples_ns.to_vec();
+        sorted.sort_unstable();
+        let n = sorted.len();
+        let us = |ns: u64| ns as f64 / 1000.0;
+
+        let median = if n.is_multiple_of(2) {
+            (us(sorted[n / 2 - 1]) + us(sorted[n / 2])) / 2.0
+        } else {
+            us(sorted[n / 2])
+        };
+        // Nearest-rank percentile
+        let p95_idx = ((n as f64 * 0.95).ceil() as usize).max(1) - 1;
+
+        Stats { min_us: us(sorted[0]), median_us: median, p95_us: us(sorted[p95_idx]) }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Timing {
+    pub stage: Stage,
+    pub samples_ns: Vec<u64>
+}
+
+impl Timing {
+    pub fn stats(&self) -> Stats {
+        Stats::from_samples(&self.samples_ns)
+    }
+}
+
+pub struct DayBench {
+    pub day: u32,
+    pub answers: Vec<(Part, String)>,
+    pub timings: Vec<Timing>
+}
+
+impl DayBench {
+    pub fn timing(&self, stage: Stage) -> Option<&Timing> {
+        self.timings.iter().find(|t| t.stage == stage)
+    }
+}
+
+pub fn time<T, F: FnOnce() -> T>(f: F) -> (T, u64) {
+    let start = precise_time_ns();
+    let res = f();
+    (res, precise_time_ns() - start)
+}
+
+// Runs a stage warmup + iterations times, returning the result of the last run and the timings
+// of the measured runs
+fn repeat<T, F>(stage: Stage, opts: &Options, mut f: F) -> Result<(T, Timing), Box<dyn Error>>
+where F: FnMut() -> Result<T, Box<dyn Error>> {
+    for _ in 0..opts.warmup {
+        f()?;
+    }
+
+    let mut samples_ns = vec![];
+    let mut last = None;
+    for _ in 0..opts.iterations.max(1) {
+        let (res, ns) = time(&mut f);
+        last = Some(res?);
+        samples_ns.push(ns);
+    }
+
+    Ok((last.unwrap(), Timing { stage, samples_ns }))
+}
+
+pub fn bench_day(day: &Day, source: &InputSource, parts: &[Part], opts: &Options) -> Result<DayBench, Box<dyn Error>> {
+    let mut timings = vec![];
+
+    let (input, t) = repeat(Stage::Load, opts, || Ok(day.read_input(source)?))?;
+    timings.push(t);
+
+    let (parsed, t) = repeat(Stage::Parse, opts, || Ok(day.parse(&input)?))?;
+    timings.push(t);
+
+    let mut answers = vec![];
+    for &part in parts {
+        let (answer, t) = repeat(Stage::of(part), opts, || day.solve(&parsed, part))?;
+        answers.push((part, answer));
+        timings.push(t);
+    }
+
+    Ok(DayBench { day: day.num, answers, timings })
+}
+
+pub fn write_report<W: Write>(w: &mut W, benches: &[DayBench], format: Format) -> io::Result<()> {
+    let rows = benches.iter()
+        .flat_map(|b| b.timings.iter().map(move |t| (b.day, t.stage, t.samples_ns.len(), t.stats())));
+
+    match format {
+        Format::Text => {
+            writeln!(w, "Day  Stage  Iterations      Min µs   Median µs      P95 µs")?;
+            for (day, stage, n, s) in rows {
+                writeln!(w, "{:3}  {:<5}  {:10}  {:10.1}  {:10.1}  {:10.1}", day, stage, n, s.min_us, s.median_us, s.p95_us)?;
+            }
+        },
+        Format::Csv => {
+            writeln!(w, "day,stage,iterations,min_us,median_us,p95_us")?;
+            for (day, stage, n, s) in rows {
+                writeln!(w, "{},{},{},{:.3},{:.3},{:.3}", day, stage, n, s.min_us, s.median_us, s.p95_us)?;
+            }
+        },
+        Format::Json => {
+            writeln!(w, "[")?;
+            let rows: Vec<_> = rows.collect();
+            for (i, (day, stage, n, s)) in rows.iter().enumerate() {
+                writeln!(w, "  {{\"day\": {}, \"stage\": \"{}\", \"iterations\": {}, \"min_us\": {:.3}, \"median_us\": {:.3}, \"p95_us\": {:.3}}}{}",
+                         day, stage, n, s.min_us, s.median_us, s.p95_us, if i + 1 < rows.len() { "," } else { "" })?;
+            }
+            writeln!(w, "]")?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_bench() -> DayBench {
+        DayBench {
+            day: 15,
+            answers: vec![],
+            timings: vec![
+                Timing { stage: Stage::Parse, samples_ns: vec![3000, 1000, 2000] },
+                Timing { stage: Stage::Part1, samples_ns: vec![1500] }
+            ]
+        }
+    }
+
+    fn report(format: Format) -> String {
+        let mut out = vec![];
+        write_report(&mut out, &[sample_bench()], format).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_stats() {
+        assert_eq!(Stats::from_samples(&[1000]), Stats { min_us: 1.0, median_us: 1.0, p95_us: 1.0 });
+        assert_eq!(Stats::from_samples(&[4000, 1000, 3000, 2000]), Stats { min_us: 1.0, median_us: 2.5, p95_us: 4.0 });
+
+        let samples: Vec<u64> = (1..=100).map(|i| i * 1000).collect();
+        assert_eq!(Stats::from_samples(&samples), Stats { min_us: 1.0, median_us: 50.5, p95_us: 95.0 });
+    }
+
+    #[test]
+    fn test_repeat() {
+        let mut calls = 0;
+        let (res, t) = repeat(Stage::Load, &Options { iterations: 3, warmup: 2 }, || { calls += 1; Ok(calls) }).unwrap();
+        assert_eq!(res, 5);
+        assert_eq!(t.samples_ns.len(), 3);
+    }
+
+    #[test]
+    fn test_report_csv() {
+        assert_eq!(report(Format::Csv),
+                   "day,stage,iterations,min_us,median_us,p95_us\n\
+                    15,parse,3,1.000,2.000,3.000\n\
+                    15,part1,1,1.500,1.500,1.500\n");
+    }
+
+    #[test]
+    fn test_report_json() {
+        assert_eq!(report(Format::Json),
+                   "[\n  \
+                    {\"day\": 15, \"stage\": \"parse\", \"iterations\": 3, \"min_us\": 1.000, \"median_us\": 2.000, \"p95_us\": 3.000},\n  \
+                    {\"day\": 15, \"stage\": \"part1\", \"iterations\": 1, \"min_us\": 1.500, \"median_us\": 1.500, \"p95_us\": 1.500}\n\
+                    ]\n");
+    }
+}
@@ -0,0 +1,624 @@
+extern crate utils;
+
+use std::env;
+use std::error::Error;
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter};
+use std::path::PathBuf;
+use std::process;
+use std::sync::mpsc;
+use std::thread;
+use utils::answers::{Answers, Outcome};
+use utils::bench::{self, DayBench, Format, Options, Stage, Stats, Timing};
+use utils::days::{self, Day, InputSource, Part};
+use utils::log;
+use utils::render::{Frames, ImageFormat};
+use utils::Solution;
+use utils::elfcode::{Device, Overflow, Program, Register};
+use utils::day15::{Day15, PowerSearch};
+use utils::day15::combat::{self, CombatSim, Map, Movement, Rules, ELF};
+use utils::day15::player::Player;
+use utils::day15::replay::Replay;
+use utils::day16::{self, Day16, Mapping};
+use utils::elfcode::asm::{Assembler, Diagnostic, Severity};
+use utils::elfcode::debugger::Debugger;
+use utils::elfcode::decompile;
+use utils::elfcode::trace::{self, Filter, TraceReader};
+use utils::elfcode::parse_register;
+
+const USAGE: &str = "Usage:
+  aoc run <days> [--part <1|2>] [--input <path>] [--frames <dir>] [--power-table]
+                                  Run solutions, days given as all, 15, 1-5 or 1,3,10-12
+  aoc bench <days> [--part <1|2>] [--input <path>] [--iterations <n>] [--warmup <n>] [--format <f>]
+                                  Benchmark loading, parsing and solving of the days
+  aoc bench-combat [--input <path>] [--sizes <sizes>] [--seed <n>] [--iterations <n>] [--warmup <n>]
+                                  Compare the movement of day 15 units with flood fills and with a
+                                  single search, on the input and on generated maps, 3 runs each
+  aoc record-combat --output <path> [--input <path>] [--elf-attack <n>]
+                                  Record every round of the day 15 combat to a replay file
+  aoc play-combat <path> [--no-colour]
+                                  Play a day 15 replay in the terminal, type help for the commands
+  aoc verify [<days>] [--answers <path>] [--record]
+                                  Check answers of the days (default all) against the answers file
+  aoc debug <day> [--input <path>]
+                                  Step through the ElfCode program of a day (19, 21) or of a file
+  aoc decompile <day> [--input <path>]
+                                  Print structured pseudo code for the ElfCode program of a day
+  aoc lint <day> [--input <path>]
+                                  Check the ElfCode program of a day for errors and jumps out of it
+  aoc disasm [--input <path>]     Translate the numeric program of day 16 to ElfCode
+  aoc exec <day> [--input <path>] [--width <w>] [--overflow <o>] [--reg0 <n>] [--limit <n>]
+                                  Run the ElfCode program of a day without optimizations
+  aoc trace <day> --output <path> [--input <path>] [--reg0 <n>] [--limit <n>]
+                                  Record every step of the ElfCode program of a day to a trace file
+  aoc trace-show <path> [--at <addrs>] [--changed <reg>]
+                                  Print the steps of a trace, only at the addresses or changing reg
+  aoc trace-diff <path> <path> [--ignore <regs>]
+                                  Print the first step at which two traces differ
+  aoc list                        List available days
+
+Options:
+  -p, --part <1|2>       Only run the given part
+  -i, --input <path>     Read the input from path instead, - for stdin (single day only)
+  -n, --iterations <n>   Number of measured runs per stage (default 10)
+  -w, --warmup <n>       Number of unmeasured runs before measuring (default 2)
+  -f, --format <f>       Benchmark output as text, json or csv (default text)
+  -a, --answers <path>   Answers file to verify against (default answers.toml in the crate root)
+      --sizes <sizes>    Sides of the generated maps, as 64 or 32,64 (default 32,64)
+      --seed <n>         Seed of the generated maps (default 1)
+      --record           Store answers for days and parts missing from the answers file
+      --width <w>        Registers as u32, u64 or i64 (default u64)
+      --overflow <o>     On overflow of additions and multiplications wrap, saturate or trap
+                         with an error (default wrap)
+  -o, --output <path>    Trace or replay file to write
+      --elf-attack <n>   Attack power of the elves in the recorded combat (default 3)
+      --no-colour        Play the replay without ANSI colours
+      --power-table      Also print the battles of the search for the elf attack power of day 15
+      --reg0 <n>         Initial value of register 0 (default 0)
+      --limit <n>        Maximum number of steps to run (default 10000000)
+      --at <addrs>       Addresses of the steps to show, as 28 or 17,28
+      --changed <reg>    Only show steps changing the register, as c or 2
+      --ignore <regs>    Registers whose values don't matter when diffing traces, as a or a,c
+      --frames <dir>     Also write an image per tick of the simulation to dir, for days 13, 15,
+                         17, 18 and 22
+      --image <f>        Frames as png or ppm (default png)
+      --scale <n>        Pixels per grid cell in the frames (default 1)
+      --every <n>        Only write every nth frame, the last one is always written (default 1)
+  -v, -vv                Log debug or also trace details of the solutions to stderr
+      --trace <targets>  Only log trace details of some days or modules, as day15 or day15,elfcode
+      --log <spec>       Log levels like $AOC_LOG, as debug or warn,day15=trace
+
+Inputs are read from $AOC_INPUT_DIR/dayNN/input, or from src/dayNN/input when not set.
+Logging is configured from $AOC_LOG, with the command line options added to it";
+
+struct Args {
+    days: Vec<&'static Day>,
+    parts: Vec<Part>,
+    source: InputSource,
+    opts: Options,
+    format: Format,
+    answers: Option<PathBuf>,
+    record: bool,
+    frames: Option<PathBuf>,
+    image: ImageFormat,
+    scale: usize,
+    every: usize
+}
+
+fn parse_args(args: &[String], opts: Options, default_spec: Option<&str>) -> Result<Args, Box<dyn Error>> {
+    let mut spec = default_spec.map(|s| s.to_string());
+    let mut spec_given = false;
+    let mut parts = Part::all();
+    let mut source = InputSource::Default;
+    let mut opts = opts;
+    let mut format = Format::Text;
+    let mut answers = None;
+    let mut record = false;
+    let mut frames = None;
+    let mut image = ImageFormat::Png;
+    let mut scale = 1;
+    let mut every = 1;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("Missing value for {}", arg));
+        match arg.as_str() {
+            "--part" | "-p" => parts = vec![value()?.parse::<Part>()?],
+            "--input" | "-i" => source = value()?.parse::<InputSource>()?,
+            "--iterations" | "-n" => opts.iterations = value()?.parse()?,
+            "--warmup" | "-w" => opts.warmup = value()?.parse()?,
+            "--format" | "-f" => format = value()?.parse::<Format>()?,
+            "--answers" | "-a" => answers = Some(PathBuf::from(value()?)),
+            "--record" => record = true,
+            "--frames" => frames = Some(PathBuf::from(value()?)),
+            "--image" => image = value()?.parse::<ImageFormat>()?,
+            "--scale" => scale = value()?.parse()?,
+            "--every" => every = value()?.parse()?,
+            _ if !spec_given => {
+                spec = Some(arg.to_owned());
+                spec_given = true;
+            },
+            _ => return Err(format!("Unexpected argument '{}'", arg).into())
+        }
+    }
+
+    let spec = spec.ok_or("No days given")?;
+    let days = days::select(&spec)?;
+    if source != InputSource::Default && days.len() > 1 {
+        return Err("--input can only be used when running a single day".into());
+    }
+    if opts.iterations == 0 {
+        return Err("--iterations must be at least 1".into());
+    }
+    if scale == 0 || every == 0 {
+        return Err("--scale and --every must be at least 1".into());
+    }
+    if let Some(day) = days.iter().find(|d| frames.is_some() && !d.has_animation()) {
+        return Err(format!("Day {:02} has no frames to write", day.num).into());
+    }
+
+    Ok(Args { days, parts, source, opts, format, answers, record, frames, image, scale, every })
+}
+
+fn print_timings(b: &DayBench) {
+    let timings: Vec<String> = b.timings.iter()
+        .map(|t| format!("{} {:.1}µs", t.stage, t.stats().median_us))
+        .collect();
+    println!("Exec time: {}", timings.join(", "));
+}
+
+fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut rest = args.to_vec();
+    let given = rest.len();
+    rest.retain(|a| a != "--power-table");
+    let power_table = rest.len() != given;
+    let args = parse_args(&rest, Options::once(), None)?;
+    if power_table && (args.days.iter().any(|d| d.num != 15) || !args.parts.contains(&Part::Two)) {
+        return Err("--power-table can only be used when running part 2 of day 15".into());
+    }
+
+    for day in args.days {
+        println!("Day {:02}: {}", day.num, day.title);
+        let parts: Vec<Part> = args.parts.iter().cloned().filter(|&p| !power_table || p != Part::Two).collect();
+        let mut b = bench::bench_day(day, &args.source, &parts, &args.opts)?;
+        // Part 2 is the search itself then, run here to keep its battles for the table
+        let mut table = None;
+        if power_table {
+            let map = Day15::parse(&day.read_input(&args.source)?)?;
+            let mut search = PowerSearch::new(&map);
+            let (score, ns) = bench::time(|| search.run().outcome.score());
+            b.answers.push((Part::Two, score.to_string()));
+            b.timings.push(Timing { stage: Stage::Part2, samples_ns: vec![ns] });
+            table = Some(search.table());
+        }
+        for (part, result) in b.answers.iter() {
+            if result.contains('\n') {
+                println!("Part{} result:\n{}", part, result);
+            } else {
+                println!("Part{} result: {}", part, result);
+            }
+        }
+        print_timings(&b);
+
+        if let Some(table) = table {
+            print!("Battles by elf attack power:\n{}", table);
+        }
+
+        if let Some(dir) = &args.frames {
+            let input = day.parse(&day.read_input(&args.source)?)?;
+            let mut frames = Frames::new(dir, &format!("day{:02}", day.num), args.image)?
+                .with_scale(args.scale)
+                .with_every(args.every);
+            day.animate(&input, &mut frames).unwrap()?;
+            println!("Wrote {} frames to {}", frames.written(), dir.display());
+        }
+    }
+    Ok(())
+}
+
+fn bench(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let args = parse_args(args, Options { iterations: 10, warmup: 2 }, None)?;
+
+    let mut benches = vec![];
+    for day in args.days {
+        if args.format == Format::Text {
+            eprintln!("Benchmarking day {:02}: {}", day.num, day.title);
+        }
+        benches.push(bench::bench_day(day, &args.source, &args.parts, &args.opts)?);
+    }
+
+    bench::write_report(&mut io::stdout(), &benches, args.format)?;
+    if args.format == Format::Text {
+        let total: f64 = benches.iter()
+            .flat_map(|b| b.timings.iter().filter(|t| t.stage != Stage::Load))
+            .map(|t| t.stats().median_us)
+            .sum();
+        println!("Total median parse + solve: {:.1}µs", total);
+    }
+    Ok(())
+}
+
+// Times whole combats of day 15 with each movement, checking that they end the same
+fn bench_combat(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut rest = args.to_vec();
+    let values = take_options(&mut rest, &["--sizes", "--seed"])?;
+    let sizes = values[0].as_deref().unwrap_or("32,64").split(',')
+        .map(|s| s.trim().parse())
+        .collect::<Result<Vec<usize>, _>>()?;
+    let seed = values[1].as_ref().map(|v| v.parse()).transpose()?.unwrap_or(1);
+    let args = parse_args(&rest, Options { iterations: 3, warmup: 0 }, Some("15"))?;
+    if args.days.iter().any(|d| d.num != 15) {
+        return Err("Only the combat of day 15 can be benchmarked".into());
+    }
+    if sizes.iter().any(|&s| s < 3) {
+        return Err("--sizes must be at least 3".into());
+    }
+
+    let rules = Rules::elves_and_goblins();
+    let mut maps = vec![("input".to_string(), Map::parse(&args.days[0].read_input(&args.source)?, &rules)?)];
+    for size in sizes {
+        maps.push((format!("{0}x{0}", size), Map::parse(&combat::synthetic_map(size, seed), &rules)?));
+    }
+
+    println!("Map        Units  Rounds  Flood fills ms  Single BFS ms  Speedup");
+    for (name, map) in maps.iter() {
+        let measure = |movement| {
+            let play = || CombatSim::new(map, rules.clone()).with_movement(movement).run();
+            for _ in 0..args.opts.warmup {
+                play();
+            }
+            let (outcomes, samples): (Vec<_>, Vec<u64>) = (0..args.opts.iterations).map(|_| bench::time(play)).unzip();
+            (outcomes[0].clone(), Stats::from_samples(&samples).median_us / 1000.0)
+        };
+        let (flood_outcome, flood_ms) = measure(Movement::FloodFills);
+        let (bfs_outcome, bfs_ms) = measure(Movement::SingleBfs);
+        if flood_outcome != bfs_outcome {
+            return Err(format!("Combats on {} differ: {:?} with flood fills, {:?} with a single BFS", name, flood_outcome, bfs_outcome).into());
+        }
+        println!("{:<9}  {:5}  {:6}  {:14.2}  {:13.2}  {:6.1}x", name, map.units().len(), bfs_outcome.rounds, flood_ms, bfs_ms, flood_ms / bfs_ms);
+    }
+    Ok(())
+}
+
+fn record_combat(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut rest = args.to_vec();
+    let values = take_options(&mut rest, &["--output|-o", "--elf-attack"])?;
+    let path = values[0].as_ref().ok_or("No replay file given, use --output <path>")?;
+    let args = parse_args(&rest, Options::once(), Some("15"))?;
+    if args.days.iter().any(|d| d.num != 15) {
+        return Err("Only the combat of day 15 can be recorded".into());
+    }
+    let mut rules = Rules::elves_and_goblins();
+    if let Some(attack) = &values[1] {
+        rules = rules.with_attack(ELF, attack.parse()?);
+    }
+
+    let map = Map::parse(&args.days[0].read_input(&args.source)?, &rules)?;
+    let replay = Replay::record(&map, rules);
+    fs::write(path, replay.to_text())?;
+    // The combat ends during a round that isn't counted as full
+    let partial = if replay.last_round() > replay.outcome.rounds { " and a partial one" } else { "" };
+    println!("Recorded {} full rounds{} to {}, outcome {}", replay.outcome.rounds, partial, path, replay.outcome.score());
+    Ok(())
+}
+
+fn play_combat(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut rest = args.to_vec();
+    let given = rest.len();
+    rest.retain(|a| a != "--no-colour");
+    let path = match rest.as_slice() {
+        [path] => path,
+        _ => return Err("Expected a single replay file".into())
+    };
+    let replay = Replay::parse(&fs::read_to_string(path)?)?;
+
+    // Lines are read on their own thread so that playing can go on until one is entered
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let sent = line.ok().map(|l| tx.send(l).is_ok());
+            if sent != Some(true) {
+                break;
+            }
+        }
+    });
+    Player::new(replay, rest.len() == given).run(&rx, &mut io::stdout(), true)?;
+    Ok(())
+}
+
+fn verify(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let args = parse_args(args, Options::once(), Some("all"))?;
+    let path = args.answers.unwrap_or_else(Answers::default_path);
+    let mut answers = if args.record && !path.exists() {
+        Answers::default()
+    } else {
+        Answers::load(&path)?
+    };
+
+    let mut failures = 0;
+    let mut recorded = 0;
+    println!("Day  Part  Result       Parse µs     Solve µs");
+    for day in args.days {
+        let b = match bench::bench_day(day, &args.source, &args.parts, &args.opts) {
+            Ok(b) => b,
+            Err(e) => {
+                println!("{:3}     -  ERROR    {}", day.num, e);
+                failures += 1;
+                continue;
+            }
+        };
+        let median = |stage| b.timing(stage).map(|t| t.stats().median_us).unwrap_or(0.0);
+
+        for (part, answer) in b.answers.iter() {
+            let (result, note) = match answers.check(day.num, *part, answer) {
+                Outcome::Pass => ("pass", String::new()),
+                Outcome::Fail { expected } => {
+                    failures += 1;
+                    ("FAIL", format!("  expected {:?}, got {:?}", expected, answer))
+                },
+                Outcome::Missing if args.record => {
+                    recorded += 1;
+                    ("recorded", String::new())
+                },
+                Outcome::Missing => ("missing", String::new())
+            };
+            println!("{:3}  {:4}  {:<8}  {:11.1}  {:11.1}{}", day.num, part, result, median(Stage::Parse), median(Stage::of(*part)), note);
+
+            if result == "recorded" {
+                answers.insert(day.num, *part, answer.to_owned());
+            }
+        }
+    }
+
+    if recorded > 0 {
+        fs::write(&path, answers.to_toml())?;
+        println!("Recorded {} answers in {}", recorded, path.display());
+    }
+    if failures > 0 {
+        return Err(format!("{} answers did not verify", failures).into());
+    }
+    Ok(())
+}
+
+// Reads the ElfCode program of the single day given
+fn load_program(args: &[String]) -> Result<Program, Box<dyn Error>> {
+    let args = parse_args(args, Options::once(), None)?;
+    if args.days.len() != 1 {
+        return Err("Expected a single day with an ElfCode program".into());
+    }
+    Ok(Program::parse(&args.days[0].read_input(&args.source)?)?)
+}
+
+fn debug(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let program = load_program(args)?;
+    let device = Device::for_program(&program);
+
+    println!("ElfCode debugger, type help for the commands");
+    let stdin = io::stdin();
+    Debugger::new(program, device).repl(stdin.lock(), &mut io::stdout(), true)?;
+    Ok(())
+}
+
+fn decompile(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let program = load_program(args)?;
+    print!("{}", decompile::decompile(&program));
+    Ok(())
+}
+
+// Prints the diagnostics, failing if there are errors among them
+fn report(diags: &[Diagnostic]) -> Result<(), Box<dyn Error>> {
+    for d in diags {
+        println!("{}", d);
+    }
+    match diags.iter().filter(|d| d.severity == Severity::Error).count() {
+        0 => Ok(()),
+        n => Err(format!("{} errors", n).into())
+    }
+}
+
+fn lint(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let args = parse_args(args, Options::once(), None)?;
+    if args.days.len() != 1 {
+        return Err("Expected a single day with an ElfCode program".into());
+    }
+    let diags = Assembler::default().lint(&args.days[0].read_input(&args.source)?);
+    if diags.is_empty() {
+        println!("No problems found");
+    }
+    report(&diags)
+}
+
+fn disasm(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let args = parse_args(args, Options::once(), Some("16"))?;
+    let input = days::find(16).unwrap().read_input(&args.source)?;
+    let (samples, _) = Day16::parse(&input)?;
+    let mapping = day16::solve_opcodes(&day16::candidates(&samples));
+    let mapping = match mapping {
+        Mapping::Complete(m) => m,
+        m => return Err(m.to_string().into())
+    };
+
+    let (offset, text) = day16::program_text(&input);
+    match Assembler::new(4).disassemble(text, &|n| mapping.get(&n).copied()) {
+        Ok(program) => {
+            print!("{}", program);
+            Ok(())
+        },
+        Err(diags) => {
+            let diags: Vec<Diagnostic> = diags.into_iter().map(|d| Diagnostic { line: d.line + offset, ..d }).collect();
+            report(&diags)
+        }
+    }
+}
+
+fn exec_program<W: Register>(program: &Program, overflow: Overflow, reg0: u32, limit: u64) -> Result<(), Box<dyn Error>> {
+    let mut device: Device<W> = Device::for_program(program).with_overflow(overflow);
+    device.registers[0] = W::from_u32(reg0);
+    let mut steps = 0;
+    while steps < limit && device.step(program)? {
+        steps += 1;
+    }
+    let registers: Vec<String> = device.registers.iter().map(|r| r.to_string()).collect();
+    let end = if device.halted(program) { "halted" } else { "stopped at the step limit" };
+    println!("Executed {} steps, {}", steps, end);
+    println!("Registers: {}", registers.join(" "));
+    Ok(())
+}
+
+fn exec(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut rest = args.to_vec();
+    let values = take_options(&mut rest, &["--width", "--overflow", "--reg0", "--limit"])?;
+    let overflow = values[1].as_ref().map(|v| v.parse()).transpose()?.unwrap_or(Overflow::Wrap);
+    let reg0 = values[2].as_ref().map(|v| v.parse()).transpose()?.unwrap_or(0);
+    let limit = values[3].as_ref().map(|v| v.parse()).transpose()?.unwrap_or(10_000_000);
+
+    let program = load_program(&rest)?;
+    match values[0].as_deref().unwrap_or("u64") {
+        "u32" => exec_program::<u32>(&program, overflow, reg0, limit),
+        "u64" => exec_program::<u64>(&program, overflow, reg0, limit),
+        "i64" => exec_program::<i64>(&program, overflow, reg0, limit),
+        w => Err(format!("Invalid register width {}, expected u32, u64 or i64", w).into())
+    }
+}
+
+// Removes the options, given as `--name|-n`, and their values from args, returning the values
+fn take_options(args: &mut Vec<String>, names: &[&str]) -> Result<Vec<Option<String>>, Box<dyn Error>> {
+    let mut values = vec![None; names.len()];
+    let mut rest = vec![];
+    let mut iter = args.drain(..);
+    while let Some(arg) = iter.next() {
+        match names.iter().position(|n| n.split('|').any(|n| n == arg)) {
+            Some(i) => values[i] = Some(iter.next().ok_or_else(|| format!("Missing value for {}", arg))?),
+            None => rest.push(arg)
+        }
+    }
+    drop(iter);
+    *args = rest;
+    Ok(values)
+}
+
+fn trace_record(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut rest = args.to_vec();
+    let values = take_options(&mut rest, &["--output|-o", "--reg0", "--limit"])?;
+    let path = values[0].as_ref().ok_or("No trace file given, use --output <path>")?;
+    let reg0 = values[1].as_ref().map(|v| v.parse()).transpose()?.unwrap_or(0);
+    let limit = values[2].as_ref().map(|v| v.parse()).transpose()?.unwrap_or(10_000_000);
+
+    let program = load_program(&rest)?;
+    let mut device = Device::for_program(&program);
+    device.registers[0] = reg0;
+    let steps = trace::trace(&program, &mut device, BufWriter::new(File::create(path)?), Some(limit))?;
+    let end = if device.halted(&program) { "halted" } else { "stopped at the step limit" };
+    println!("Traced {} steps to {}, {}", steps, path, end);
+    Ok(())
+}
+
+fn trace_show(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut rest = args.to_vec();
+    let values = take_options(&mut rest, &["--at", "--changed"])?;
+    let mut filters = vec![];
+    if let Some(addrs) = &values[0] {
+        let addrs = addrs.split(',').map(|a| a.trim().parse()).collect::<Result<BTreeSet<usize>, _>>()?;
+        filters.push(Filter::Addresses(addrs));
+    }
+    if let Some(reg) = &values[1] {
+        filters.push(Filter::Changed(parse_register(reg)?));
+    }
+    let path = match rest.as_slice() {
+        [path] => path,
+        _ => return Err("Expected a single trace file".into())
+    };
+
+    let reader = TraceReader::new(BufReader::new(File::open(path)?))?;
+    let ip_reg = reader.ip_reg;
+    for step in reader {
+        let step = step?;
+        if filters.iter().all(|f| f.matches(&step)) {
+            println!("{}", trace::describe(&step, ip_reg));
+        }
+    }
+    Ok(())
+}
+
+fn trace_diff(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut rest = args.to_vec();
+    let values = take_options(&mut rest, &["--ignore"])?;
+    let ignore = match &values[0] {
+        Some(regs) => regs.split(',').map(|r| parse_register(r.trim())).collect::<Result<Vec<usize>, _>>()?,
+        None => vec![]
+    };
+    let open = |path| -> Result<_, Box<dyn Error>> { Ok(TraceReader::new(BufReader::new(File::open(path)?))?) };
+    match rest.as_slice() {
+        [left, right] => {
+            match trace::diff(open(left)?, open(right)?, &ignore)? {
+                Some(d) => println!("{}", d),
+                None => println!("Traces are identical")
+            }
+            Ok(())
+        },
+        _ => Err("Expected two trace files".into())
+    }
+}
+
+// Takes the logging options from anywhere in args, they apply to all commands
+fn configure_logging(args: &mut Vec<String>) -> Result<(), Box<dyn Error>> {
+    let mut spec: Vec<String> = env::var(log::LOG_VAR).into_iter().collect();
+    let given = args.len();
+    args.retain(|a| match a.as_str() {
+        "-v" => { spec.push("debug".to_string()); false },
+        "-vv" => { spec.push("trace".to_string()); false },
+        _ => true
+    });
+    let values = take_options(args, &["--trace", "--log"])?;
+    if let Some(targets) = &values[0] {
+        spec.extend(targets.split(',').map(|t| format!("{}=trace", t.trim())));
+    }
+    spec.extend(values[1].iter().cloned());
+
+    if args.len() < given {
+        log::configure(log::Config::parse(&spec.join(","))?);
+    }
+    Ok(())
+}
+
+fn list() {
+    for day in days::DAYS.iter() {
+        println!("{:2}  {}", day.num, day.title);
+    }
+}
+
+fn main() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    if let Err(e) = configure_logging(&mut args) {
+        eprintln!("Error: {}", e);
+        process::exit(2);
+    }
+
+    let res = match args.first().map(|s| s.as_str()) {
+        Some("run") => run(&args[1..]),
+        Some("bench") => bench(&args[1..]),
+        Some("bench-combat") => bench_combat(&args[1..]),
+        Some("record-combat") => record_combat(&args[1..]),
+        Some("play-combat") => play_combat(&args[1..]),
+        Some("verify") => verify(&args[1..]),
+        Some("debug") => debug(&args[1..]),
+        Some("decompile") => decompile(&args[1..]),
+        Some("lint") => lint(&args[1..]),
+        Some("disasm") => disasm(&args[1..]),
+        Some("exec") => exec(&args[1..]),
+        Some("trace") => trace_record(&args[1..]),
+        Some("trace-show") => trace_show(&args[1..]),
+        Some("trace-diff") => trace_diff(&args[1..]),
+        Some("list") => { list(); Ok(()) },
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(e) = res {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,141 @@
+// Cycle detection for iterated simulations, to find the state after a huge number of steps
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+// The state after start + length steps is the same as the one after start steps
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Cycle {
+    pub start: usize,
+    pub length: usize
+}
+
+impl Cycle {
+    // Number of steps, before the end of the first cycle, giving the same state as n steps
+    pub fn equivalent(&self, n: usize) -> usize {
+        if n < self.start { n } else { self.start + (n - self.start) % self.length }
+    }
+}
+
+// Steps from the initial state until the key of a state repeats or n steps have been taken,
+// returning the states and the cycle if one was found
+fn run<S, K, F, G>(initial: S, mut step: F, mut key: G, n: Option<usize>) -> (Vec<S>, Option<Cycle>)
+where K: Hash + Eq, F: FnMut(&S) -> S, G: FnMut(&S) -> K {
+    let mut seen: HashMap<K, usize> = HashMap::new();
+    let mut states = vec![initial];
+    loop {
+        let i = states.len() - 1;
+        match seen.entry(key(&states[i])) {
+            Entry::Occupied(e) => return (states, Some(Cycle { start: *e.get(), length: i - e.get() })),
+            Entry::Vacant(e) => { e.insert(i); }
+        }
+        if n == Some(i) {
+            return (states, None);
+        }
+        let next = step(&states[i]);
+        states.push(next);
+    }
+}
+
+// Finds the first repeated state by remembering all states, never returns if none repeats
+pub fn find_cycle<S, F>(initial: S, step: F) -> Cycle
+where S: Hash + Eq + Clone, F: FnMut(&S) -> S {
+    run(initial, step, |s| s.clone(), None).1.unwrap()
+}
+
+// Finds the first repeated state with Brent's algorithm, only keeping two states but stepping
+// about three times as much as find_cycle
+pub fn find_cycle_brent<S, F>(initial: S, mut step: F) -> Cycle
+where S: PartialEq + Clone, F: FnMut(&S) -> S {
+    // Length of the cycle, searching with windows of doubling size
+    let mut power = 1;
+    let mut length = 1;
+    let mut tortoise = initial.clone();
+    let mut hare = step(&initial);
+    while tortoise != hare {
+        if power == length {
+            tortoise = hare.clone();
+            power *= 2;
+            length = 0;
+        }
+        hare = step(&hare);
+        length += 1;
+    }
+
+    // Start of the cycle, where two states length steps apart first meet
+    let mut tortoise = initial.clone();
+    let mut hare = initial;
+    for _ in 0..length {
+        hare = step(&hare);
+    }
+    let mut start = 0;
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        start += 1;
+    }
+    Cycle { start, length }
+}
+
+// State after n steps, skipping the repetitions of the first cycle found, and the cycle if one
+// was found within the n steps
+pub fn extrapolate<S, F>(initial: S, step: F, n: usize) -> (S, Option<Cycle>)
+where S: Hash + Eq + Clone, F: FnMut(&S) -> S {
+    let (mut states, cycle) = run(initial, step, |s| s.clone(), Some(n));
+    let i = cycle.map(|c| c.equivalent(n)).unwrap_or(n);
+    (states.swap_remove(i), cycle)
+}
+
+// State after n steps for simulations repeating up to a translation, like a pattern moving
+// one step to the right per step. Normalize gives a key of the state independent of the
+// translation, and its offset. Returns a state of the first cycle together with how much
+// further its offset moves over the skipped repetitions
+pub fn extrapolate_shifted<S, K, F, G>(initial: S, step: F, mut normalize: G, n: usize) -> (S, i64)
+where K: Hash + Eq, F: FnMut(&S) -> S, G: FnMut(&S) -> (K, i64) {
+    let (mut states, cycle) = run(initial, step, |s| normalize(s).0, Some(n));
+    match cycle {
+        Some(c) => {
+            let shift = normalize(&states[c.start + c.length]).1 - normalize(&states[c.start]).1;
+            let repetitions = ((n - c.start) / c.length) as i64;
+            (states.swap_remove(c.equivalent(n)), shift * repetitions)
+        },
+        None => (states.swap_remove(n), 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 0, 1, 2, 3, 4, 5, 6, 7, 3, 4, ...
+    fn step(&s: &u64) -> u64 {
+        if s == 7 { 3 } else { s + 1 }
+    }
+
+    #[test]
+    fn test_find_cycle() {
+        assert_eq!(find_cycle(0, step), Cycle { start: 3, length: 5 });
+        assert_eq!(find_cycle_brent(0, step), Cycle { start: 3, length: 5 });
+        assert_eq!(find_cycle_brent(5, step), Cycle { start: 0, length: 5 });
+        assert_eq!(find_cycle(1u64, |&s| s * 7 % 10), find_cycle_brent(1u64, |&s| s * 7 % 10));
+    }
+
+    #[test]
+    fn test_extrapolate() {
+        assert_eq!(extrapolate(0, step, 2), (2, None));
+        assert_eq!(extrapolate(0, step, 8), (3, Some(Cycle { start: 3, length: 5 })));
+        assert_eq!(extrapolate(0, step, 1_000_000_000_000).0, 3 + (1_000_000_000_000 - 3) % 5);
+        let c = Cycle { start: 3, length: 5 };
+        assert_eq!((c.equivalent(2), c.equivalent(12)), (2, 7));
+    }
+
+    #[test]
+    fn test_extrapolate_shifted() {
+        // A pattern that settles into moving 2 steps right per step, (position, phase)
+        let step = |&(p, ph): &(i64, u8)| if p < 4 { (p + 1, 0) } else { (p + 2, (ph + 1) % 3) };
+        let normalize = |&(p, ph): &(i64, u8)| ((if p < 4 { p as u8 + 10 } else { ph }), p);
+        let (s, shift) = extrapolate_shifted((0, 0), step, normalize, 1000);
+        assert_eq!(s.0 + shift, 4 + 2 * (1000 - 4));
+        assert_eq!(extrapolate_shifted((0, 0), step, normalize, 2), ((2, 0), 0));
+    }
+}
@@ -1,18 +1,48 @@
-extern crate utils;
-
 use std::collections::HashSet;
 use std::error::Error;
-use std::io::{self, BufReader};
-use std::io::prelude::*;
-use std::fs::File;
-use utils::*;
+use std::fmt;
+use {parse_lines, ParseError, Solution};
+
+// Part 2 gives up after as many passes over the changes as there are changes
+#[derive(Debug, PartialEq)]
+pub struct NoRepeatedFreq {
+    passes: usize
+}
+
+impl fmt::Display for NoRepeatedFreq {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "No frequency repeated within {} passes over the changes", self.passes)
+    }
+}
+
+impl Error for NoRepeatedFreq {}
+
+
+pub struct Day01;
+
+impl Solution for Day01 {
+    type Input = Vec<i32>;
+    type Answer1 = i32;
+    type Answer2 = Result<i32, NoRepeatedFreq>;
+
+    fn parse(input: &str) -> Result<Vec<i32>, ParseError> {
+        let freq_changes = parse_lines(input, |l| l.parse::<i32>().map_err(|_| ParseError::expected("a frequency change like +3", l)))?;
+        if freq_changes.is_empty() {
+            return Err(ParseError::new("No frequency changes"));
+        }
+        Ok(freq_changes)
+    }
 
+    fn part1(freq_changes: &Vec<i32>) -> i32 {
+        freq_changes.iter().sum()
+    }
 
-fn part1(freq_changes: Vec<i32>) -> i32 {
-    freq_changes.iter().sum()
+    fn part2(freq_changes: &Vec<i32>) -> Result<i32, NoRepeatedFreq> {
+        first_repeated_freq(freq_changes).ok_or(NoRepeatedFreq { passes: freq_changes.len() })
+    }
 }
 
-fn part2(freq_changes: Vec<i32>) -> Option<i32> {
+fn first_repeated_freq(freq_changes: &Vec<i32>) -> Option<i32> {
     let mut freq = 0;
     let mut reached_freqs = HashSet::new();
     reached_freqs.insert(freq);
@@ -29,48 +59,36 @@ fn part2(freq_changes: Vec<i32>) -> Option<i32> {
     None
 }
 
-fn main() -> Result<(), Box<Error>> {
-    measure_exec(|| {
-        let res_freq = part1(input()?);
-        println!("Part1 result frequency: {:?}", res_freq);
-        Ok(())
-    })?;
-    measure_exec(|| {
-        let freq_repeat = part2(input()?);
-        println!("Part2 first repeated frequency: {:?}", freq_repeat.unwrap());
-        Ok(())
-    })?;
-    Ok(())
-}
-
-fn input() -> io::Result<Vec<i32>> {
-    let f = File::open("src/day01/input")?;
-    let f = BufReader::new(f);
-    Ok(f.lines().map(|l| l.unwrap().parse::<i32>().unwrap()).collect())
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
     fn to_input(s: &str) -> Vec<i32> {
-        s.split(',').map(|s| s.trim().to_string().parse::<i32>().unwrap()).collect()
+        Day01::parse(&s.replace(',', "\n")).unwrap()
     }
 
     #[test]
     fn test_part1() {
-        assert_eq!(part1(to_input("+1, -2, +3, +1")), 3);
-        assert_eq!(part1(to_input("+1, +1, +1")), 3);
-        assert_eq!(part1(to_input("+1, +1, -2")), 0);
-        assert_eq!(part1(to_input("-1, -2, -3")), -6);
+        assert_eq!(Day01::part1(&to_input("+1, -2, +3, +1")), 3);
+        assert_eq!(Day01::part1(&to_input("+1, +1, +1")), 3);
+        assert_eq!(Day01::part1(&to_input("+1, +1, -2")), 0);
+        assert_eq!(Day01::part1(&to_input("-1, -2, -3")), -6);
     }
 
     #[test]
     fn test_part2() {
-        assert_eq!(part2(to_input("+1, -2, +3, +1")), Some(2));
-        assert_eq!(part2(to_input("+1, -1")), Some(0));
-        assert_eq!(part2(to_input("+3, +3, +4, -2, -4")), Some(10));
-        assert_eq!(part2(to_input("-6, +3, +8, +5, -6")), Some(5));
-        assert_eq!(part2(to_input("+7, +7, -2, -7, -4")), Some(14));
+        assert_eq!(first_repeated_freq(&to_input("+1, -2, +3, +1")), Some(2));
+        assert_eq!(first_repeated_freq(&to_input("+1, -1")), Some(0));
+        assert_eq!(first_repeated_freq(&to_input("+3, +3, +4, -2, -4")), Some(10));
+        assert_eq!(first_repeated_freq(&to_input("-6, +3, +8, +5, -6")), Some(5));
+        assert_eq!(first_repeated_freq(&to_input("+7, +7, -2, -7, -4")), Some(14));
+        assert_eq!(Day01::part2(&to_input("+1, -1")), Ok(0));
+        assert_eq!(Day01::part2(&to_input("+1, +1")).unwrap_err().to_string(), "No frequency repeated within 2 passes over the changes");
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert_eq!(Day01::parse("\n").err(), Some(ParseError::new("No frequency changes")));
+        assert_eq!(Day01::parse("+1\n2x").unwrap_err().line(), Some(2));
     }
 }
\ No newline at end of file
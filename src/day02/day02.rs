@@ -1,18 +1,59 @@
-extern crate utils;
-
 use std::collections::HashMap;
 use std::error::Error;
-use std::io::{self, BufReader};
-use std::io::prelude::*;
-use std::fs::File;
-use utils::*;
+use std::fmt;
+use {parse_lines, ParseError, Solution};
+
+#[derive(Debug, PartialEq)]
+pub struct NoCloseIds;
+
+impl fmt::Display for NoCloseIds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "No two box ids differ by exactly one character")
+    }
+}
+
+impl Error for NoCloseIds {}
+
+
+pub struct Day02;
+
+impl Solution for Day02 {
+    type Input = Vec<String>;
+    type Answer1 = i32;
+    type Answer2 = Result<String, NoCloseIds>;
+
+    fn parse(input: &str) -> Result<Vec<String>, ParseError> {
+        let box_ids = parse_lines(input, |l| if l.chars().all(|c| c.is_ascii_lowercase()) {
+            Ok(l.to_string())
+        } else {
+            Err(ParseError::expected("a box id of lowercase letters", l))
+        })?;
+        if box_ids.len() < 2 {
+            return Err(ParseError::new("Fewer than two box ids"));
+        }
+        Ok(box_ids)
+    }
+
+    fn part1(box_ids: &Vec<String>) -> i32 {
+        let (c2, c3) = box_ids.iter()
+            .map(count_2or3_letters)
+            .fold((0, 0), |(a2, a3), (c2, c3)| (a2 + c2, a3 + c3));
+        c2 * c3
+    }
 
+    fn part2(box_ids: &Vec<String>) -> Result<String, NoCloseIds> {
+        let candidates: Vec<String> = box_ids.iter()
+            .flat_map(|id| find_differs_by_one((*id).to_owned(), box_ids))
+            .collect();
+        if candidates.len() < 2 {
+            return Err(NoCloseIds);
+        }
 
-fn part1(box_ids: Vec<String>) -> i32 {
-    let (c2, c3) = box_ids.iter()
-        .map(count_2or3_letters)
-        .fold((0, 0), |(a2, a3), (c2, c3)| (a2 + c2, a3 + c3));
-    c2 * c3
+        Ok(candidates[0].chars().zip(candidates[1].chars())
+            .filter(|(c1, c2)| c1 == c2)
+            .map(|(ch, _)| ch)
+            .collect())
+    }
 }
 
 fn count_2or3_letters(s: &String) -> (i32, i32) {
@@ -27,17 +68,6 @@ fn count_2or3_letters(s: &String) -> (i32, i32) {
         })
 }
 
-fn part2(box_ids: &Vec<String>) -> String {
-    let candidates: Vec<String> = box_ids.iter()
-        .flat_map(|id| find_differs_by_one((*id).to_owned(), box_ids))
-        .collect();
-
-    candidates[0].chars().zip(candidates[1].chars())
-        .filter(|(c1, c2)| c1 == c2)
-        .map(|(ch, _)| ch)
-        .collect()
-}
-
 fn find_differs_by_one(id: String, box_ids: &Vec<String>) -> Option<String> {
     box_ids.iter()
         .filter(|bid| {
@@ -47,32 +77,12 @@ fn find_differs_by_one(id: String, box_ids: &Vec<String>) -> Option<String> {
         .next()
 }
 
-fn main() -> Result<(), Box<Error>> {
-    measure_exec(|| {
-        let checksum = part1(input()?);
-        println!("Part1 checksum: {}", checksum);
-        Ok(())
-    })?;
-    measure_exec(|| {
-        let common_letters = part2(&input()?);
-        println!("Part2 common letters: {}", common_letters);
-        Ok(())
-    })?;
-    Ok(())
-}
-
-fn input() -> io::Result<Vec<String>> {
-    let f = File::open("src/day02/input")?;
-    let f = BufReader::new(f);
-    Ok(f.lines().map(|l| l.unwrap()).collect())
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
     fn to_input(s: &str) -> Vec<String> {
-        s.split(',').map(|s| s.trim().to_string()).collect()
+        Day02::parse(&s.replace(',', "\n")).unwrap()
     }
 
     #[test]
@@ -88,7 +98,7 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        assert_eq!(part1(to_input("abcdef, bababc, abbcde, abcccd, aabcdd, abcdee, ababab")), 12);
+        assert_eq!(Day02::part1(&to_input("abcdef, bababc, abbcde, abcccd, aabcdd, abcdee, ababab")), 12);
     }
 
     #[test]
@@ -99,6 +109,14 @@ mod tests {
 
     #[test]
     fn test_part2() {
-        assert_eq!(part2(&to_input("abcde, fghij, klmno, pqrst, fguij, axcye, wvxyz")), "fgij".to_string());
+        assert_eq!(Day02::part2(&to_input("abcde, fghij, klmno, pqrst, fguij, axcye, wvxyz")), Ok("fgij".to_string()));
+        assert_eq!(Day02::part2(&to_input("abcde, abdce")).unwrap_err().to_string(), "No two box ids differ by exactly one character");
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert_eq!(Day02::parse("").err(), Some(ParseError::new("Fewer than two box ids")));
+        assert_eq!(Day02::parse("abcde").err(), Some(ParseError::new("Fewer than two box ids")));
+        assert!(Day02::parse("abcde\nABCDE").is_err());
     }
 }
\ No newline at end of file
@@ -1,17 +1,8 @@
-extern crate regex;
-#[macro_use] extern crate lazy_static;
-extern crate utils;
-
 use std::cmp;
-use std::collections::HashSet;
-use std::error::Error;
-use std::num::ParseIntError;
-use std::io::{self, BufReader};
-use std::io::prelude::*;
-use std::fs::File;
 use std::str::FromStr;
 use regex::Regex;
-use utils::*;
+use grid::Grid;
+use {parse_lines, ParseError, Solution};
 
 
 #[derive(PartialEq, Debug, Clone, Copy)]
@@ -34,13 +25,14 @@ pub struct Claim {
 }
 
 impl FromStr for Claim {
-    type Err = ParseIntError;
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         lazy_static! {
-            static ref RE: Regex = Regex::new(r"^\#(\d*) @ (\d*),(\d*): (\d*)x(\d*)$").unwrap();
+            static ref RE: Regex = Regex::new(r"^\#(\d+) @ (\d+),(\d+): (\d+)x(\d+)$").unwrap();
         }
-        let caps = RE.captures(s).unwrap();
+        let caps = RE.captures(s)
+            .ok_or_else(|| ParseError::expected("a claim like '#1 @ 1,3: 4x4'", s))?;
         let get = |idx| caps.get(idx).unwrap().as_str().parse();
 
         Ok(Claim {
@@ -60,20 +52,17 @@ impl Claim {
     }
 }
 
-fn parse_claims(input: &Vec<String>) -> Result<Vec<Claim>, ParseIntError> {
-    input.iter().map(|i| i.parse()).collect()
-}
-
-fn lay_claims(claims: &Vec<Claim>) -> Vec<Vec<i32>> {
+// Number of claims covering every square inch
+fn lay_claims(claims: &Vec<Claim>) -> Grid<i32> {
     let (max_x, max_y) = claims.iter()
         .fold((0, 0), |(mx, my), c| (cmp::max(mx, c.max_x()), cmp::max(my, c.max_y())));
 
-    let mut grid: Vec<Vec<i32>> = vec![vec![]; (max_x * max_y) as usize];
+    let mut grid = Grid::new(max_x as usize, max_y as usize, 0);
 
     for c in claims {
         for y in 0..c.size.h {
             for x in 0..c.size.w {
-                grid[(max_x * (c.pos.y + y) + c.pos.x + x) as usize].push(c.id);
+                grid[((c.pos.x + x) as usize, (c.pos.y + y) as usize)] += 1;
             }
         }
     }
@@ -81,55 +70,46 @@ fn lay_claims(claims: &Vec<Claim>) -> Vec<Vec<i32>> {
     grid
 }
 
-fn part1(claims: &Vec<Claim>) -> i32 {
-    let grid = lay_claims(claims);
-    grid.iter().filter(|g| g.len() > 1).count() as i32
+pub struct Day03;
+
+impl Solution for Day03 {
+    type Input = Vec<Claim>;
+    type Answer1 = i32;
+    type Answer2 = String;
+
+    fn parse(input: &str) -> Result<Vec<Claim>, ParseError> {
+        parse_lines(input, |l| l.parse())
+    }
+
+    fn part1(claims: &Vec<Claim>) -> i32 {
+        let grid = lay_claims(claims);
+        grid.iter().filter(|&&n| n > 1).count() as i32
+    }
+
+    fn part2(claims: &Vec<Claim>) -> String {
+        let ids: Vec<String> = non_overlapping_ids(claims).iter().map(|id| id.to_string()).collect();
+        ids.join(",")
+    }
 }
 
-fn part2(claims: &Vec<Claim>) -> Vec<i32> {
-    let claim_ids: HashSet<i32> = claims.iter().map(|c| c.id).collect();
+fn non_overlapping_ids(claims: &Vec<Claim>) -> Vec<i32> {
     let grid = lay_claims(claims);
-    grid.iter()
-        .fold(claim_ids, |mut claim_ids, ids| {
-            if ids.len() > 1 {
-                for id in ids {
-                    claim_ids.remove(&id);
-                }
-            }
-            claim_ids
+    claims.iter()
+        .filter(|c| {
+            grid.view(c.pos.x as usize, c.pos.y as usize, c.size.w as usize, c.size.h as usize)
+                .iter()
+                .all(|&n| n == 1)
         })
-        .iter()
-        .cloned()
+        .map(|c| c.id)
         .collect()
 }
 
-fn main() -> Result<(), Box<Error>> {
-    let claims = parse_claims(&input()?)?;
-    measure_exec(|| {
-        let overlapp_sq_inch = part1(&claims);
-        println!("Part1 overlapping square inches: {}", overlapp_sq_inch);
-        Ok(())
-    })?;
-    measure_exec(|| {
-        let non_overlapping_claim = part2(&claims);
-        println!("Part2 non overlapping claim id: {:?}", non_overlapping_claim);
-        Ok(())
-    })?;
-    Ok(())
-}
-
-fn input() -> io::Result<Vec<String>> {
-    let f = File::open("src/day03/input")?;
-    let f = BufReader::new(f);
-    Ok(f.lines().map(|l| l.unwrap()).collect())
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    fn as_input(s: &str) -> Vec<String> {
-        s.split('\n').map(|s| s.trim().into()).collect()
+    fn as_input(s: &str) -> Vec<Claim> {
+        Day03::parse(s).unwrap()
     }
 
     #[test]
@@ -140,13 +120,20 @@ mod tests {
         assert_eq!(claim.max_y(), 6);
     }
 
+    #[test]
+    fn test_parse_error() {
+        let e = Day03::parse("#1 @ 1,3: 4x4\n #2 @ 3,x: 4x4").unwrap_err();
+        assert_eq!(e.to_string(), "Parse error on line 2: expected a claim like '#1 @ 1,3: 4x4', found '#2 @ 3,x: 4x4'");
+    }
+
     #[test]
     fn test_part1() {
-        assert_eq!(part1(&parse_claims(&as_input("#1 @ 1,3: 4x4\n #2 @ 3,1: 4x4\n #3 @ 5,5: 2x2")).unwrap()), 4);
+        assert_eq!(Day03::part1(&as_input("#1 @ 1,3: 4x4\n #2 @ 3,1: 4x4\n #3 @ 5,5: 2x2")), 4);
     }
 
     #[test]
     fn test_part2() {
-        assert_eq!(part2(&parse_claims(&as_input("#1 @ 1,3: 4x4\n #2 @ 3,1: 4x4\n #3 @ 5,5: 2x2")).unwrap()), vec![3]);
+        assert_eq!(non_overlapping_ids(&as_input("#1 @ 1,3: 4x4\n #2 @ 3,1: 4x4\n #3 @ 5,5: 2x2")), vec![3]);
+        assert_eq!(Day03::part2(&as_input("#1 @ 1,3: 4x4\n #2 @ 3,1: 4x4\n #3 @ 5,5: 2x2")), "3");
     }
 }
\ No newline at end of file
@@ -1,14 +1,6 @@
-extern crate regex;
-#[macro_use] extern crate lazy_static;
-extern crate utils;
-
 use std::collections::HashMap;
-use std::error::Error;
-use std::io::{self, BufReader};
-use std::io::prelude::*;
-use std::fs::File;
 use regex::Regex;
-use utils::*;
+use {parse_lines, ParseError, Solution};
 
 
 #[derive(PartialEq, Debug, Clone)]
@@ -28,135 +20,131 @@ impl GuardRecord {
     }
 }
 
-fn get_input_parts(i: &String) -> (String, i32, &str) {
-    lazy_static! {
-        static ref RE: Regex = Regex::new(r"^\[1518-(.*) \d*:(\d*)\] (.*)$").unwrap();
-    }
-    let caps = RE.captures(i).unwrap();
-    let get = |idx| caps.get(idx).unwrap().as_str();
-
-    (get(1).to_string(), get(2).parse::<i32>().unwrap(), get(3))
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum Change {
+    BeginsShift(i32), FallsAsleep, WakesUp
 }
 
-fn parse_guard_records(input: &Vec<String>) -> Vec<GuardRecord> {
+// Date, minute and change of a record
+fn get_input_parts(i: &str) -> Result<(String, i32, Change), ParseError> {
     lazy_static! {
-        static ref RE: Regex = Regex::new(r"^Guard \#(\d*) begins shift$").unwrap();
+        static ref RE: Regex = Regex::new(r"^\[1518-(.*) \d+:(\d+)\] (.*)$").unwrap();
+        static ref RE_GUARD: Regex = Regex::new(r"^Guard \#(\d+) begins shift$").unwrap();
     }
+    let caps = RE.captures(i)
+        .ok_or_else(|| ParseError::expected("a record like '[1518-11-01 00:05] falls asleep'", i))?;
+    let get = |idx| caps.get(idx).unwrap().as_str();
 
-    let (records, _) = input.iter()
-        .fold((vec![], 0), |(mut records, mut last_min), i| {
-            let (date, minute, change) = get_input_parts(i);
+    let minute = get(2).parse::<i32>()?;
+    if minute >= 60 {
+        return Err(ParseError::expected("a minute below 60", get(2)));
+    }
+    let change = match get(3) {
+        "falls asleep" => Change::FallsAsleep,
+        "wakes up" => Change::WakesUp,
+        c => match RE_GUARD.captures(c) {
+            Some(gcaps) => Change::BeginsShift(gcaps.get(1).unwrap().as_str().parse()?),
+            None => return Err(ParseError::expected("'Guard #<id> begins shift', 'falls asleep' or 'wakes up'", c))
+        }
+    };
+    Ok((get(1).to_string(), minute, change))
+}
 
-            if RE.is_match(change) {
-                let caps = RE.captures(change).unwrap();
-                let id = caps.get(1).unwrap().as_str().parse::<i32>().unwrap();
+// Records in chronological order, with the record text for errors
+fn parse_guard_records(input: &[(String, (String, i32, Change))]) -> Result<Vec<GuardRecord>, ParseError> {
+    let mut records: Vec<GuardRecord> = vec![];
+    let mut last_min = 0;
+    for (text, (date, minute, change)) in input.iter().cloned() {
+        match change {
+            Change::BeginsShift(id) => {
                 records.push(GuardRecord { date: date, id: id, minues_slept: 0 });
                 last_min = 0;
-            } else {
-                match change {
-                    "falls asleep" => {
-                        last_min = minute;
-                    },
-                    "wakes up" => {
-                        let last_idx = records.len() - 1;
-                        if let Some(last_record) = records.get_mut(last_idx) {
-                            for i in last_min..minute {
-                                last_record.minues_slept = last_record.minues_slept | (1 << i);
-                            }
-                        }
-                    },
-                    _ => unreachable!()
+            },
+            Change::FallsAsleep => {
+                last_min = minute;
+            },
+            Change::WakesUp => {
+                let last_record = records.last_mut()
+                    .ok_or_else(|| ParseError::new("Wakes up before any guard began a shift").in_text(&text))?;
+                for i in last_min..minute {
+                    last_record.minues_slept = last_record.minues_slept | (1 << i);
                 }
-
             }
-            (records, last_min)
-        });
-
-    records
+        }
+    }
+    Ok(records)
 }
 
-fn part1(input: &Vec<String>) -> i32 {
-    let guard_records = parse_guard_records(input);
-
-    // Calculate which id has the most minutes asleep
-    let (mid, _) = guard_records.iter()
-        .fold(HashMap::new(), |mut acc: HashMap<i32, i32>, r| {
-            *acc.entry(r.id).or_insert(0) += r.count_minutes();
-            acc
-        })
-        .iter()
-        .fold((0, 0), |(lid, lc), (&id, &c)| {
-            if c > lc { (id, c) } else { (lid, lc) }
-        });
-
-    // Calculate which minute the given id has been asleep the most
-    let (mmin, _) = guard_records.iter()
-        .filter(|r| r.id == mid)
-        .fold([0; 60], |mut mcnt, r| {
-            for i in 0..60 {
-                mcnt[i as usize] += (r.minues_slept >> i) as i32 & 0x1;
-            }
-            mcnt
-        })
-        .iter()
-        .enumerate()
-        .fold((0 as i32, 0), |(lmin, lc), (min, &c)| {
-            if c > lc { (min as i32, c) } else { (lmin, lc) }
-        });
-
-    mid * mmin
-}
+pub struct Day04;
+
+impl Solution for Day04 {
+    type Input = Vec<GuardRecord>;
+    type Answer1 = i32;
+    type Answer2 = i32;
 
-fn part2(input: &Vec<String>) -> i32 {
-    let guard_records = parse_guard_records(input);
+    fn parse(input: &str) -> Result<Vec<GuardRecord>, ParseError> {
+        let mut lines = parse_lines(input, |l| Ok((l.to_string(), get_input_parts(l)?)))?;
+        lines.sort_by(|(a, _), (b, _)| a.cmp(b));
+        parse_guard_records(&lines)
+    }
 
-    // Calculate total times slept per minute per id
-    let mcnt_by_id = guard_records.iter()
-        .fold(HashMap::new(), |mut acc: HashMap<i32, [i32; 64]>, r| {
-            {
-                let mcnt = acc.entry(r.id).or_insert([0; 64]);
-                for i in 0..64 {
+    fn part1(guard_records: &Vec<GuardRecord>) -> i32 {
+        // Calculate which id has the most minutes asleep
+        let (mid, _) = guard_records.iter()
+            .fold(HashMap::new(), |mut acc: HashMap<i32, i32>, r| {
+                *acc.entry(r.id).or_insert(0) += r.count_minutes();
+                acc
+            })
+            .iter()
+            .fold((0, 0), |(lid, lc), (&id, &c)| {
+                if c > lc { (id, c) } else { (lid, lc) }
+            });
+
+        // Calculate which minute the given id has been asleep the most
+        let (mmin, _) = guard_records.iter()
+            .filter(|r| r.id == mid)
+            .fold([0; 60], |mut mcnt, r| {
+                for i in 0..60 {
                     mcnt[i as usize] += (r.minues_slept >> i) as i32 & 0x1;
                 }
-            }
-            acc
-        });
-
-    // Calculate which id has been most frequently asleep at a given minute
-    let (mid, _, mmin) = mcnt_by_id.iter()
-        .map(|(&id, mcnt)| {
-            (0..60).into_iter()
-                .fold((id, 0, 0), |(id, max, midx), i| {
-                    if mcnt[i] > max { (id, mcnt[i], i as i32) } else { (id, max, midx) }
-                })
-        })
-        .fold((0, 0, 0), |(lid, lc, li), (id, c, i)| {
-            if c > lc { (id, c, i) } else { (lid, lc, li) }
-        });
-
-    mid * mmin
-}
-
-fn main() -> Result<(), Box<Error>> {
-    measure_exec(|| {
-        let result = part1(&input()?);
-        println!("Part1 result: {}", result);
-        Ok(())
-    })?;
-    measure_exec(|| {
-        let result = part2(&input()?);
-        println!("Part2 result: {}", result);
-        Ok(())
-    })?;
-    Ok(())
-}
+                mcnt
+            })
+            .iter()
+            .enumerate()
+            .fold((0 as i32, 0), |(lmin, lc), (min, &c)| {
+                if c > lc { (min as i32, c) } else { (lmin, lc) }
+            });
+
+        mid * mmin
+    }
 
-fn input() -> io::Result<Vec<String>> {
-    let f = File::open("src/day04/input")?;
-    let f = BufReader::new(f);
-    let mut lines: Vec<String> = f.lines().map(|l| l.unwrap()).collect();
-    lines.sort();
-    Ok(lines)
+    fn part2(guard_records: &Vec<GuardRecord>) -> i32 {
+        // Calculate total times slept per minute per id
+        let mcnt_by_id = guard_records.iter()
+            .fold(HashMap::new(), |mut acc: HashMap<i32, [i32; 64]>, r| {
+                {
+                    let mcnt = acc.entry(r.id).or_insert([0; 64]);
+                    for i in 0..64 {
+                        mcnt[i as usize] += (r.minues_slept >> i) as i32 & 0x1;
+                    }
+                }
+                acc
+            });
+
+        // Calculate which id has been most frequently asleep at a given minute
+        let (mid, _, mmin) = mcnt_by_id.iter()
+            .map(|(&id, mcnt)| {
+                (0..60)
+                    .fold((id, 0, 0), |(id, max, midx), i| {
+                        if mcnt[i] > max { (id, mcnt[i], i as i32) } else { (id, max, midx) }
+                    })
+            })
+            .fold((0, 0, 0), |(lid, lc, li), (id, c, i)| {
+                if c > lc { (id, c, i) } else { (lid, lc, li) }
+            });
+
+        mid * mmin
+    }
 }
 
 #[cfg(test)]
@@ -182,17 +170,26 @@ mod tests {
         [1518-11-05 00:45] falls asleep
         [1518-11-05 00:55] wakes up";
 
-    fn as_input(s: &str) -> Vec<String> {
-        s.split('\n').map(|s| s.trim().into()).collect()
+    fn as_input(s: &str) -> Vec<GuardRecord> {
+        Day04::parse(s).unwrap()
     }
 
     #[test]
     fn test_part1() {
-        assert_eq!(part1(&as_input(INPUT)), 240);
+        assert_eq!(Day04::part1(&as_input(INPUT)), 240);
     }
 
     #[test]
     fn test_part2() {
-        assert_eq!(part2(&as_input(INPUT)), 4455);
+        assert_eq!(Day04::part2(&as_input(INPUT)), 4455);
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let e = Day04::parse("[1518-11-01 00:00] Guard #10 begins shift\n [1518-11-01 00:05] dozes off").unwrap_err();
+        assert_eq!((e.line(), e.text()), (Some(2), Some("dozes off")));
+        let e = Day04::parse("[1518-11-01 00:25] wakes up").unwrap_err();
+        assert_eq!(e.message(), "Wakes up before any guard began a shift");
+        assert!(Day04::parse("[1518-11-01 00:75] falls asleep").is_err());
     }
 }
\ No newline at end of file
@@ -1,20 +1,13 @@
-extern crate time;
-extern crate utils;
-
 use std::collections::BTreeSet;
 use std::collections::BTreeMap;
-use std::error::Error;
-use std::io::{self, BufReader};
-use std::io::prelude::*;
-use std::fs::File;
-use utils::*;
+use {ParseError, Solution};
 
 fn opposite_polarity(a: char, b: char) -> bool {
     a.to_ascii_uppercase() == b.to_ascii_uppercase() && a as i32 - b as i32 != 0
 }
 
 fn react(polymer: &str) -> String {
-    react_iter(&mut vec![], polymer.into())
+    react_iter(&mut vec![], polymer)
 }
 
 fn react_iter(xs: &mut Vec<char>, ys: &str) -> String {
@@ -38,44 +31,43 @@ fn react_iter(xs: &mut Vec<char>, ys: &str) -> String {
     }
 }
 
-fn part1(polymer: &str) -> i32 {
-    react(polymer).len() as i32
-}
+pub struct Day05;
 
-fn part2(polymer: &str) -> i32 {
-    let units = polymer.chars()
-        .fold(BTreeMap::new(), |mut units: BTreeMap<char, BTreeSet<char>>, c| {
-            units.entry(c.to_ascii_uppercase()).or_insert(BTreeSet::new()).insert(c);
-            units
-        });
-
-    units.iter()
-        .map(|(_, chars)| {
-            let reduced_polymer: String = polymer.chars().filter(|c| !chars.contains(c)).collect();
-            react(&reduced_polymer[..]).len()
-        })
-        .min()
-        .unwrap() as i32
-}
+impl Solution for Day05 {
+    type Input = String;
+    type Answer1 = i32;
+    type Answer2 = i32;
 
-fn main() -> Result<(), Box<Error>> {
-    measure_exec(|| {
-        let result = part1(input()?.as_str());
-        println!("Part1 result: {}", result);
-        Ok(())
-    })?;
-    measure_exec(|| {
-        let result = part2(input()?.as_str());
-        println!("Part2 result: {}", result);
-        Ok(())
-    })?;
-    Ok(())
-}
+    fn parse(input: &str) -> Result<String, ParseError> {
+        let polymer = input.lines().map(|l| l.trim()).next().ok_or_else(|| ParseError::new("Empty input"))?;
+        if polymer.is_empty() {
+            return Err(ParseError::new("Empty polymer").at_line(1));
+        }
+        if !polymer.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(ParseError::expected("a polymer of letters", polymer).at_line(1));
+        }
+        Ok(polymer.to_string())
+    }
 
-fn input() -> io::Result<String> {
-    let f = File::open("src/day05/input")?;
-    let f = BufReader::new(f);
-    Ok(f.lines().map(|l| l.unwrap()).next().unwrap())
+    fn part1(polymer: &String) -> i32 {
+        react(polymer).len() as i32
+    }
+
+    fn part2(polymer: &String) -> i32 {
+        let units = polymer.chars()
+            .fold(BTreeMap::new(), |mut units: BTreeMap<char, BTreeSet<char>>, c| {
+                units.entry(c.to_ascii_uppercase()).or_insert(BTreeSet::new()).insert(c);
+                units
+            });
+
+        units.iter()
+            .map(|(_, chars)| {
+                let reduced_polymer: String = polymer.chars().filter(|c| !chars.contains(c)).collect();
+                react(&reduced_polymer[..]).len()
+            })
+            .min()
+            .unwrap() as i32
+    }
 }
 
 #[cfg(test)]
@@ -100,11 +92,18 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        assert_eq!(part1(INPUT), 10);
+        assert_eq!(Day05::part1(&Day05::parse(INPUT).unwrap()), 10);
     }
 
     #[test]
     fn test_part2() {
-        assert_eq!(part2(INPUT), 4);
+        assert_eq!(Day05::part2(&Day05::parse(INPUT).unwrap()), 4);
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert_eq!(Day05::parse("").err(), Some(ParseError::new("Empty input")));
+        assert_eq!(Day05::parse("\n").err(), Some(ParseError::new("Empty polymer").at_line(1)));
+        assert_eq!(Day05::parse("ab1").unwrap_err().expectation(), Some("a polymer of letters"));
     }
 }
\ No newline at end of file
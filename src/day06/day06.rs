@@ -1,40 +1,12 @@
-extern crate utils;
-
-use std::cmp;
 use std::collections::HashSet;
-use std::str::FromStr;
-use std::error::Error;
-use std::num::ParseIntError;
-use std::io::{self, BufReader};
-use std::io::prelude::*;
-use std::fs::File;
-use utils::*;
-
-#[derive(PartialEq, Debug, Clone, Copy)]
-pub struct Coord {
-    pub x: i32,
-    pub y: i32
-}
-
-impl FromStr for Coord {
-    type Err = ParseIntError;
+use grid::Grid;
+use point::Point;
+use {parse_lines, ParseError, Solution};
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut sp = s.split(',').map(|s| s.trim());
-
-        Ok(Coord {
-            x: sp.next().unwrap().parse().unwrap(),
-            y: sp.next().unwrap().parse().unwrap()
-        })
-    }
-}
-
-fn manh_dist(a: &Coord, b: &Coord) -> i32 {
-    (a.x - b.x).abs() + (a.y - b.y).abs()
-}
+pub type Coord = Point<2>;
 
 fn closest_coords(c: &Coord, coords: &Vec<Coord>) -> Vec<usize> {
-    let dst = |cx| manh_dist(c, cx);
+    let dst = |cx| c.manhattan(cx);
     let mut dists: Vec<_> = coords.iter().map(dst).enumerate().collect();
 
     dists.sort_by(|(_, a), (_, b)| a.cmp(b));
@@ -43,73 +15,72 @@ fn closest_coords(c: &Coord, coords: &Vec<Coord>) -> Vec<usize> {
     dists.iter().take_while(|(_, d)| *d == cdst).map(|(i, _)| *i).collect()
 }
 
-fn part1(coords: &Vec<Coord>) -> i32 {
-    let (mx, my) = coords.iter()
-        .fold((0, 0), |(mx, my), c| (cmp::max(mx, c.x + 2), cmp::max(my, c.y + 2)));
+// Size of the area from the origin to beyond the bottom right coordinate
+fn area(coords: &Vec<Coord>) -> (usize, usize) {
+    let (_, max) = Point::bounding_box(coords).unwrap_or((Point::origin(), Point::origin()));
+    ((max.x() + 2) as usize, (max.y() + 2) as usize)
+}
 
-    let mut cnt_by_idx = vec![0; coords.len()];
-    let mut infinite: HashSet<usize> = HashSet::new();
+pub struct Day06;
 
-    for y in 0..my {
-        for x in 0..mx {
-            let closest = closest_coords(&Coord { x: x, y: y }, coords);
-            if closest.len() == 1 {
-                cnt_by_idx[closest[0]] += 1;
+impl Solution for Day06 {
+    type Input = Vec<Coord>;
+    type Answer1 = i32;
+    type Answer2 = i32;
 
-                if y == 0 || x == 0 || y == my - 1 || x == mx - 1 {
-                    for &c in closest.iter() {
-                        infinite.insert(c);
-                    }
+    fn parse(input: &str) -> Result<Vec<Coord>, ParseError> {
+        let coords = parse_lines(input, |l| l.parse::<Coord>())?;
+        if coords.is_empty() {
+            return Err(ParseError::new("No coordinates"));
+        }
+        Ok(coords)
+    }
+
+    fn part1(coords: &Vec<Coord>) -> i32 {
+        let (mx, my) = area(coords);
+
+        // Index of the single closest coordinate of every location
+        let closest = Grid::from_fn(mx, my, |x, y| {
+            let closest = closest_coords(&Point([x as i64, y as i64]), coords);
+            if closest.len() == 1 { Some(closest[0]) } else { None }
+        });
+
+        let mut cnt_by_idx = vec![0; coords.len()];
+        let mut infinite: HashSet<usize> = HashSet::new();
+        for (x, y) in closest.positions() {
+            if let Some(c) = closest[(x, y)] {
+                cnt_by_idx[c] += 1;
+                if y == 0 || x == 0 || y == closest.height() - 1 || x == closest.width() - 1 {
+                    infinite.insert(c);
                 }
             }
         }
+
+        cnt_by_idx.iter()
+            .enumerate()
+            .filter(|(i, _)| !infinite.contains(&i))
+            .map(|(_, &c)| c)
+            .max()
+            .unwrap()
     }
 
-    cnt_by_idx.iter()
-        .enumerate()
-        .filter(|(i, _)| !infinite.contains(&i))
-        .map(|(_, &c)| c)
-        .max()
-        .unwrap()
+    fn part2(coords: &Vec<Coord>) -> i32 {
+        safe_region_size(coords, 10000)
+    }
 }
 
-fn part2(coords: &Vec<Coord>, max_sum: i32) -> i32 {
-    let (mx, my) = coords.iter()
-        .fold((0, 0), |(mx, my), c| (cmp::max(mx, c.x + 2), cmp::max(my, c.y + 2)));
+fn safe_region_size(coords: &Vec<Coord>, max_sum: i64) -> i32 {
+    let (mx, my) = area(coords);
 
-    let mut within_max_sum = vec![false; (mx * my) as usize];
-
-    for y in 0..my {
-        for x in 0..mx {
-            let scoord = Coord { x: x, y: y };
-            let csum: i32 = coords.iter().map(|c| manh_dist(&scoord, c)).sum();
-            within_max_sum[(mx * y + x) as usize] = csum < max_sum;
-        }
-    }
+    let within_max_sum = Grid::from_fn(mx, my, |x, y| {
+        let scoord = Point([x as i64, y as i64]);
+        let csum: i64 = coords.iter().map(|c| scoord.manhattan(c)).sum();
+        csum < max_sum
+    });
 
     within_max_sum.iter().filter(|&&s| s).count() as i32
 }
 
-fn main() -> Result<(), Box<Error>> {
-    measure_exec(|| {
-        let result = part1(&input()?);
-        println!("Part1 result: {}", result);
-        Ok(())
-    })?;
-    measure_exec(|| {
-        let result = part2(&input()?, 10000);
-        println!("Part2 result: {}", result);
-        Ok(())
-    })?;
-    Ok(())
-}
-
-fn input() -> io::Result<Vec<Coord>> {
-    let f = File::open("src/day06/input")?;
-    let f = BufReader::new(f);
-    Ok(f.lines().map(|l| l.unwrap().trim().parse::<Coord>().unwrap()).collect())
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -123,16 +94,16 @@ mod tests {
         8, 9";
 
     fn as_input(s: &str) -> Vec<Coord> {
-        s.split('\n').map(|s| s.trim().parse::<Coord>().unwrap()).collect()
+        Day06::parse(s).unwrap()
     }
 
     #[test]
     fn test_part1() {
-        assert_eq!(part1(&as_input(INPUT)), 17);
+        assert_eq!(Day06::part1(&as_input(INPUT)), 17);
     }
 
     #[test]
     fn test_part2() {
-        assert_eq!(part2(&as_input(INPUT), 32), 16);
+        assert_eq!(safe_region_size(&as_input(INPUT), 32), 16);
     }
 }
\ No newline at end of file
@@ -1,16 +1,8 @@
-extern crate regex;
-#[macro_use] extern crate lazy_static;
-extern crate utils;
-
 use std::collections::HashSet;
 use std::collections::BTreeSet;
 use std::collections::BTreeMap;
-use std::error::Error;
-use std::io::{self, BufReader};
-use std::io::prelude::*;
-use std::fs::File;
 use regex::Regex;
-use utils::*;
+use {parse_lines, ParseError, Solution};
 
 
 #[derive(PartialEq, Debug, Clone)]
@@ -19,54 +11,69 @@ pub struct Step {
     pub req: BTreeSet<char>
 }
 
-fn parse_instructions(input: &Vec<String>) -> BTreeMap<char, Step> {
+fn parse_instructions(input: &str) -> Result<BTreeMap<char, Step>, ParseError> {
     lazy_static! {
-        static ref RE: Regex = Regex::new(r"^.* (.) .* (.) .*$").unwrap();
+        static ref RE: Regex = Regex::new(r"^Step ([A-Z]) must be finished before step ([A-Z]) can begin\.$").unwrap();
     }
     let mut steps = BTreeMap::new();
 
-    for i in input.iter() {
-        let caps = RE.captures(i).unwrap();
+    let deps = parse_lines(input, |i| {
+        let caps = RE.captures(i)
+            .ok_or_else(|| ParseError::expected("'Step <A-Z> must be finished before step <A-Z> can begin.'", i))?;
         let get = |idx| caps.get(idx).unwrap().as_str().chars().next().unwrap();
+        Ok((get(1), get(2)))
+    })?;
 
-        let rid = get(1);
-        let id = get(2);
-
+    for (rid, id) in deps {
         steps.entry(rid).or_insert(Step { id: rid, req: BTreeSet::new() });
         let Step { id: _, req } = steps.entry(id).or_insert(Step { id: id, req: BTreeSet::new() });
         req.insert(rid);
     }
-    steps
+    Ok(steps)
 }
 
-fn part1(input: &Vec<String>) -> String {
-    let steps = parse_instructions(input);
-    let mut visited: HashSet<char> = HashSet::new();
-    let mut result: Vec<char> = vec![];
+pub struct Day07;
 
-    loop {
-        let no_req = steps.iter()
-            .filter(|(_, s)| {
-                (*s).req.iter().filter(|r| !visited.contains(r)).count() == 0
-            })
-            .map(|(id, _)| id.to_owned())
-            .filter(|id| !visited.contains(id))
-            .next();
+impl Solution for Day07 {
+    type Input = BTreeMap<char, Step>;
+    type Answer1 = String;
+    type Answer2 = i32;
 
-        match no_req {
-            None => break,
-            Some(s) => {
-                visited.insert(s);
-                result.push(s);
+    fn parse(input: &str) -> Result<BTreeMap<char, Step>, ParseError> {
+        parse_instructions(input)
+    }
+
+    fn part1(steps: &BTreeMap<char, Step>) -> String {
+        let mut visited: HashSet<char> = HashSet::new();
+        let mut result: Vec<char> = vec![];
+
+        loop {
+            let no_req = steps.iter()
+                .filter(|(_, s)| {
+                    (*s).req.iter().filter(|r| !visited.contains(r)).count() == 0
+                })
+                .map(|(id, _)| id.to_owned())
+                .filter(|id| !visited.contains(id))
+                .next();
+
+            match no_req {
+                None => break,
+                Some(s) => {
+                    visited.insert(s);
+                    result.push(s);
+                }
             }
         }
+
+        result.iter().collect()
     }
 
-    result.iter().collect()
+    fn part2(steps: &BTreeMap<char, Step>) -> i32 {
+        assembly_time(steps, 5, 60)
+    }
 }
 
-fn part2(input: &Vec<String>, n_workers: usize, base_time: i32) -> i32 {
-    let steps = parse_instructions(input);
+fn assembly_time(steps: &BTreeMap<char, Step>, n_workers: usize, base_time: i32) -> i32 {
     let mut workers: Vec<(char, i32)> = vec![(' ', 0); n_workers];
     let mut visited: HashSet<char> = HashSet::new();
     let mut result: Vec<char> = vec![];
@@ -121,26 +128,6 @@ fn part2(input: &Vec<String>, n_workers: usize, base_time: i32) -> i32 {
     tot_time
 }
 
-fn main() -> Result<(), Box<Error>> {
-    measure_exec(|| {
-        let result = part1(&input()?);
-        println!("Part1 result: {}", result);
-        Ok(())
-    })?;
-    measure_exec(|| {
-        let result = part2(&input()?, 5, 60);
-        println!("Part2 result: {}", result);
-        Ok(())
-    })?;
-    Ok(())
-}
-
-fn input() -> io::Result<Vec<String>> {
-    let f = File::open("src/day07/input")?;
-    let f = BufReader::new(f);
-    Ok(f.lines().map(|l| l.unwrap()).collect())
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -154,17 +141,17 @@ mod tests {
         Step D must be finished before step E can begin.
         Step F must be finished before step E can begin.";
 
-    fn as_input(s: &str) -> Vec<String> {
-        s.split('\n').map(|s| s.trim().into()).collect()
+    fn as_input(s: &str) -> BTreeMap<char, Step> {
+        Day07::parse(s).unwrap()
     }
 
     #[test]
     fn test_part1() {
-        assert_eq!(part1(&as_input(INPUT)), "CABDFE");
+        assert_eq!(Day07::part1(&as_input(INPUT)), "CABDFE");
     }
 
     #[test]
     fn test_part2() {
-        assert_eq!(part2(&as_input(INPUT), 2, 0), 15);
+        assert_eq!(assembly_time(&as_input(INPUT), 2, 0), 15);
     }
 }
\ No newline at end of file
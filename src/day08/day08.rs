@@ -1,35 +1,60 @@
-extern crate utils;
-
-use std::error::Error;
-use std::io::{self, BufReader};
-use std::io::prelude::*;
-use std::fs::File;
-use utils::*;
+use {ParseError, Solution};
 
 pub struct Node {
     pub children: Vec<Node>,
     pub metadata: Vec<i32>
 }
 
-fn parse_tree(xs: &[i32]) -> (usize, Node) {
-    let (c_len, children) = (0..xs[0]).into_iter()
-        .fold((0, vec![]), |(cl, mut ch): (usize, Vec<Node>), _| {
-            let (c_len, node) = parse_tree(&xs[(2 + cl)..]);
-            ch.push(node);
-            (cl + c_len, ch)
-        });
-    let m_cnt = xs[1] as usize;
+// Length and node of the tree at the start of xs, which ends early if the counts are wrong
+fn parse_tree(xs: &[i32]) -> Result<(usize, Node), ParseError> {
+    let truncated = || ParseError::new("Input ends within the tree");
+    let (&c_cnt, &m_cnt) = match xs {
+        [c, m, ..] if *c >= 0 && *m >= 0 => (c, m),
+        [_, _, ..] => return Err(ParseError::expected("a header of two non-negative counts", &format!("{} {}", xs[0], xs[1]))),
+        _ => return Err(truncated())
+    };
+    let mut c_len = 0;
+    let mut children = vec![];
+    for _ in 0..c_cnt {
+        let (cl, node) = parse_tree(&xs[(2 + c_len)..])?;
+        children.push(node);
+        c_len += cl;
+    }
     let m_idx = 2 + c_len;
-    (2 + c_len + m_cnt, Node { children: children, metadata: xs[m_idx..(m_idx + m_cnt)].to_vec() })
+    let metadata = xs.get(m_idx..(m_idx + m_cnt as usize)).ok_or_else(truncated)?.to_vec();
+    Ok((m_idx + m_cnt as usize, Node { children, metadata }))
 }
 
 fn sum_metadata(node: &Node) -> i32 {
     (*node).metadata.iter().sum::<i32>() + (*node).children.iter().map(sum_metadata).sum::<i32>()
 }
 
-fn part1(input: &Vec<i32>) -> i32 {
-    let (_, root) = parse_tree(input);
-    sum_metadata(&root)
+pub struct Day08;
+
+impl Solution for Day08 {
+    type Input = Node;
+    type Answer1 = i32;
+    type Answer2 = i32;
+
+    fn parse(input: &str) -> Result<Node, ParseError> {
+        let xs = input.split_whitespace()
+            .map(|n| n.parse().map_err(|_| ParseError::expected("a number", n)))
+            .collect::<Result<Vec<i32>, _>>()?;
+        let (len, root) = parse_tree(&xs)?;
+        if len < xs.len() {
+            let rest: Vec<String> = xs[len..].iter().map(|n| n.to_string()).collect();
+            return Err(ParseError::expected("the end of the input after the tree", &rest.join(" ")));
+        }
+        Ok(root)
+    }
+
+    fn part1(root: &Node) -> i32 {
+        sum_metadata(root)
+    }
+
+    fn part2(root: &Node) -> i32 {
+        sum_value(root)
+    }
 }
 
 fn sum_value(node: &Node) -> i32 {
@@ -46,31 +71,6 @@ fn sum_value(node: &Node) -> i32 {
     }
 }
 
-fn part2(input: &Vec<i32>) -> i32 {
-    let (_, root) = parse_tree(input);
-    sum_value(&root)
-}
-
-fn main() -> Result<(), Box<Error>> {
-    measure_exec(|| {
-        let result = part1(&input()?);
-        println!("Part1 result: {}", result);
-        Ok(())
-    })?;
-    measure_exec(|| {
-        let result = part2(&input()?);
-        println!("Part2 result: {}", result);
-        Ok(())
-    })?;
-    Ok(())
-}
-
-fn input() -> io::Result<Vec<i32>> {
-    let f = File::open("src/day08/input")?;
-    let f = BufReader::new(f);
-    Ok(f.lines().next().unwrap().unwrap().split(' ').map(|l| l.parse().unwrap()).collect())
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -78,17 +78,24 @@ mod tests {
     const INPUT: &'static str =
        "2 3 0 3 10 11 12 1 1 0 1 99 2 1 1 2";
 
-    fn as_input(s: &str) -> Vec<i32> {
-        s.split('\n').next().unwrap().split(' ').map(|s| s.parse().unwrap()).collect()
+    fn as_input(s: &str) -> Node {
+        Day08::parse(s).unwrap()
     }
 
     #[test]
     fn test_part1() {
-        assert_eq!(part1(&as_input(INPUT)), 138);
+        assert_eq!(Day08::part1(&as_input(INPUT)), 138);
     }
 
     #[test]
     fn test_part2() {
-        assert_eq!(part2(&as_input(INPUT)), 66);
+        assert_eq!(Day08::part2(&as_input(INPUT)), 66);
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert_eq!(Day08::parse("2 3 0 3 10 11 12").err(), Some(ParseError::new("Input ends within the tree")));
+        assert_eq!(Day08::parse("0 1 5 7 8").err(), Some(ParseError::expected("the end of the input after the tree", "7 8")));
+        assert!(Day08::parse("0 x").is_err());
     }
 }
\ No newline at end of file
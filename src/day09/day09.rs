@@ -1,16 +1,8 @@
-extern crate regex;
-#[macro_use] extern crate lazy_static;
-extern crate utils;
-
-use std::error::Error;
-use std::io::{self, BufReader};
-use std::io::prelude::*;
-use std::fs::File;
 use regex::Regex;
-use utils::*;
+use {parse_lines, ParseError, Solution};
 
 #[derive(Debug)]
-struct Game {
+pub struct Game {
     n_players: usize,
     last_marble: u32
 }
@@ -22,15 +14,20 @@ struct Marble {
 }
 
 impl Game {
-    fn parse(input: &String) -> Game {
+    fn parse(input: &str) -> Result<Game, ParseError> {
         lazy_static! {
-            static ref RE: Regex = Regex::new(r"^(\d*) .* (\d*) .*$").unwrap();
+            static ref RE: Regex = Regex::new(r"^(\d+) players; last marble is worth (\d+) points").unwrap();
         }
-        let caps = RE.captures(input).unwrap();
-        Game {
-            n_players: caps.get(1).unwrap().as_str().parse().unwrap(),
-            last_marble: caps.get(2).unwrap().as_str().parse().unwrap()
+        let caps = RE.captures(input)
+            .ok_or_else(|| ParseError::expected("'<n> players; last marble is worth <n> points'", input))?;
+        let n_players = caps.get(1).unwrap().as_str().parse()?;
+        if n_players == 0 {
+            return Err(ParseError::new("A game needs players").in_text(input));
         }
+        Ok(Game {
+            n_players,
+            last_marble: caps.get(2).unwrap().as_str().parse()?
+        })
     }
 
     fn play(&self) -> u32 {
@@ -72,39 +69,35 @@ impl Game {
     }
 }
 
-fn part1(input: &Vec<String>) -> Vec<u32> {
-    input.iter()
-        .map(Game::parse)
+fn high_scores(games: &Vec<Game>, multiplier: u32) -> Vec<u32> {
+    games.iter()
+        .map(|g| Game { n_players: g.n_players, last_marble: g.last_marble * multiplier })
         .map(|g| g.play())
         .collect()
 }
 
-fn part2(input: &Vec<String>) -> Vec<u32> {
-    input.iter()
-        .map(Game::parse)
-        .map(|g| Game { n_players: g.n_players, last_marble: g.last_marble * 100 })
-        .map(|g| g.play())
-        .collect()
-}
+pub struct Day09;
 
-fn main() -> Result<(), Box<Error>> {
-    measure_exec(|| {
-        let result = part1(&input()?)[0];
-        println!("Part1 result: {:?}", result);
-        Ok(())
-    })?;
-    measure_exec(|| {
-        let result = part2(&input()?)[0];
-        println!("Part2 result: {:?}", result);
-        Ok(())
-    })?;
-    Ok(())
-}
+impl Solution for Day09 {
+    type Input = Vec<Game>;
+    type Answer1 = u32;
+    type Answer2 = u32;
+
+    fn parse(input: &str) -> Result<Vec<Game>, ParseError> {
+        let games = parse_lines(input, Game::parse)?;
+        if games.is_empty() {
+            return Err(ParseError::new("No games"));
+        }
+        Ok(games)
+    }
 
-fn input() -> io::Result<Vec<String>> {
-    let f = File::open("src/day09/input")?;
-    let f = BufReader::new(f);
-    Ok(f.lines().map(|l| l.unwrap()).collect())
+    fn part1(games: &Vec<Game>) -> u32 {
+        high_scores(games, 1)[0]
+    }
+
+    fn part2(games: &Vec<Game>) -> u32 {
+        high_scores(games, 100)[0]
+    }
 }
 
 #[cfg(test)]
@@ -119,12 +112,19 @@ mod tests {
         21 players; last marble is worth 6111 points
         30 players; last marble is worth 5807 points";
 
-    fn as_input(s: &str) -> Vec<String> {
-        s.split('\n').map(|s| s.trim().into()).collect()
+    fn as_input(s: &str) -> Vec<Game> {
+        Day09::parse(s).unwrap()
     }
 
     #[test]
     fn test_part1() {
-        assert_eq!(part1(&as_input(INPUT)), vec![32, 8317, 146373, 2764, 54718, 37305]);
+        assert_eq!(high_scores(&as_input(INPUT), 1), vec![32, 8317, 146373, 2764, 54718, 37305]);
+        assert_eq!(Day09::part1(&as_input(INPUT)), 32);
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert_eq!(Day09::parse("").err(), Some(ParseError::new("No games")));
+        assert!(Day09::parse("0 players; last marble is worth 25 points").is_err());
     }
 }
\ No newline at end of file
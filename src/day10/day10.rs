@@ -1,40 +1,35 @@
-extern crate regex;
-#[macro_use] extern crate lazy_static;
-extern crate utils;
-
-use std::error::Error;
-use std::io::{self, BufReader};
-use std::io::prelude::*;
-use std::fs::File;
 use regex::Regex;
-use utils::*;
+use {parse_lines, ParseError, Solution};
 
 #[derive(Clone)]
-struct Vec2 {
+pub struct Vec2 {
     x: i32,
     y: i32
 }
 
 #[derive(Clone)]
-struct Point {
+pub struct Point {
     pos: Vec2,
     vel: Vec2
 }
 
-fn parse_points(input: &Vec<String>) -> Vec<Point> {
+fn parse_points(input: &str) -> Result<Vec<Point>, ParseError> {
     lazy_static! {
         static ref RE: Regex = Regex::new(r"^.+<\s*(-?\d+),\s*(-?\d+)>.+<\s*(-?\d+),\s*(-?\d+)>$").unwrap();
     }
-    input.iter()
-        .map(|i| {
-            let caps = RE.captures(i).unwrap();
-            let get = |idx| caps.get(idx).unwrap().as_str().parse::<i32>().unwrap();
-            Point {
-                pos: Vec2 { x: get(1), y: get(2) },
-                vel: Vec2 { x: get(3), y: get(4) }
-            }
+    let points = parse_lines(input, |i| {
+        let caps = RE.captures(i)
+            .ok_or_else(|| ParseError::expected("a point like 'position=< 9,  1> velocity=< 0,  2>'", i))?;
+        let get = |idx| caps.get(idx).unwrap().as_str().parse::<i32>();
+        Ok(Point {
+            pos: Vec2 { x: get(1)?, y: get(2)? },
+            vel: Vec2 { x: get(3)?, y: get(4)? }
         })
-        .collect()
+    })?;
+    if points.is_empty() {
+        return Err(ParseError::new("No points"));
+    }
+    Ok(points)
 }
 
 fn tot_height(points: &Vec<Point>) -> i32 {
@@ -64,8 +59,8 @@ fn gen_message(points: &Vec<Point>) -> String {
 }
 
 // An imperative approach to finding the message
-fn find_message(input: &Vec<String>) -> (String, i32) {
-    let mut points = parse_points(input);
+fn find_message(input: &Vec<Point>) -> (String, i32) {
+    let mut points = input.clone();
     let points = points.as_mut();
     let mut ltoth = tot_height(&points);
     let mut nsec = 0;
@@ -84,79 +79,26 @@ fn find_message(input: &Vec<String>) -> (String, i32) {
     (gen_message(points), nsec)
 }
 
-// A functional approach to finding the message
-fn find_message_func(input: &Vec<String>) -> (String, i32) {
-    let points = parse_points(input);
-    let th = tot_height(&points);
-
-    (1..)
-        .scan((points, th), |(ref mut lpts, ref mut lth), i| {
-            let pts: Vec<_> = lpts.iter()
-                .map(|p| Point { pos: Vec2 { x: p.pos.x + p.vel.x, y: p.pos.y + p.vel.y }, vel: p.vel.to_owned() })
-                .collect();
-
-            let th = tot_height(&pts);
-            if th > *lth {
-                None
-            } else {
-                *lpts = pts.to_owned();
-                *lth = th;
-                Some((pts, i))
-            }
-        })
-        .last()
-        .map(|(rpoints, tsecs)| (gen_message(&rpoints), tsecs))
-        .unwrap()
-}
+pub struct Day10;
 
-fn part1(input: &Vec<String>) -> String {
-    let (message, _) = find_message(input);
-    message
-}
-
-fn part1_func(input: &Vec<String>) -> String {
-    let (message, _) = find_message_func(input);
-    message
-}
-
-fn part2(input: &Vec<String>) -> i32 {
-    let (_, count) = find_message(input);
-    count
-}
+impl Solution for Day10 {
+    type Input = Vec<Point>;
+    type Answer1 = String;
+    type Answer2 = i32;
 
-fn part2_func(input: &Vec<String>) -> i32 {
-    let (_, count) = find_message_func(input);
-    count
-}
+    fn parse(input: &str) -> Result<Vec<Point>, ParseError> {
+        parse_points(input)
+    }
 
-fn main() -> Result<(), Box<Error>> {
-    measure_exec(|| {
-        let result = part1(&input()?);
-        println!("Part1 result:\n{}", result);
-        Ok(())
-    })?;
-    measure_exec(|| {
-        let result = part1_func(&input()?);
-        println!("Part1 func result:\n{}", result);
-        Ok(())
-    })?;
-    measure_exec(|| {
-        let result = part2(&input()?);
-        println!("Part2 result: {}", result);
-        Ok(())
-    })?;
-    measure_exec(|| {
-        let result = part2_func(&input()?);
-        println!("Part2 func result: {}", result);
-        Ok(())
-    })?;
-    Ok(())
-}
+    fn part1(points: &Vec<Point>) -> String {
+        let (message, _) = find_message(points);
+        message
+    }
 
-fn input() -> io::Result<Vec<String>> {
-    let f = File::open("src/day10/input")?;
-    let f = BufReader::new(f);
-    Ok(f.lines().map(|l| l.unwrap()).collect())
+    fn part2(points: &Vec<Point>) -> i32 {
+        let (_, count) = find_message(points);
+        count
+    }
 }
 
 #[cfg(test)]
@@ -196,8 +138,8 @@ mod tests {
         position=<14,  7> velocity=<-2,  0>
         position=<-3,  6> velocity=< 2, -1>";
 
-    fn as_input(s: &str) -> Vec<String> {
-        s.split('\n').map(|s| s.trim().into()).collect()
+    fn as_input(s: &str) -> Vec<Point> {
+        Day10::parse(s).unwrap()
     }
 
     fn as_display(s: &str) -> String {
@@ -217,32 +159,11 @@ mod tests {
              #...#..###"
         );
 
-        assert_eq!(part1(&as_input(INPUT)), expected);
-    }
-
-    #[test]
-    fn test_part1_func() {
-        let expected = as_display(
-            "#...#..###
-             #...#...#.
-             #...#...#.
-             #####...#.
-             #...#...#.
-             #...#...#.
-             #...#...#.
-             #...#..###"
-        );
-
-        assert_eq!(part1_func(&as_input(INPUT)), expected);
+        assert_eq!(Day10::part1(&as_input(INPUT)), expected);
     }
 
     #[test]
     fn test_part2() {
-        assert_eq!(part2(&as_input(INPUT)), 3);
-    }
-
-    #[test]
-    fn test_part2_func() {
-        assert_eq!(part2_func(&as_input(INPUT)), 3);
+        assert_eq!(Day10::part2(&as_input(INPUT)), 3);
     }
 }
\ No newline at end of file
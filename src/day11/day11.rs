@@ -1,24 +1,14 @@
-extern crate utils;
-
-use std::error::Error;
-use std::io::{self, BufReader};
-use std::io::prelude::*;
-use std::fs::File;
-use utils::*;
+use grid::Grid;
+use {ParseError, Solution};
 
 fn find_pos_highest_power(ser_no: usize, smin: usize, smax: usize) -> (usize, usize, usize, i32) {
     const N: usize = 300;
-    let mut grid = [[0i32; N]; N];
-
-    for y in 0..N {
-        for x in 0..N {
-            let r_id = (x + 1) + 10;
-            let p_lev_s = r_id * (y + 1);
-            let h_dig = (((p_lev_s + ser_no) * r_id) / 100) % 10;
-            let p_lev = h_dig as i32 - 5;
-            grid[y][x] = p_lev;
-        }
-    }
+    let grid = Grid::from_fn(N, N, |x, y| {
+        let r_id = (x + 1) + 10;
+        let p_lev_s = r_id * (y + 1);
+        let h_dig = (((p_lev_s + ser_no) * r_id) / 100) % 10;
+        h_dig as i32 - 5
+    });
 
     let mut ltot_p = 0;
     let mut lpos = (0, 0, 0, 0);
@@ -30,7 +20,7 @@ fn find_pos_highest_power(ser_no: usize, smin: usize, smax: usize) -> (usize, us
     //             let mut tot_p = 0;
     //             for sy in y..(y + s) {
     //                 for sx in x..(x + s) {
-    //                     tot_p += grid[sy][sx];
+    //                     tot_p += grid[(sx, sy)];
     //                 }
     //             }
     //             if tot_p > ltot_p {
@@ -48,18 +38,16 @@ fn find_pos_highest_power(ser_no: usize, smin: usize, smax: usize) -> (usize, us
 
             for sy in 0..(smin - 1) {
                 for sx in 0..(smin - 1) {
-                    tot_p += grid[y + sy][x + sx];
+                    tot_p += grid[(x + sx, y + sy)];
                 }
             }
 
             let smx = std::cmp::min(std::cmp::min(N - y, N - x), smax);
             if smx >= smin {
                 for s in smin..=smx {
-                    for sx in x..(x + s) {
-                        tot_p += grid[y + s - 1][sx];
-                    }
+                    tot_p += grid.row(y + s - 1)[x..(x + s)].iter().sum::<i32>();
                     for sy in y..(y + s - 1) {
-                        tot_p += grid[sy][x + s - 1];
+                        tot_p += grid[(x + s - 1, sy)];
                     }
                     if tot_p > ltot_p {
                         ltot_p = tot_p;
@@ -73,34 +61,26 @@ fn find_pos_highest_power(ser_no: usize, smin: usize, smax: usize) -> (usize, us
     lpos
 }
 
-fn part1(ser_no: usize) -> (usize, usize) {
-    let (x, y, _, _) = find_pos_highest_power(ser_no, 3, 3);
-    (x, y)
-}
+pub struct Day11;
 
-fn part2(ser_no: usize) -> (usize, usize, usize) {
-    let (x, y, s, _) = find_pos_highest_power(ser_no, 1, 300);
-    (x, y, s)
-}
+impl Solution for Day11 {
+    type Input = usize;
+    type Answer1 = String;
+    type Answer2 = String;
 
-fn main() -> Result<(), Box<Error>> {
-    measure_exec(|| {
-        let result = part1(input()?);
-        println!("Part1 result: {:?}", result);
-        Ok(())
-    })?;
-    measure_exec(|| {
-        let result = part2(input()?);
-        println!("Part2 result: {:?}", result);
-        Ok(())
-    })?;
-    Ok(())
-}
+    fn parse(input: &str) -> Result<usize, ParseError> {
+        input.trim().parse().map_err(|_| ParseError::expected("a grid serial number", input.trim()))
+    }
 
-fn input() -> io::Result<usize> {
-    let f = File::open("src/day11/input")?;
-    let f = BufReader::new(f);
-    Ok(f.lines().map(|l| l.unwrap().parse::<usize>().unwrap()).next().unwrap())
+    fn part1(ser_no: &usize) -> String {
+        let (x, y, _, _) = find_pos_highest_power(*ser_no, 3, 3);
+        format!("{},{}", x, y)
+    }
+
+    fn part2(ser_no: &usize) -> String {
+        let (x, y, s, _) = find_pos_highest_power(*ser_no, 1, 300);
+        format!("{},{},{}", x, y, s)
+    }
 }
 
 #[cfg(test)]
@@ -108,7 +88,7 @@ mod tests {
     use super::*;
 
     fn as_input(s: &str) -> usize {
-        s.split('\n').map(|s| s.trim().parse::<usize>().unwrap()).next().unwrap()
+        Day11::parse(s).unwrap()
     }
 
     #[test]
@@ -121,13 +101,13 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        assert_eq!(part1(as_input("18")), (33, 45));
-        assert_eq!(part1(as_input("42")), (21, 61));
+        assert_eq!(Day11::part1(&as_input("18")), "33,45");
+        assert_eq!(Day11::part1(&as_input("42")), "21,61");
     }
 
     #[test]
     fn test_part2() {
-        assert_eq!(part2(as_input("18")), (90, 269, 16));
-        assert_eq!(part2(as_input("42")), (232, 251, 12));
+        assert_eq!(Day11::part2(&as_input("18")), "90,269,16");
+        assert_eq!(Day11::part2(&as_input("42")), "232,251,12");
     }
 }
\ No newline at end of file
@@ -1,132 +1,125 @@
-extern crate utils;
-
-use std::error::Error;
-use std::io::{self, BufReader};
-use std::io::prelude::*;
-use std::fs::File;
-use utils::*;
-
-fn parse_input(input: &Vec<String>) -> (Vec<bool>, Vec<Vec<bool>>) {
-    let init_state: Vec<_> = (input[0])["initial state: ".len()..]
-        .chars()
-        .map(|c| c == '#')
-        .collect();
-
-    let growth_patterns: Vec<_> = input.iter()
-        .skip(2)
-        .filter(|l| l.ends_with(" => #"))
-        .map(|l| {
-            l.chars().take(5).map(|c| c == '#').collect()
+use cycle;
+use {parse_lines, ParseError, Solution};
+
+// The initial state, or a pattern of five pots and whether it grows a plant
+enum Note {
+    Initial(Vec<bool>), Pattern(Vec<bool>, bool)
+}
+
+fn parse_pots(s: &str) -> Result<Vec<bool>, ParseError> {
+    s.chars()
+        .map(|c| match c {
+            '#' => Ok(true),
+            '.' => Ok(false),
+            _ => Err(ParseError::expected("pots of '#' or '.'", s))
         })
-        .collect();
+        .collect()
+}
 
-    (init_state, growth_patterns)
+fn parse_note(l: &str) -> Result<Note, ParseError> {
+    if let Some(s) = l.strip_prefix("initial state: ") {
+        return Ok(Note::Initial(parse_pots(s)?));
+    }
+    match l.split_once(" => ") {
+        Some((p, r)) if p.len() == 5 && r.len() == 1 => Ok(Note::Pattern(parse_pots(p)?, parse_pots(r)?[0])),
+        _ => Err(ParseError::expected("a pattern like '..#.. => #'", l))
+    }
 }
 
-// fn print_state(state: &Vec<bool>, s_idx: usize) {
-//     for &p in state.iter().skip(20) {
-//         print!("{}", if p { "#" } else { "." });
-//     }
-//     println!();
-// }
-
-fn run_gen(state: &Vec<bool>, growth_patters: &Vec<Vec<bool>>) -> Vec<bool> {
-    let mut next_state = vec![false; state.len() + 1];
-
-    for i in 2..(state.len() - 2) {
-        for j in 0..growth_patters.len() {
-            let gp = &growth_patters[j];
-            if gp[0] == state[i - 2] &&
-               gp[1] == state[i - 1] &&
-               gp[2] == state[i - 0] &&
-               gp[3] == state[i + 1] &&
-               gp[4] == state[i + 2] {
-                next_state[i] = true;
-                break;
-            }
+fn parse_input(input: &str) -> Result<(Vec<bool>, Vec<Vec<bool>>), ParseError> {
+    let mut notes = parse_lines(input, parse_note)?.into_iter();
+    let init_state = match notes.next() {
+        Some(Note::Initial(s)) => s,
+        _ => return Err(ParseError::new("Missing initial state"))
+    };
+
+    let mut growth_patterns = vec![];
+    for n in notes {
+        match n {
+            Note::Pattern(p, true) => growth_patterns.push(p),
+            Note::Pattern(_, false) => {},
+            Note::Initial(_) => return Err(ParseError::new("More than one initial state"))
         }
     }
 
-    next_state
+    Ok((init_state, growth_patterns))
 }
 
-fn pad_state(state: &Vec<bool>, pad: usize) -> Vec<bool> {
-    let p_iter = || [false].iter().cycle().take(pad);
-    p_iter().chain(state.iter()).chain(p_iter()).map(|b| *b).collect()
+// Pots with plants, from the first to the last one with a plant, and the index of the first
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+struct Plants {
+    first: i64,
+    pots: Vec<bool>
 }
 
-fn part1(input: &Vec<String>) -> i32 {
-    let (init_state, growth_patters) = parse_input(input);
+impl Plants {
+    fn new(first: i64, pots: &[bool]) -> Plants {
+        match pots.iter().position(|&p| p) {
+            Some(s) => {
+                let e = pots.iter().rposition(|&p| p).unwrap();
+                Plants { first: first + s as i64, pots: pots[s..=e].to_vec() }
+            },
+            None => Plants { first: 0, pots: vec![] }
+        }
+    }
 
-    let s_idx = 5;
-    let mut state: Vec<bool> = pad_state(&init_state, s_idx);
-    //print_state(&state, s_idx);
-    for _ in 0..20 {
-        state = run_gen(&state, &growth_patters);
-        //print_state(&state, s_idx);
+    fn next(&self, growth_patterns: &[Vec<bool>]) -> Plants {
+        // Plants can only grow up to two pots away from the current ones
+        let padded: Vec<bool> = [false; 4].iter()
+            .chain(self.pots.iter())
+            .chain([false; 4].iter())
+            .copied()
+            .collect();
+        let pots: Vec<bool> = padded.windows(5)
+            .map(|w| growth_patterns.iter().any(|gp| gp[..] == w[..]))
+            .collect();
+        Plants::new(self.first - 2, &pots)
     }
 
-    let plant_idxs: Vec<i32> = state.iter()
-        .enumerate()
-        .filter(|(_, &p)| p)
-        .map(|(i, _)| i as i32 - s_idx as i32)
-        .collect();
+    fn sum(&self) -> i64 {
+        self.pots.iter()
+            .enumerate()
+            .filter(|(_, &p)| p)
+            .map(|(i, _)| self.first + i as i64)
+            .sum()
+    }
 
-    plant_idxs.iter().sum()
+    fn count(&self) -> i64 {
+        self.pots.iter().filter(|&&p| p).count() as i64
+    }
 }
 
-fn part2(input: &Vec<String>) -> usize {
-    let (init_state, growth_patters) = parse_input(input);
+// Sum of the indices of pots with plants after the given number of generations, the pattern
+// eventually repeats while moving along the pots
+fn plant_sum(input: &(Vec<bool>, Vec<Vec<bool>>), generations: usize) -> i64 {
+    let (init_state, growth_patterns) = input;
+    let (plants, shift) = cycle::extrapolate_shifted(
+        Plants::new(0, init_state),
+        |p| p.next(growth_patterns),
+        |p| (p.pots.clone(), p.first),
+        generations
+    );
+    plants.sum() + shift * plants.count()
+}
 
-    let s_idx = 5;
-    let mut state: Vec<bool> = pad_state(&init_state, s_idx);
-    //print_state(&state, s_idx);
-    let mut s_gen = 0;
+pub struct Day12;
 
-    // We assume here that a stable pattern can be reached within 200 iterations
-    // This is based purely on obeservation of the input data
-    for g in 0..200 {
-        let next_state = run_gen(&state, &growth_patters);
-        //print_state(&next_state, s_idx);
+impl Solution for Day12 {
+    type Input = (Vec<bool>, Vec<Vec<bool>>);
+    type Answer1 = i64;
+    type Answer2 = i64;
 
-        let stable = state.iter().zip(next_state.iter().skip(1)).all(|(a, b)| a == b);
-        if stable {
-            s_gen = g;
-            break;
-        }
-        state = next_state;
+    fn parse(input: &str) -> Result<(Vec<bool>, Vec<Vec<bool>>), ParseError> {
+        parse_input(input)
     }
 
-    let i_add = 50_000_000_000 - s_gen;
-
-    let plant_idxs: Vec<usize> = state.iter()
-        .enumerate()
-        .filter(|(_, &p)| p)
-        .map(|(i, _)| i - s_idx)
-        .map(|i| i + i_add)
-        .collect();
-
-    plant_idxs.iter().sum()
-}
-
-fn main() -> Result<(), Box<Error>> {
-    measure_exec(|| {
-        let result = part1(&input()?);
-        println!("Part1 result: {}", result);
-        Ok(())
-    })?;
-    measure_exec(|| {
-        let result = part2(&input()?);
-        println!("Part2 result: {}", result);
-        Ok(())
-    })?;
-    Ok(())
-}
+    fn part1(input: &(Vec<bool>, Vec<Vec<bool>>)) -> i64 {
+        plant_sum(input, 20)
+    }
 
-fn input() -> io::Result<Vec<String>> {
-    let f = File::open("src/day12/input.king")?;
-    let f = BufReader::new(f);
-    Ok(f.lines().map(|l| l.unwrap()).collect())
+    fn part2(input: &(Vec<bool>, Vec<Vec<bool>>)) -> i64 {
+        plant_sum(input, 50_000_000_000)
+    }
 }
 
 #[cfg(test)]
@@ -151,12 +144,29 @@ mod tests {
         ###.# => #
         ####. => #";
 
-    fn as_input(s: &str) -> Vec<String> {
-        s.split('\n').map(|s| s.trim().into()).collect()
+    fn as_input(s: &str) -> (Vec<bool>, Vec<Vec<bool>>) {
+        Day12::parse(s).unwrap()
     }
 
     #[test]
     fn test_part1() {
-        assert_eq!(part1(&as_input(INPUT)), 325);
+        assert_eq!(Day12::part1(&as_input(INPUT)), 325);
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert_eq!(Day12::parse("..#.. => #").err(), Some(ParseError::new("Missing initial state")));
+        let e = Day12::parse("initial state: #..#\n\n..#.. => #\n.#x.. => .").unwrap_err();
+        assert_eq!((e.line(), e.text()), (Some(4), Some(".#x..")));
+    }
+
+    #[test]
+    fn test_shifting_pattern() {
+        // The example settles into a pattern moving one pot to the right per generation
+        let input = as_input(INPUT);
+        assert_eq!(plant_sum(&input, 200) + 800 * 20, plant_sum(&input, 1000));
+        let plants = Plants::new(-3, &[false, true, false, true, false]);
+        assert_eq!(plants, Plants { first: -2, pots: vec![true, false, true] });
+        assert_eq!(plants.sum(), -2);
     }
 }
\ No newline at end of file
@@ -1,76 +1,120 @@
-extern crate utils;
-
 use std::error::Error;
-use std::collections::BTreeSet;
-use std::io::{self, BufReader};
-use std::io::prelude::*;
-use std::fs::File;
-use utils::*;
-
-struct Tracks {
-    grid: Vec<Vec<char>>
+use std::fmt;
+use std::io;
+use grid::Grid;
+use render::{Animation, Frames, Rgb};
+use {ParseError, Solution};
+
+pub type Pos = (usize, usize);
+
+pub struct Tracks {
+    grid: Grid<char>
 }
 
 impl Tracks {
-    fn parse_input(input: &Vec<String>) -> (Tracks, Vec<Cart>) {
-        let mut grid: Vec<Vec<char>> = vec![];
+    fn parse_input(input: &str) -> Result<(Tracks, Vec<Cart>), ParseError> {
         let mut carts: Vec<Cart> = vec![];
 
-        for y in 0..input.len() {
-            let l: Vec<_> = input[y].chars().collect();
-            let mut chars = vec![];
-
-            for x in 0..l.len() {
-                let c = l[x];
-                let c = match Dir::try_parse(c) {
-                    Some(dir) => {
-                        carts.push(Cart { pos: (x, y), dir: dir.to_owned(), turn: Turn::Left });
-                        match dir {
-                            Dir::Right => '-',
-                            Dir::Down  => '|',
-                            Dir::Left  => '-',
-                            Dir::Up    => '|'
-                        }
-                    },
-                    None => c
-                };
-
-                chars.push(c);
+        // Lines may lack the trailing spaces of the tracks
+        let w = input.lines().map(|l| l.chars().count()).max().unwrap_or(0);
+        let lines: Vec<String> = input.lines().map(|l| format!("{:<w$}", l, w = w)).collect();
+
+        let grid = Grid::parse_lines(lines.iter().map(|l| l.as_str()), |x, y, c| {
+            Ok(match Dir::try_parse(c) {
+                Some(dir) => {
+                    carts.push(Cart { id: carts.len(), pos: (x, y), dir, turn: Turn::Left });
+                    match dir {
+                        Dir::Right => '-',
+                        Dir::Down  => '|',
+                        Dir::Left  => '-',
+                        Dir::Up    => '|'
+                    }
+                },
+                None if " -|/\\+".contains(c) => c,
+                None => return Err(ParseError::expected("a track or cart", &c.to_string()))
+            })
+        })?;
+        let mut tracks = Tracks { grid };
+
+        // Carts going straight may also be on intersections
+        for cart in carts.iter() {
+            if Dir::all().iter().all(|&d| tracks.leads(cart.pos, d)) {
+                tracks.grid[cart.pos] = '+';
             }
-            grid.push(chars);
         }
-
-        (Tracks { grid: grid }, carts)
+        tracks.validate(&lines)?;
+        if carts.is_empty() {
+            return Err(ParseError::new("No carts on the tracks"));
+        }
+        Ok((tracks, carts))
     }
 
-    fn at(&self, x: usize, y: usize) -> char {
-        self.grid[y][x]
+    // Whether a cart going in the direction from the position gets onto a track it can go on
+    fn leads(&self, pos: Pos, dir: Dir) -> bool {
+        self.grid.offset(pos, dir.offset()).is_some_and(|n| match (self.grid[n], dir) {
+            ('-', Dir::Left) | ('-', Dir::Right) | ('|', Dir::Up) | ('|', Dir::Down) => true,
+            (c, _) => "+/\\".contains(c)
+        })
     }
 
-    #[cfg(feature = "print")]
-    fn print(&self, carts: &Vec<Cart>) {
-        for y in 0..self.grid.len() {
-            for x in 0..(self.grid[y].len()) {
-                let c = self.grid[y][x];
-                let c = carts.iter()
-                    .filter(|Cart { pos: (cx, cy), dir: _, turn: _ }| *cx == x && *cy == y)
-                    .map(Cart::as_char)
-                    .next()
-                    .unwrap_or(c);
-                print!("{}", c);
+    // Every track has to lead to tracks both ways, curves one of the two ways they may turn
+    fn validate(&self, lines: &[String]) -> Result<(), ParseError> {
+        use self::Dir::*;
+        for pos in self.grid.positions() {
+            let leads = |dirs: &[Dir]| dirs.iter().all(|&d| self.leads(pos, d));
+            let c = self.grid[pos];
+            let connected = match c {
+                '-' => leads(&[Left, Right]),
+                '|' => leads(&[Up, Down]),
+                '+' => leads(&Dir::all()),
+                '/' => leads(&[Right, Down]) || leads(&[Left, Up]),
+                '\\' => leads(&[Left, Down]) || leads(&[Right, Up]),
+                _ => true
+            };
+            if !connected {
+                return Err(ParseError::new(format!("Track '{}' at {},{} doesn't connect to the tracks around it", c, pos.0, pos.1))
+                    .at_line(pos.1 + 1)
+                    .in_text(lines[pos.1].trim_end()));
             }
-            println!();
         }
+        Ok(())
+    }
 
+    fn at(&self, pos: Pos) -> Option<char> {
+        self.grid.get(pos.0, pos.1).copied()
+    }
+
+    fn render(&self, carts: &[Cart]) -> Grid<Rgb> {
+        let mut grid = self.grid.map(|&c| match c {
+            ' ' => Rgb(16, 16, 24),
+            '+' => Rgb(160, 160, 170),
+            _ => Rgb(100, 100, 110)
+        });
+        for cart in carts.iter() {
+            grid[cart.pos] = Rgb(230, 40, 40);
+        }
+        grid
+    }
+
+    fn draw(&self, carts: &[Cart]) -> Grid<char> {
+        let mut grid = self.grid.clone();
+        for cart in carts.iter() {
+            grid[cart.pos] = cart.as_char();
+        }
+        grid
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Dir {
     Right, Down, Left, Up
 }
 
 impl Dir {
+    fn all() -> [Dir; 4] {
+        [Dir::Right, Dir::Down, Dir::Left, Dir::Up]
+    }
+
     fn try_parse(c: char) -> Option<Dir> {
         match c {
             '>' => Some(Dir::Right),
@@ -81,6 +125,15 @@ impl Dir {
         }
     }
 
+    fn offset(self) -> (i64, i64) {
+        match self {
+            Dir::Right => (1, 0),
+            Dir::Down  => (0, 1),
+            Dir::Left  => (-1, 0),
+            Dir::Up    => (0, -1)
+        }
+    }
+
     fn turn(self, turn: Turn) -> Dir {
         match (self, turn) {
             (Dir::Right, Turn::Left)  => Dir::Up,
@@ -101,15 +154,16 @@ enum Turn {
     Left, Straight, Right
 }
 
+// Carts are numbered in reading order of where they start
 #[derive(Debug, Clone)]
-struct Cart {
-    pos: (usize, usize),
+pub struct Cart {
+    id: usize,
+    pos: Pos,
     dir: Dir,
     turn: Turn
 }
 
 impl Cart {
-    #[cfg(feature = "print")]
     fn as_char(&self) -> char {
         match self.dir {
             Dir::Right => '>',
@@ -119,16 +173,10 @@ impl Cart {
         }
     }
 
-    fn move_one(&mut self, tracks: &Tracks) {
-        let (cx, cy) = self.pos;
-        let (nx, ny) = match self.dir {
-            Dir::Right => (cx + 1, cy),
-            Dir::Down  => (cx, cy + 1),
-            Dir::Left  => (cx - 1, cy),
-            Dir::Up    => (cx, cy - 1)
-        };
-
-        match (tracks.at(nx, ny), self.dir) {
+    // Moves on to the next track, None if there is no track to go on to
+    fn move_one(&mut self, tracks: &Tracks) -> Option<Pos> {
+        let next = tracks.grid.offset(self.pos, self.dir.offset())?;
+        match (tracks.at(next)?, self.dir) {
             ('+', _) => {
                 self.dir = self.dir.turn(self.turn);
                 self.turn = match self.turn {
@@ -145,119 +193,198 @@ impl Cart {
             ('\\', Dir::Left)  => self.dir = Dir::Up,
             ('\\', Dir::Down)  => self.dir = Dir::Right,
             ('\\', Dir::Right) => self.dir = Dir::Down,
-            _ => {}
+            ('-', Dir::Left) | ('-', Dir::Right) | ('|', Dir::Up) | ('|', Dir::Down) => {},
+            _ => return None
         }
 
-        self.pos = (nx, ny);
+        self.pos = next;
+        Some(next)
     }
 }
 
-fn first_chrash_pos(tracks: &Tracks, carts: &Vec<Cart>) -> (usize, usize) {
-    (0..)
-        .scan((carts.to_vec(), false), |(ref mut carts, ref mut collided), _i| {
-            #[cfg(feature = "print")] {
-                println!("Iteration {}:", _i);
-                tracks.print(&carts);
-            }
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Collision {
+    pub tick: usize,
+    pub pos: Pos,
+    // The cart that moved and the one it hit
+    pub carts: (usize, usize)
+}
 
-            if *collided {
-                return None
-            }
+// A cart running off the tracks, which the layout checks of parsing don't rule out entirely as
+// curves may turn either way
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Derailment {
+    pub tick: usize,
+    pub cart: usize,
+    pub pos: Pos
+}
 
-            (*carts).sort_unstable_by(|a, b| a.pos.1.cmp(&b.pos.1).then(a.pos.0.cmp(&b.pos.0)));
+impl fmt::Display for Derailment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Cart {} derailed at {},{} in tick {}", self.cart, self.pos.0, self.pos.1, self.tick)
+    }
+}
 
-            for i in 0..carts.len() {
-                carts[i].move_one(tracks);
-                for j in 0..carts.len() {
-                    if i != j && carts[i].pos == carts[j].pos {
-                        *collided = true;
-                        return Some(Some(carts[i].pos))
-                    }
-                }
-            }
-            Some(None)
-        })
-        .last()
-        .unwrap()
-        .unwrap()
+impl Error for Derailment {}
+
+// Why no carts crash for part 1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FirstCrashError {
+    Derailed(Derailment),
+    // A single cart has nothing to crash into
+    NoCollision
 }
 
-fn part1(input: &Vec<String>) -> (usize, usize) {
-    let (tracks, carts) = Tracks::parse_input(input);
-    first_chrash_pos(&tracks, &carts)
+impl fmt::Display for FirstCrashError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FirstCrashError::Derailed(d) => d.fmt(f),
+            FirstCrashError::NoCollision => write!(f, "Fewer than two carts, no collision is possible")
+        }
+    }
 }
 
-fn last_remaining_pos(tracks: &Tracks, carts: &Vec<Cart>) -> (usize, usize) {
-    (0..)
-        .scan(carts.to_vec(), |carts, _i| {
-            #[cfg(feature = "print")] {
-                println!("Iteration {}:", _i);
-                tracks.print(&carts);
-            }
-            if carts.len() == 1 {
-                return None
-            }
+impl Error for FirstCrashError {}
 
-            (*carts).sort_unstable_by(|a, b| a.pos.1.cmp(&b.pos.1).then(a.pos.0.cmp(&b.pos.0)));
-
-            let mut collided_idx = BTreeSet::new();
-            for i in 0..carts.len() {
-                if !collided_idx.contains(&i) {
-                    carts[i].move_one(tracks);
-                    for j in 0..carts.len() {
-                        if i != j && !collided_idx.contains(&j) && carts[i].pos == carts[j].pos {
-                            collided_idx.insert(i);
-                            collided_idx.insert(j);
-                        }
-                    }
-                }
+// Why no cart is left at the end for part 2
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LastCartError {
+    Derailed(Derailment),
+    // Carts crash in pairs, so with an even number of them all may crash
+    AllCrashed
+}
+
+impl fmt::Display for LastCartError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LastCartError::Derailed(d) => d.fmt(f),
+            LastCartError::AllCrashed => write!(f, "All carts crashed, none is left")
+        }
+    }
+}
+
+impl Error for LastCartError {}
+
+// Moves the carts tick by tick, crashed ones are removed
+pub struct Simulation<'a> {
+    tracks: &'a Tracks,
+    carts: Vec<Cart>,
+    ticks: usize,
+    collisions: Vec<Collision>
+}
+
+impl<'a> Simulation<'a> {
+    pub fn new(tracks: &'a Tracks, carts: &[Cart]) -> Simulation<'a> {
+        Simulation { tracks, carts: carts.to_vec(), ticks: 0, collisions: vec![] }
+    }
+
+    pub fn carts(&self) -> &[Cart] {
+        &self.carts
+    }
+
+    pub fn ticks(&self) -> usize {
+        self.ticks
+    }
+
+    // All collisions so far, in the order they happened
+    pub fn collisions(&self) -> &[Collision] {
+        &self.collisions
+    }
+
+    // Moves every cart once in order, returning the collisions of the tick
+    pub fn tick(&mut self) -> Result<&[Collision], Derailment> {
+        trace!("Tick {}:\n{}", self.ticks, self.tracks.draw(&self.carts));
+        self.ticks += 1;
+        self.carts.sort_unstable_by_key(|c| (c.pos.1, c.pos.0));
+
+        let before = self.collisions.len();
+        let mut crashed = vec![false; self.carts.len()];
+        for i in 0..self.carts.len() {
+            if crashed[i] {
+                continue;
             }
-            for (i, idx) in collided_idx.iter().enumerate() {
-                (*carts).remove(idx - i);
+            let pos = self.carts[i].move_one(self.tracks)
+                .ok_or(Derailment { tick: self.ticks, cart: self.carts[i].id, pos: self.carts[i].pos })?;
+            if let Some(j) = (0..self.carts.len()).find(|&j| j != i && !crashed[j] && self.carts[j].pos == pos) {
+                crashed[i] = true;
+                crashed[j] = true;
+                let c = Collision { tick: self.ticks, pos, carts: (self.carts[i].id, self.carts[j].id) };
+                debug!("{:?}", c);
+                self.collisions.push(c);
             }
+        }
+        let mut crashed = crashed.into_iter();
+        self.carts.retain(|_| !crashed.next().unwrap());
+        Ok(&self.collisions[before..])
+    }
+}
 
-            if carts.len() == 1 {
-                Some(Some(carts[0].pos))
-            } else {
-                Some(None)
-            }
-        })
-        .last()
-        .unwrap()
-        .unwrap()
+fn first_crash_pos(tracks: &Tracks, carts: &[Cart]) -> Result<Pos, FirstCrashError> {
+    let mut sim = Simulation::new(tracks, carts);
+    while sim.carts().len() > 1 {
+        if let Some(c) = sim.tick().map_err(FirstCrashError::Derailed)?.first() {
+            return Ok(c.pos);
+        }
+    }
+    Err(FirstCrashError::NoCollision)
 }
 
-fn part2(input: &Vec<String>) -> (usize, usize) {
-    let (tracks, carts) = Tracks::parse_input(input);
-    last_remaining_pos(&tracks, &carts)
+// Position of the last cart once all others have crashed
+fn last_remaining_pos(tracks: &Tracks, carts: &[Cart]) -> Result<Pos, LastCartError> {
+    let mut sim = Simulation::new(tracks, carts);
+    while sim.carts().len() > 1 {
+        sim.tick().map_err(LastCartError::Derailed)?;
+    }
+    sim.carts().first().map(|c| c.pos).ok_or(LastCartError::AllCrashed)
 }
 
-fn main() -> Result<(), Box<Error>> {
-    measure_exec(|| {
-        let result = part1(&input()?);
-        println!("Part1 result: {:?}", result);
-        Ok(())
-    })?;
-    measure_exec(|| {
-        let result = part2(&input()?);
-        println!("Part2 result: {:?}", result);
-        Ok(())
-    })?;
-    Ok(())
+pub struct Day13;
+
+impl Solution for Day13 {
+    type Input = (Tracks, Vec<Cart>);
+    type Answer1 = Result<String, FirstCrashError>;
+    type Answer2 = Result<String, LastCartError>;
+
+    fn parse(input: &str) -> Result<(Tracks, Vec<Cart>), ParseError> {
+        Tracks::parse_input(input)
+    }
+
+    fn part1((tracks, carts): &(Tracks, Vec<Cart>)) -> Result<String, FirstCrashError> {
+        first_crash_pos(tracks, carts).map(|(x, y)| format!("{},{}", x, y))
+    }
+
+    fn part2((tracks, carts): &(Tracks, Vec<Cart>)) -> Result<String, LastCartError> {
+        last_remaining_pos(tracks, carts).map(|(x, y)| format!("{},{}", x, y))
+    }
 }
 
-fn input() -> io::Result<Vec<String>> {
-    let f = File::open("src/day13/input")?;
-    let f = BufReader::new(f);
-    Ok(f.lines().map(|l| l.unwrap()).collect())
+// The carts until only one is left, crashed ones disappear
+impl Animation for Day13 {
+    fn animate((tracks, carts): &(Tracks, Vec<Cart>), frames: &mut Frames) -> io::Result<()> {
+        let mut sim = Simulation::new(tracks, carts);
+        while sim.carts().len() > 1 {
+            frames.tick(|| tracks.render(sim.carts()))?;
+            sim.tick().map_err(|d| io::Error::new(io::ErrorKind::InvalidData, d.to_string()))?;
+        }
+        frames.last(|| tracks.render(sim.carts()))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use days::{self, Part};
+
+    // The tracks between the dots of each line
+    fn strip_dots(s: &str) -> String {
+        let lines: Vec<String> = s.split('\n')
+            .map(|s| s.trim().chars().skip(1).take_while(|&c| c != '.').collect())
+            .collect();
+        lines.join("\n")
+    }
 
-    fn as_input(s: &str) -> Vec<String> {
-        s.split('\n').map(|s| s.trim().to_string().chars().skip(1).take_while(|&c| c != '.').collect()).collect()
+    fn as_input(s: &str) -> (Tracks, Vec<Cart>) {
+        Day13::parse(&strip_dots(s)).unwrap()
     }
 
     const INPUT1: &'static str =
@@ -270,7 +397,9 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        assert_eq!(part1(&as_input(INPUT1)), (7, 3));
+        let (tracks, carts) = as_input(INPUT1);
+        assert_eq!(first_crash_pos(&tracks, &carts), Ok((7, 3)));
+        assert_eq!(Day13::part1(&as_input(INPUT1)), Ok("7,3".to_string()));
     }
 
     const INPUT2: &'static str =
@@ -284,6 +413,77 @@ mod tests {
 
     #[test]
     fn test_part2() {
-        assert_eq!(part2(&as_input(INPUT2)), (6, 4));
+        let (tracks, carts) = as_input(INPUT2);
+        assert_eq!(last_remaining_pos(&tracks, &carts), Ok((6, 4)));
+        assert_eq!(Day13::part2(&as_input(INPUT2)), Ok("6,4".to_string()));
+    }
+
+    #[test]
+    fn test_collisions() {
+        let (tracks, carts) = as_input(INPUT2);
+        let mut sim = Simulation::new(&tracks, &carts);
+        while sim.carts().len() > 1 {
+            sim.tick().unwrap();
+        }
+        let collision = |tick, pos, carts| Collision { tick, pos, carts };
+        assert_eq!(sim.collisions(), &[
+            collision(1, (2, 0), (1, 0)),
+            collision(1, (2, 4), (5, 4)),
+            collision(1, (6, 4), (6, 3)),
+            collision(3, (2, 4), (7, 2))
+        ]);
+        assert_eq!((sim.ticks(), sim.carts()[0].id), (3, 8));
+    }
+
+    #[test]
+    fn test_validation() {
+        let parse = |s: &str| Day13::parse(s).err();
+        assert_eq!(parse("/>\\\n\\-/"), None);
+        assert_eq!(parse("/>-\\\n\\-/"), Some(ParseError::new("Track '\\' at 3,0 doesn't connect to the tracks around it").at_line(1).in_text("/>-\\")));
+        assert_eq!(parse("/-\\\n|^+-\n\\-/").unwrap().line(), Some(2));
+        assert!(parse("/-\\\n| |\n\\- ").is_some());
+        assert!(parse("->").is_some());
+        assert_eq!(parse("/-\\\n\\-/"), Some(ParseError::new("No carts on the tracks")));
+
+        // A cart on an intersection
+        let (tracks, _) = Day13::parse(
+            "  /-\\\n\
+             /-<-+-\\\n\
+             | \\-/ |\n\
+             \\-----/").unwrap();
+        assert_eq!(tracks.at((2, 1)), Some('+'));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_derailment() {
+        // Tracks that wouldn't pass the checks of parsing
+        let tracks = Tracks { grid: Grid::parse("--| --", |_, _, c| Ok(c)).unwrap() };
+        let carts = vec![
+            Cart { id: 0, pos: (0, 0), dir: Dir::Right, turn: Turn::Left },
+            Cart { id: 1, pos: (4, 0), dir: Dir::Right, turn: Turn::Left }
+        ];
+        let derailment = Derailment { tick: 2, cart: 0, pos: (1, 0) };
+        assert_eq!(first_crash_pos(&tracks, &carts), Err(FirstCrashError::Derailed(derailment.clone())));
+        assert_eq!(derailment.to_string(), "Cart 0 derailed at 1,0 in tick 2");
+        assert_eq!(last_remaining_pos(&tracks, &carts), Err(LastCartError::Derailed(derailment)));
+        assert_eq!(last_remaining_pos(&tracks, &carts[..0]), Err(LastCartError::AllCrashed));
+        assert_eq!(first_crash_pos(&tracks, &carts[..1]), Err(FirstCrashError::NoCollision));
+    }
+
+    #[test]
+    fn test_solve_errors() {
+        let day = days::find(13).unwrap();
+        // The curve right of the first cart also connects to the tracks right of and below it,
+        // which passes the checks of parsing, but the cart turns up off the tracks
+        let input = day.parse("/->/\\\n|  ||\n\\<-//").unwrap();
+        assert_eq!(day.solve(&input, Part::One).unwrap_err().to_string(), "Cart 0 derailed at 3,0 in tick 2");
+        // The two carts of part 1 crash into each other
+        let input = day.parse(&strip_dots(INPUT1)).unwrap();
+        assert_eq!(day.solve(&input, Part::One).unwrap(), "7,3");
+        assert_eq!(day.solve(&input, Part::Two).unwrap_err().to_string(), "All carts crashed, none is left");
+        // A single cart goes around forever
+        let input = day.parse("/>\\\n\\-/").unwrap();
+        assert_eq!(day.solve(&input, Part::One).unwrap_err().to_string(), "Fewer than two carts, no collision is possible");
+        assert_eq!(day.solve(&input, Part::Two).unwrap(), "1,0");
+    }
+}
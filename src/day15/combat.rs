@@ -0,0 +1,654 @@
+// A configurable simulation of the cave combat of day 15. Any number of factions can take part,
+// each with its own hit points and attack power, fighting all factions they aren't allied with.
+// The movement, targeting and turn order rules of the puzzle apply to all of them
+use std::collections::HashSet;
+use grid::Grid;
+use point::Point;
+use render::Rgb;
+use search;
+use ParseError;
+
+pub type Pos = Point<2>;
+pub type FactionId = usize;
+pub type UnitId = usize;
+
+// The factions of Rules::elves_and_goblins
+pub const ELF: FactionId = 0;
+pub const GOBLIN: FactionId = 1;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Faction {
+    pub name: String,
+    pub glyph: char,
+    pub hp: i32,
+    pub attack: i32
+}
+
+impl Faction {
+    // A faction with the hit points and attack power of the puzzle
+    pub fn new<S: Into<String>>(name: S, glyph: char) -> Faction {
+        Faction { name: name.into(), glyph, hp: 200, attack: 3 }
+    }
+
+    pub fn with_hp(mut self, hp: i32) -> Faction {
+        self.hp = hp;
+        self
+    }
+
+    pub fn with_attack(mut self, attack: i32) -> Faction {
+        self.attack = attack;
+        self
+    }
+}
+
+// The factions taking part and which of them are allied, every faction is allied with itself
+#[derive(Clone, PartialEq, Debug)]
+pub struct Rules {
+    factions: Vec<Faction>,
+    allied: Vec<Vec<bool>>
+}
+
+impl Rules {
+    pub fn new(factions: Vec<Faction>) -> Rules {
+        let n = factions.len();
+        Rules { factions, allied: (0..n).map(|a| (0..n).map(|b| a == b).collect()).collect() }
+    }
+
+    // The two factions of the puzzle, ELF and GOBLIN
+    pub fn elves_and_goblins() -> Rules {
+        Rules::new(vec![Faction::new("Elf", 'E'), Faction::new("Goblin", 'G')])
+    }
+
+    pub fn with_alliance(mut self, a: FactionId, b: FactionId) -> Rules {
+        self.allied[a][b] = true;
+        self.allied[b][a] = true;
+        self
+    }
+
+    pub fn with_attack(mut self, f: FactionId, attack: i32) -> Rules {
+        self.factions[f].attack = attack;
+        self
+    }
+
+    pub fn factions(&self) -> &[Faction] {
+        &self.factions
+    }
+
+    pub fn faction(&self, f: FactionId) -> &Faction {
+        &self.factions[f]
+    }
+
+    pub fn by_glyph(&self, c: char) -> Option<FactionId> {
+        self.factions.iter().position(|f| f.glyph == c)
+    }
+
+    pub fn are_enemies(&self, a: FactionId, b: FactionId) -> bool {
+        !self.allied[a][b]
+    }
+}
+
+// Walls and open ground, and where the units of which faction start in reading order
+#[derive(Clone, Debug)]
+pub struct Map {
+    grid: Grid<char>,
+    units: Vec<(FactionId, Pos)>
+}
+
+impl Map {
+    // Units are drawn with the glyphs of the factions of the rules
+    pub fn parse(input: &str, rules: &Rules) -> Result<Map, ParseError> {
+        let mut units = vec![];
+        let grid = Grid::parse(input, |x, y, c| {
+            match (rules.by_glyph(c), c) {
+                (Some(f), _) => {
+                    units.push((f, Point([x as i64, y as i64])));
+                    Ok('.')
+                },
+                (None, '#') | (None, '.') => Ok(c),
+                _ => {
+                    let glyphs: Vec<String> = rules.factions().iter().map(|f| format!("'{}'", f.glyph)).collect();
+                    Err(ParseError::expected(format!("'#', '.' or a unit {}", glyphs.join(", ")), &c.to_string()))
+                }
+            }
+        })?;
+        Ok(Map { grid, units })
+    }
+
+    pub fn is_open(&self, p: Pos) -> bool {
+        self.grid.get_signed(p.x(), p.y()) == Some(&'.')
+    }
+
+    // The walls and open ground, without the units
+    pub fn grid(&self) -> &Grid<char> {
+        &self.grid
+    }
+
+    pub fn units(&self) -> &[(FactionId, Pos)] {
+        &self.units
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Unit {
+    pub id: UnitId,
+    pub faction: FactionId,
+    pub pos: Pos,
+    pub hp: i32,
+    pub attack: i32
+}
+
+impl Unit {
+    pub fn is_alive(&self) -> bool {
+        self.hp > 0
+    }
+}
+
+// What a unit did during its turn
+#[derive(Clone, PartialEq, Debug)]
+pub enum Event {
+    Move { unit: UnitId, from: Pos, to: Pos },
+    Attack { unit: UnitId, target: UnitId, damage: i32 },
+    Death { unit: UnitId, by: UnitId, pos: Pos }
+}
+
+// The full rounds played and the hit points left, and whether the combat is over or was stopped
+#[derive(Clone, PartialEq, Debug)]
+pub struct Outcome {
+    pub rounds: usize,
+    pub hp_left: i32,
+    pub over: bool
+}
+
+impl Outcome {
+    pub fn score(&self) -> i32 {
+        self.rounds as i32 * self.hp_left
+    }
+}
+
+// How units find their way to the enemies
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Movement {
+    // A single breadth first search per move
+    SingleBfs,
+    // Two flood fills per move, one to choose the target and one to find the step towards it, and
+    // scanning the units for every position checked. The original solution, as a reference
+    FloodFills
+}
+
+// Called with the number of the round being played for every event
+pub type Observer<'a> = Box<dyn FnMut(usize, &Event) + 'a>;
+
+pub struct CombatSim<'a> {
+    map: &'a Map,
+    rules: Rules,
+    movement: Movement,
+    units: Vec<Unit>,
+    // The unit at every position, which may have died since
+    occupied: Grid<Option<UnitId>>,
+    rounds: usize,
+    // Units yet to take their turn in the current round, the next one last
+    queue: Vec<UnitId>,
+    over: bool,
+    observers: Vec<Observer<'a>>
+}
+
+impl<'a> CombatSim<'a> {
+    // The units of the map with the hit points and attack power of their faction, the rules
+    // need to have the factions the map was parsed with
+    pub fn new(map: &'a Map, rules: Rules) -> CombatSim<'a> {
+        let units = map.units.iter().enumerate()
+            .map(|(id, &(f, pos))| {
+                let faction = rules.faction(f);
+                Unit { id, faction: f, pos, hp: faction.hp, attack: faction.attack }
+            })
+            .collect::<Vec<Unit>>();
+        let mut occupied = Grid::new(map.grid.width(), map.grid.height(), None);
+        for u in units.iter() {
+            occupied[u.pos] = Some(u.id);
+        }
+        CombatSim {
+            map,
+            rules,
+            movement: Movement::SingleBfs,
+            units,
+            occupied,
+            rounds: 0,
+            queue: vec![],
+            over: false,
+            observers: vec![]
+        }
+    }
+
+    pub fn with_movement(mut self, movement: Movement) -> CombatSim<'a> {
+        self.movement = movement;
+        self
+    }
+
+    pub fn observe<F: FnMut(usize, &Event) + 'a>(&mut self, observer: F) {
+        self.observers.push(Box::new(observer));
+    }
+
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    pub fn units(&self) -> &[Unit] {
+        &self.units
+    }
+
+    pub fn unit(&self, id: UnitId) -> &Unit {
+        &self.units[id]
+    }
+
+    // To give units their own hit points or attack power
+    pub fn set_hp(&mut self, id: UnitId, hp: i32) {
+        self.units[id].hp = hp;
+    }
+
+    pub fn set_attack(&mut self, id: UnitId, attack: i32) {
+        self.units[id].attack = attack;
+    }
+
+    pub fn alive(&self) -> impl Iterator<Item = &Unit> {
+        self.units.iter().filter(|u| u.is_alive())
+    }
+
+    pub fn rounds(&self) -> usize {
+        self.rounds
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.over
+    }
+
+    pub fn casualties(&self, f: FactionId) -> usize {
+        self.units.iter().filter(|u| u.faction == f && !u.is_alive()).count()
+    }
+
+    pub fn outcome(&self) -> Outcome {
+        Outcome { rounds: self.rounds, hp_left: self.alive().map(|u| u.hp).sum(), over: self.over }
+    }
+
+    fn emit(&mut self, e: Event) {
+        let round = self.rounds + 1;
+        for o in self.observers.iter_mut() {
+            o(round, &e);
+        }
+    }
+
+    // Whether any units left are enemies
+    fn is_hostile(&self) -> bool {
+        let factions: HashSet<FactionId> = self.alive().map(|u| u.faction).collect();
+        factions.iter().any(|&a| factions.iter().any(|&b| self.rules.are_enemies(a, b)))
+    }
+
+    fn enemies(&self, id: UnitId) -> impl Iterator<Item = &Unit> {
+        let f = self.units[id].faction;
+        self.alive().filter(move |u| self.rules.are_enemies(f, u.faction))
+    }
+
+    // The living unit at the position
+    fn occupant(&self, p: Pos) -> Option<UnitId> {
+        self.occupied.get_signed(p.x(), p.y())
+            .and_then(|&o| o)
+            .filter(|&id| self.units[id].is_alive())
+    }
+
+    fn is_open(&self, p: Pos) -> bool {
+        self.map.is_open(p) && self.occupant(p).is_none()
+    }
+
+    fn is_enemy_of(&self, id: UnitId, other: Option<UnitId>) -> bool {
+        other.is_some_and(|o| self.rules.are_enemies(self.units[id].faction, self.units[o].faction))
+    }
+
+    fn adjacent_enemies(&self, id: UnitId) -> Vec<UnitId> {
+        self.units[id].pos.neighbours().into_iter()
+            .map(|p| self.occupant(p))
+            .filter(|&o| self.is_enemy_of(id, o))
+            .flatten()
+            .collect()
+    }
+
+    // Where the unit moves towards the nearest position in range of an enemy, the one first in
+    // reading order of the nearest ones, taking the step first in reading order of the shortest
+    // paths there
+    fn next_step(&self, id: UnitId) -> Option<Pos> {
+        match self.movement {
+            Movement::SingleBfs => self.step_towards_enemy(id),
+            Movement::FloodFills => self.enemy_choosen_min_step_dists(id)
+                .and_then(|(candidate_pos, _)| candidate_pos.into_iter().min())
+        }
+    }
+
+    // A breadth first search from the unit that remembers the first step towards each position.
+    // The positions at each distance are visited ordered by their first steps, starting from the
+    // ones next to the unit in reading order, so each position gets the first step of its
+    // shortest paths that is first in reading order
+    fn step_towards_enemy(&self, id: UnitId) -> Option<Pos> {
+        let in_range = |p: Pos| p.neighbours().into_iter().any(|n| self.is_enemy_of(id, self.occupant(n)));
+        let mut visited = Grid::new(self.occupied.width(), self.occupied.height(), false);
+        let mut frontier: Vec<(Pos, Pos)> = self.units[id].pos.neighbours().into_iter()
+            .filter(|&p| self.is_open(p))
+            .map(|p| (p, p))
+            .collect();
+        for &(p, _) in frontier.iter() {
+            visited[p] = true;
+        }
+
+        while !frontier.is_empty() {
+            if let Some(&(_, step)) = frontier.iter().filter(|&&(p, _)| in_range(p)).min_by_key(|&&(p, _)| p) {
+                return Some(step);
+            }
+            let mut next = vec![];
+            for &(p, step) in frontier.iter() {
+                for n in p.neighbours() {
+                    if self.is_open(n) && !visited[n] {
+                        visited[n] = true;
+                        next.push((n, step));
+                    }
+                }
+            }
+            frontier = next;
+        }
+        None
+    }
+
+    // Open without an occupancy grid, as originally
+    fn is_open_scanning(&self, p: Pos) -> bool {
+        self.map.is_open(p) && !self.alive().any(|u| u.pos == p)
+    }
+
+    fn enemy_inrange_pos(&self, id: UnitId) -> Vec<Pos> {
+        self.enemies(id)
+            .flat_map(|e| e.pos.neighbours().into_iter().filter(|p| self.is_open_scanning(*p)))
+            .collect()
+    }
+
+    // Steps from a position to every open position reachable from it
+    fn distances(&self, from: Pos) -> search::Paths<Pos, usize> {
+        search::bfs(from, |p| p.neighbours().into_iter().filter(|&n| self.is_open_scanning(n)).collect::<Vec<_>>())
+    }
+
+    fn enemy_reachable_pos(&self, id: UnitId) -> Vec<(Pos, usize)> {
+        let distances = self.distances(self.units[id].pos);
+        self.enemy_inrange_pos(id).into_iter()
+            .flat_map(|p| distances.cost(&p).map(|d| (p, d)))
+            .collect()
+    }
+
+    fn enemy_choosen_pos(&self, id: UnitId) -> Option<Pos> {
+        self.enemy_reachable_pos(id).iter()
+            .min_by(|(p1, p1d), (p2, p2d)| p1d.cmp(p2d).then(p1.cmp(p2)))
+            .map(|(p, _)| *p)
+    }
+
+    fn enemy_choosen_min_step_dists(&self, id: UnitId) -> Option<(Vec<Pos>, usize)> {
+        self.enemy_choosen_pos(id)
+            .map(|p| self.min_distances(p, self.units[id].pos))
+    }
+
+    // Positions next to end_pos closest to start_pos, and their distance
+    fn min_distances(&self, start_pos: Pos, end_pos: Pos) -> (Vec<Pos>, usize) {
+        let distances = self.distances(start_pos);
+        let adj_w_dst: Vec<_> = end_pos.neighbours().iter()
+            .flat_map(|ap| distances.cost(ap).map(|dst| (*ap, dst)))
+            .collect();
+
+        let min_dst = adj_w_dst.iter().map(|(_, d)| *d).min().unwrap();
+        (adj_w_dst.iter().filter(|(_, d)| *d == min_dst).map(|(ap, _)| *ap).collect(), min_dst)
+    }
+
+    fn take_turn(&mut self, id: UnitId) {
+        // Move if no adjacent enemies
+        if self.adjacent_enemies(id).is_empty() {
+            if let Some(to) = self.next_step(id) {
+                let from = self.units[id].pos;
+                self.units[id].pos = to;
+                self.occupied[from] = None;
+                self.occupied[to] = Some(id);
+                self.emit(Event::Move { unit: id, from, to });
+            }
+        }
+
+        // Attack if there are adjacent enemies, the weakest first
+        let target = self.adjacent_enemies(id).into_iter()
+            .min_by(|&a, &b| {
+                self.units[a].hp.cmp(&self.units[b].hp)
+                    .then(self.units[a].pos.cmp(&self.units[b].pos))
+            });
+        if let Some(target) = target {
+            let damage = self.units[id].attack;
+            self.units[target].hp -= damage;
+            self.emit(Event::Attack { unit: id, target, damage });
+            if !self.units[target].is_alive() {
+                let pos = self.units[target].pos;
+                self.emit(Event::Death { unit: target, by: id, pos });
+            }
+        }
+    }
+
+    // Plays the turn of the next unit, false once the combat is over as no enemies are left
+    pub fn step(&mut self) -> bool {
+        if self.over {
+            return false;
+        }
+        if self.queue.is_empty() {
+            let mut order: Vec<&Unit> = self.alive().collect();
+            order.sort_by_key(|u| u.pos);
+            self.queue = order.iter().rev().map(|u| u.id).collect();
+        }
+        let id = match self.queue.pop() {
+            Some(id) if self.is_hostile() => id,
+            _ => {
+                self.over = true;
+                return false;
+            }
+        };
+
+        if self.enemies(id).next().is_some() {
+            self.take_turn(id);
+        }
+
+        // The round is complete once no living unit is left to take its turn
+        while self.queue.last().is_some_and(|&id| !self.units[id].is_alive()) {
+            self.queue.pop();
+        }
+        if self.queue.is_empty() {
+            self.rounds += 1;
+        }
+        true
+    }
+
+    // Plays the rest of the round, false if the combat ended before it was complete
+    pub fn round(&mut self) -> bool {
+        let rounds = self.rounds;
+        while self.rounds == rounds {
+            if !self.step() {
+                return false;
+            }
+        }
+        true
+    }
+
+    pub fn run(&mut self) -> Outcome {
+        self.run_until(|_| false)
+    }
+
+    // Plays until the combat is over or stop holds after a turn
+    pub fn run_until<P: FnMut(&CombatSim<'a>) -> bool>(&mut self, mut stop: P) -> Outcome {
+        while self.step() && !stop(self) {}
+        self.outcome()
+    }
+
+    // Units are drawn darker the more hit points they lost
+    pub fn render(&self) -> Grid<Rgb> {
+        const COLOURS: [(i32, i32, i32); 6] =
+            [(40, 200, 60), (220, 40, 40), (60, 90, 230), (230, 200, 40), (170, 60, 200), (40, 190, 200)];
+        let mut grid = self.map.grid.map(|&c| if c == '#' { Rgb(70, 50, 40) } else { Rgb(200, 180, 140) });
+        for u in self.alive() {
+            let full = self.rules.faction(u.faction).hp.max(1);
+            let shade = |v: i32| (v * (55 + 200 * u.hp.min(full) / full) / 255) as u8;
+            let (r, g, b) = COLOURS[u.faction % COLOURS.len()];
+            grid[u.pos] = Rgb(shade(r), shade(g), shade(b));
+        }
+        grid
+    }
+
+    pub fn draw(&self) -> Grid<char> {
+        self.draw_marked(&[], ' ')
+    }
+
+    // The map with the units, and mark at the given positions
+    fn draw_marked(&self, marked: &[Pos], mark: char) -> Grid<char> {
+        let mut grid = self.map.grid.clone();
+        for u in self.alive() {
+            grid[u.pos] = self.rules.faction(u.faction).glyph;
+        }
+        for p in marked.iter() {
+            grid[*p] = mark;
+        }
+        grid
+    }
+}
+
+// A square cave of elves and goblins for benchmarks, the same for the same seed. Walls within
+// are only at even positions so all open positions are connected and the combat ends
+pub fn synthetic_map(size: usize, seed: u64) -> String {
+    // xorshift64
+    let mut state = seed.max(1);
+    let mut next = || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state % 100
+    };
+    let mut map = String::with_capacity(size * (size + 1));
+    for y in 0..size {
+        for x in 0..size {
+            let border = x == 0 || y == 0 || x == size - 1 || y == size - 1;
+            map.push(match next() {
+                _ if border => '#',
+                0..=39 if x % 2 == 0 && y % 2 == 0 => '#',
+                0..=1 => 'E',
+                2..=3 => 'G',
+                _ => '.'
+            });
+        }
+        map.push('\n');
+    }
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    fn as_map(s: &str) -> Map {
+        Map::parse(s, &Rules::elves_and_goblins()).unwrap()
+    }
+
+    // The steps of choosing where the unit moves, with AOC_LOG=day15=trace
+    fn trace_move(c: &CombatSim, id: UnitId) {
+        if !log_enabled!(Trace) {
+            return;
+        }
+        trace!("In range:\n{}", c.draw_marked(&c.enemy_inrange_pos(id), '?'));
+        let reachable_pos: Vec<Pos> = c.enemy_reachable_pos(id).iter().map(|(p, _)| *p).collect();
+        trace!("Reachable:\n{}", c.draw_marked(&reachable_pos, '@'));
+        trace!("Choosen:\n{}", c.draw_marked(&[c.enemy_choosen_pos(id).unwrap()], '+'));
+        let (min_dist_pos, min_dist) = c.enemy_choosen_min_step_dists(id).unwrap();
+        trace!("Min dists:\n{}", c.draw_marked(&min_dist_pos, (b'0' + min_dist as u8) as char));
+    }
+
+    #[test]
+    fn test_step1() {
+        let map = as_map(
+            "#######
+             #E..G.#
+             #...#.#
+             #.G.#G#
+             #######");
+        let mut c = CombatSim::new(&map, Rules::elves_and_goblins());
+        trace_move(&c, 0);
+        c.take_turn(0);
+        trace!("Step:\n{}", c.draw());
+        assert_eq!(c.unit(0).pos, Point([2, 1]));
+    }
+
+    #[test]
+    fn test_step2() {
+        let map = as_map(
+            "#######
+             #.E...#
+             #.....#
+             #...G.#
+             #######");
+        let mut c = CombatSim::new(&map, Rules::elves_and_goblins());
+        trace_move(&c, 1);
+        c.take_turn(1);
+        trace!("Step:\n{}", c.draw());
+        assert_eq!(c.unit(1).pos, Point([4, 2]));
+    }
+
+    #[test]
+    fn test_movements() {
+        for seed in 1..=6 {
+            let map = as_map(&synthetic_map(24, seed));
+            let play = |movement| {
+                let mut c = CombatSim::new(&map, Rules::elves_and_goblins()).with_movement(movement);
+                let outcome = c.run();
+                (outcome, c.units().to_vec())
+            };
+            assert_eq!(play(Movement::SingleBfs), play(Movement::FloodFills), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn test_events() {
+        let map = as_map("#E.G#");
+        let events = RefCell::new(vec![]);
+        let mut c = CombatSim::new(&map, Rules::elves_and_goblins());
+        c.observe(|round, e| events.borrow_mut().push((round, e.clone())));
+        c.set_attack(0, 100);
+        c.set_hp(1, 150);
+
+        assert!(c.round());
+        let outcome = c.run();
+        drop(c);
+        assert_eq!(outcome, Outcome { rounds: 2, hp_left: 197, over: true });
+        assert_eq!(events.into_inner(), vec![
+            (1, Event::Move { unit: 0, from: Point([1, 0]), to: Point([2, 0]) }),
+            (1, Event::Attack { unit: 0, target: 1, damage: 100 }),
+            (1, Event::Attack { unit: 1, target: 0, damage: 3 }),
+            (2, Event::Attack { unit: 0, target: 1, damage: 100 }),
+            (2, Event::Death { unit: 1, by: 0, pos: Point([3, 0]) })
+        ]);
+    }
+
+    #[test]
+    fn test_alliances() {
+        let rules = || Rules::new(vec![Faction::new("Elf", 'E'), Faction::new("Goblin", 'G'), Faction::new("Orc", 'O').with_hp(50)]);
+        let map = Map::parse("#E..G..O#", &rules()).unwrap();
+        assert!(Map::parse("#E..X#", &rules()).is_err());
+
+        // All against all, the orc joins the elf in fighting the goblin and the elf is left
+        let mut c = CombatSim::new(&map, rules());
+        c.run();
+        assert_eq!(c.alive().map(|u| u.faction).collect::<Vec<_>>(), vec![ELF]);
+
+        // Allied the goblin and the orc win, and stop fighting once the elf is dead
+        let mut c = CombatSim::new(&map, rules().with_alliance(GOBLIN, 2));
+        let outcome = c.run();
+        assert_eq!(c.casualties(ELF), 1);
+        assert_eq!(c.alive().count(), 2);
+        assert!(outcome.over);
+
+        // Stopped as soon as the first unit dies, within a round
+        let mut c = CombatSim::new(&map, rules());
+        let outcome = c.run_until(|c| c.alive().count() < 3);
+        assert!(!outcome.over && !c.is_over());
+        assert_eq!(c.alive().count(), 2);
+    }
+}
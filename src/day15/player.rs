@@ -0,0 +1,329 @@
+// Terminal player of day 15 replays, drawing the cave after every round with the units in the
+// colours of their factions and bars of their hit points next to their rows
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::Duration;
+use ParseError;
+use super::combat::{Event, Unit, UnitId};
+use super::replay::{self, Replay};
+
+pub const HELP: &str = "Commands:
+  n, next [<n>]     Forward one or n rounds
+  b, back [<n>]     Back one or n rounds
+  g, goto <round>   Show the units after the round, 0 for the start
+  p, play [<ms>]    Play the rounds left, one every ms (default 300), an empty line pauses
+  d, death <unit>   Go to the round in which the unit died
+  h, help           Show this help
+  q, quit           Exit the player
+An empty line repeats the last command";
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Command {
+    Next(usize),
+    Back(usize),
+    Goto(usize),
+    Play(u64),
+    Death(UnitId),
+    Help,
+    Quit
+}
+
+impl FromStr for Command {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (cmd, args) = match s.find(char::is_whitespace) {
+            Some(i) => (&s[..i], s[i..].trim()),
+            None => (s, "")
+        };
+        let number = || args.parse::<usize>().map_err(|_| ParseError::expected(format!("a number for {}", cmd), args));
+        let no_args = |c: Command| if args.is_empty() {
+            Ok(c)
+        } else {
+            Err(ParseError::new(format!("Unexpected arguments for {}: {}", cmd, args)))
+        };
+
+        match cmd {
+            "n" | "next" if args.is_empty() => Ok(Command::Next(1)),
+            "n" | "next" => Ok(Command::Next(number()?)),
+            "b" | "back" if args.is_empty() => Ok(Command::Back(1)),
+            "b" | "back" => Ok(Command::Back(number()?)),
+            "g" | "goto" => Ok(Command::Goto(number()?)),
+            "p" | "play" if args.is_empty() => Ok(Command::Play(300)),
+            "p" | "play" => Ok(Command::Play(number()? as u64)),
+            "d" | "death" => Ok(Command::Death(number()?)),
+            "h" | "help" => no_args(Command::Help),
+            "q" | "quit" => no_args(Command::Quit),
+            _ => Err(ParseError::new(format!("Unknown command: {}, try help", cmd)))
+        }
+    }
+}
+
+const CLEAR: &str = "\x1b[2J\x1b[H";
+const RESET: &str = "\x1b[0m";
+const WALL: &str = "\x1b[90m";
+// As in CombatSim::render
+const COLOURS: [&str; 6] = ["\x1b[1;32m", "\x1b[1;31m", "\x1b[1;34m", "\x1b[1;33m", "\x1b[1;35m", "\x1b[1;36m"];
+const BAR: usize = 10;
+
+pub struct Player {
+    replay: Replay,
+    round: usize,
+    units: Vec<Unit>,
+    // Whether to use ANSI escape codes
+    ansi: bool
+}
+
+impl Player {
+    pub fn new(replay: Replay, ansi: bool) -> Player {
+        let units = replay.units_at(0);
+        Player { replay, round: 0, units, ansi }
+    }
+
+    pub fn round(&self) -> usize {
+        self.round
+    }
+
+    pub fn units(&self) -> &[Unit] {
+        &self.units
+    }
+
+    // Shows the units after the round, going forward from the current round when possible
+    pub fn seek(&mut self, round: usize) {
+        let round = round.min(self.replay.last_round());
+        if round < self.round {
+            self.units = self.replay.units_at(round);
+        } else {
+            let from = self.round;
+            for (_, e) in self.replay.events.iter().filter(|&&(r, _)| r > from && r <= round) {
+                replay::apply(&mut self.units, e);
+            }
+        }
+        self.round = round;
+    }
+
+    fn paint(&self, s: &str, colour: &str) -> String {
+        if self.ansi {
+            format!("{}{}{}", colour, s, RESET)
+        } else {
+            s.to_string()
+        }
+    }
+
+    fn label(&self, id: UnitId) -> String {
+        let u = &self.units[id];
+        let f = self.replay.rules.faction(u.faction);
+        self.paint(&format!("{}{}", f.glyph, id), COLOURS[u.faction % COLOURS.len()])
+    }
+
+    fn hp_bar(&self, u: &Unit) -> String {
+        let full = self.replay.rules.faction(u.faction).hp.max(u.hp).max(1);
+        let filled = ((u.hp.max(0) * BAR as i32 + full - 1) / full) as usize;
+        let colour = match u.hp * 4 / full {
+            0 => "\x1b[31m",
+            1 => "\x1b[33m",
+            _ => "\x1b[32m"
+        };
+        format!("[{}{}] {:3}", self.paint(&"█".repeat(filled), colour), " ".repeat(BAR - filled), u.hp)
+    }
+
+    // The round, the cave with the units and their hit points, and who died in the round
+    pub fn screen(&self) -> String {
+        let grid = self.replay.map.grid();
+        let mut screen = format!("Round {} of {}\n", self.round, self.replay.last_round());
+        for y in 0..grid.height() {
+            let mut row_units: Vec<&Unit> = self.units.iter()
+                .filter(|u| u.is_alive() && u.pos.y() == y as i64)
+                .collect();
+            row_units.sort_by_key(|u| u.pos);
+            // Escape codes only where the colour changes
+            let mut colour = None;
+            for x in 0..grid.width() {
+                let (c, cell_colour) = match row_units.iter().find(|u| u.pos.x() == x as i64) {
+                    Some(u) => (self.replay.rules.faction(u.faction).glyph, Some(COLOURS[u.faction % COLOURS.len()])),
+                    None if grid[(x, y)] == '#' => ('#', Some(WALL)),
+                    None => (grid[(x, y)], None)
+                };
+                if self.ansi && cell_colour != colour {
+                    if colour.is_some() {
+                        screen += RESET;
+                    }
+                    screen += cell_colour.unwrap_or("");
+                    colour = cell_colour;
+                }
+                screen.push(c);
+            }
+            if self.ansi && colour.is_some() {
+                screen += RESET;
+            }
+            for u in row_units {
+                screen += &format!("   {} {}", self.label(u.id), self.hp_bar(u));
+            }
+            screen.push('\n');
+        }
+        for e in self.replay.round_events(self.round) {
+            if let Event::Death { unit, by, pos } = *e {
+                screen += &format!("{} killed by {} at {}\n", self.label(unit), self.label(by), pos);
+            }
+        }
+        if self.round == self.replay.last_round() {
+            let o = &self.replay.outcome;
+            let end = if o.over { "Combat over" } else { "Combat stopped" };
+            screen += &format!("{} after {} full rounds with {} hit points left, outcome {}\n", end, o.rounds, o.hp_left, o.score());
+        }
+        screen
+    }
+
+    fn frame(&self) -> String {
+        format!("{}{}", if self.ansi { CLEAR } else { "" }, self.screen())
+    }
+
+    // Executes the command, returning the output to show. Playing is done by run
+    pub fn execute(&mut self, cmd: &Command) -> String {
+        match *cmd {
+            Command::Next(n) => self.seek(self.round + n),
+            Command::Back(n) => self.seek(self.round.saturating_sub(n)),
+            Command::Goto(round) => self.seek(round),
+            Command::Play(_) => self.seek(self.replay.last_round()),
+            Command::Death(unit) if unit >= self.units.len() => return format!("Error: No unit {}\n", unit),
+            Command::Death(unit) => match self.replay.death_round(unit) {
+                Some(round) => self.seek(round),
+                None => return format!("{} survives\n", self.label(unit))
+            },
+            Command::Help => return format!("{}\n", HELP),
+            Command::Quit => return String::new()
+        }
+        self.frame()
+    }
+
+    // Plays a round at a time until the last one, or until a line is entered
+    fn play<W: Write>(&mut self, input: &Receiver<String>, output: &mut W, delay: Duration) -> io::Result<()> {
+        while self.round < self.replay.last_round() {
+            match input.recv_timeout(delay) {
+                Ok(_) => return Ok(()),
+                Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => ()
+            }
+            let round = self.round + 1;
+            self.seek(round);
+            write!(output, "{}", self.frame())?;
+            output.flush()?;
+        }
+        Ok(())
+    }
+
+    // Reads commands line by line until quit or the end of the input
+    pub fn run<W: Write>(&mut self, input: &Receiver<String>, output: &mut W, prompt: bool) -> io::Result<()> {
+        let mut last = None;
+        write!(output, "{}", self.frame())?;
+        loop {
+            if prompt {
+                write!(output, "(replay) ")?;
+                output.flush()?;
+            }
+            let line = match input.recv() {
+                Ok(l) => l,
+                Err(_) => return Ok(())
+            };
+
+            let cmd = if line.trim().is_empty() {
+                match last.clone() {
+                    Some(c) => c,
+                    None => continue
+                }
+            } else {
+                match line.parse::<Command>() {
+                    Ok(c) => c,
+                    Err(e) => {
+                        writeln!(output, "Error: {}", e.message())?;
+                        continue;
+                    }
+                }
+            };
+            match cmd {
+                Command::Quit => return Ok(()),
+                Command::Play(ms) => self.play(input, output, Duration::from_millis(ms))?,
+                _ => write!(output, "{}", self.execute(&cmd))?
+            }
+            last = Some(cmd);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use super::super::combat::{Map, Rules};
+
+    fn player() -> Player {
+        let map = Map::parse(
+            "#######
+             #.G...#
+             #...EG#
+             #.#.#G#
+             #..G#E#
+             #.....#
+             #######", &Rules::elves_and_goblins()).unwrap();
+        Player::new(Replay::record(&map, Rules::elves_and_goblins()), false)
+    }
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!("n".parse(), Ok(Command::Next(1)));
+        assert_eq!("next 5".parse(), Ok(Command::Next(5)));
+        assert_eq!("b".parse(), Ok(Command::Back(1)));
+        assert_eq!("goto 23".parse(), Ok(Command::Goto(23)));
+        assert_eq!("p".parse(), Ok(Command::Play(300)));
+        assert_eq!("play 50".parse(), Ok(Command::Play(50)));
+        assert_eq!("death 4".parse(), Ok(Command::Death(4)));
+        assert_eq!("goto".parse::<Command>(), Err(ParseError::expected("a number for goto", "")));
+        assert_eq!("jump".parse::<Command>(), Err(ParseError::new("Unknown command: jump, try help")));
+        assert!("quit now".parse::<Command>().is_err());
+    }
+
+    #[test]
+    fn test_seek() {
+        let mut p = player();
+        p.execute(&Command::Next(2));
+        // As in the example
+        assert_eq!(p.screen(), "Round 2 of 47\n\
+            #######\n\
+            #...G.#   G0 [██████████] 200\n\
+            #..GEG#   G4 [██████████] 200   E1 [██████████] 188   G2 [██████████] 194\n\
+            #.#.#G#   G3 [██████████] 194\n\
+            #...#E#   E5 [██████████] 194\n\
+            #.....#\n\
+            #######\n");
+
+        // Elf 1 died in round 23, going back to it and on again
+        p.execute(&Command::Goto(40));
+        assert!(p.execute(&Command::Death(1)).ends_with("E1 killed by G2 at 4,2\n"));
+        assert_eq!(p.round(), 23);
+        p.execute(&Command::Back(23));
+        assert_eq!(p.units(), &p.replay.units_at(0)[..]);
+        assert!(p.execute(&Command::Next(100)).ends_with("Combat over after 47 full rounds with 590 hit points left, outcome 27730\n"));
+        assert_eq!(p.units(), &p.replay.units_at(47)[..]);
+        assert_eq!(p.execute(&Command::Death(0)), "G0 survives\n");
+        assert_eq!(p.execute(&Command::Death(6)), "Error: No unit 6\n");
+    }
+
+    #[test]
+    fn test_run() {
+        let mut p = player();
+        let (tx, rx) = mpsc::channel();
+        for line in ["next 10", "", "bogus", "play 0"].iter() {
+            tx.send(line.to_string()).unwrap();
+        }
+        drop(tx);
+        let mut out = vec![];
+        p.run(&rx, &mut out, false).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("Round 0 of 47\n"));
+        assert!(out.contains("Round 20 of 47\n"));
+        assert!(out.contains("Error: Unknown command: bogus, try help\n"));
+        assert_eq!(out.matches("Round ").count(), 1 + 2 + 27);
+        assert_eq!(p.round(), 47);
+    }
+}
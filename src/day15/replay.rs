@@ -0,0 +1,263 @@
+// Recordings of day 15 combats, the rules and the initial map followed by what every unit did in
+// every round, in a text file like
+//
+//   day15 replay 1
+//   faction Elf E 200 3
+//   faction Goblin G 200 3
+//   map
+//   #E.G#
+//   end
+//   1 move 0 1,0 2,0
+//   1 attack 1 0 3
+//   ...
+//   67 death 0 1 2,0
+//   outcome 66 200 over
+//
+// where every action starts with the round it was taken in, and the outcome gives the full
+// rounds, the hit points left and whether the combat is over or was stopped. Allied factions are
+// given by their numbers in lines like `alliance 1 2` after the factions
+use std::cell::RefCell;
+use std::fmt::Write;
+use std::str::FromStr;
+use ParseError;
+use super::combat::{CombatSim, Event, Faction, Map, Outcome, Pos, Rules, Unit, UnitId};
+
+const HEADER: &str = "day15 replay 1";
+
+#[derive(Clone, Debug)]
+pub struct Replay {
+    pub rules: Rules,
+    pub map: Map,
+    pub events: Vec<(usize, Event)>,
+    pub outcome: Outcome
+}
+
+impl Replay {
+    // Plays the whole combat
+    pub fn record(map: &Map, rules: Rules) -> Replay {
+        let events = RefCell::new(vec![]);
+        let mut sim = CombatSim::new(map, rules.clone());
+        sim.observe(|round, e| events.borrow_mut().push((round, e.clone())));
+        let outcome = sim.run();
+        drop(sim);
+        Replay { rules, map: map.clone(), events: events.into_inner(), outcome }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{}\n", HEADER);
+        let factions = self.rules.factions();
+        for f in factions {
+            writeln!(text, "faction {} {} {} {}", f.name, f.glyph, f.hp, f.attack).unwrap();
+        }
+        for a in 0..factions.len() {
+            for b in a + 1..factions.len() {
+                if !self.rules.are_enemies(a, b) {
+                    writeln!(text, "alliance {} {}", a, b).unwrap();
+                }
+            }
+        }
+        writeln!(text, "map\n{}\nend", CombatSim::new(&self.map, self.rules.clone()).draw()).unwrap();
+        for (round, e) in self.events.iter() {
+            match e {
+                Event::Move { unit, from, to } => writeln!(text, "{} move {} {} {}", round, unit, from, to),
+                Event::Attack { unit, target, damage } => writeln!(text, "{} attack {} {} {}", round, unit, target, damage),
+                Event::Death { unit, by, pos } => writeln!(text, "{} death {} {} {}", round, unit, by, pos)
+            }.unwrap();
+        }
+        let o = &self.outcome;
+        writeln!(text, "outcome {} {} {}", o.rounds, o.hp_left, if o.over { "over" } else { "stopped" }).unwrap();
+        text
+    }
+
+    pub fn parse(input: &str) -> Result<Replay, ParseError> {
+        let mut lines = input.lines().enumerate().map(|(ln, l)| (ln + 1, l.trim())).filter(|(_, l)| !l.is_empty());
+        match lines.next() {
+            Some((_, HEADER)) => (),
+            Some((ln, l)) => return Err(ParseError::expected(format!("'{}'", HEADER), l).at_line(ln)),
+            None => return Err(ParseError::new("Empty replay"))
+        }
+
+        let mut factions = vec![];
+        let mut alliances = vec![];
+        let mut map = None;
+        let mut events = vec![];
+        let mut outcome = None;
+        while let Some((ln, l)) = lines.next() {
+            let words: Vec<&str> = l.split_whitespace().collect();
+            let mut parse_line = || -> Result<(), ParseError> {
+                match words[..] {
+                    _ if outcome.is_some() => return Err(ParseError::expected("the end of the replay after the outcome", l)),
+                    ["faction", name, glyph, hp, attack] if glyph.chars().count() == 1 => {
+                        let glyph = glyph.chars().next().unwrap();
+                        factions.push(Faction::new(name, glyph).with_hp(number(hp)?).with_attack(number(attack)?));
+                    },
+                    ["alliance", a, b] => alliances.push((number(a)?, number(b)?)),
+                    ["map"] => {
+                        // Keeps the rows on their lines, for the line numbers of errors
+                        let mut text = String::new();
+                        for (row_ln, row) in lines.by_ref().take_while(|&(_, r)| r != "end") {
+                            text += &"\n".repeat(row_ln - 1 - text.lines().count());
+                            text += row;
+                            text.push('\n');
+                        }
+                        map = Some(Map::parse(&text, &Rules::new(factions.clone()))?);
+                    },
+                    ["outcome", rounds, hp_left, over] if over == "over" || over == "stopped" => {
+                        outcome = Some(Outcome { rounds: number(rounds)?, hp_left: number(hp_left)?, over: over == "over" });
+                    },
+                    [round, "move" | "attack" | "death", ..] if map.is_some() => {
+                        let e = parse_event(&words)?;
+                        let map = map.as_ref().unwrap();
+                        if let Some(p) = event_positions(&e).into_iter().find(|&p| !map.is_open(p)) {
+                            return Err(ParseError::new(format!("Position {} is not on the open ground of the map", p)));
+                        }
+                        events.push((number(round)?, e));
+                    },
+                    _ if map.is_some() => return Err(ParseError::expected("an action or the outcome", l)),
+                    _ => return Err(ParseError::expected("a faction, alliance or the map", l))
+                }
+                Ok(())
+            };
+            parse_line().map_err(|e| e.at_line(ln).in_text(l))?;
+        }
+
+        let n = factions.len();
+        let mut rules = Rules::new(factions);
+        for (a, b) in alliances {
+            if a >= n || b >= n {
+                return Err(ParseError::new(format!("Alliance of unknown factions {} and {}", a, b)));
+            }
+            rules = rules.with_alliance(a, b);
+        }
+        let map = map.ok_or_else(|| ParseError::new("Missing map"))?;
+        let outcome = outcome.ok_or_else(|| ParseError::new("Missing outcome"))?;
+        let n_units = map.units().len();
+        if let Some((_, e)) = events.iter().find(|(_, e)| event_units(e).iter().any(|&u| u >= n_units)) {
+            return Err(ParseError::new(format!("Action of a unit not on the map: {:?}", e)));
+        }
+        Ok(Replay { rules, map, events, outcome })
+    }
+
+    // The last round played, which may not have been completed
+    pub fn last_round(&self) -> usize {
+        self.events.last().map(|&(r, _)| r).unwrap_or(0).max(self.outcome.rounds)
+    }
+
+    // The units after the round, with the dead ones
+    pub fn units_at(&self, round: usize) -> Vec<Unit> {
+        let mut units = CombatSim::new(&self.map, self.rules.clone()).units().to_vec();
+        for (_, e) in self.events.iter().take_while(|&&(r, _)| r <= round) {
+            apply(&mut units, e);
+        }
+        units
+    }
+
+    // The round in which the unit died
+    pub fn death_round(&self, unit: UnitId) -> Option<usize> {
+        self.events.iter()
+            .find(|(_, e)| matches!(e, Event::Death { unit: u, .. } if *u == unit))
+            .map(|&(r, _)| r)
+    }
+
+    // The actions taken in the round
+    pub fn round_events(&self, round: usize) -> impl Iterator<Item = &Event> {
+        self.events.iter().filter(move |&&(r, _)| r == round).map(|(_, e)| e)
+    }
+}
+
+pub fn apply(units: &mut [Unit], e: &Event) {
+    match *e {
+        Event::Move { unit, to, .. } => units[unit].pos = to,
+        Event::Attack { target, damage, .. } => units[target].hp -= damage,
+        Event::Death { .. } => ()
+    }
+}
+
+fn event_units(e: &Event) -> Vec<UnitId> {
+    match *e {
+        Event::Move { unit, .. } => vec![unit],
+        Event::Attack { unit, target, .. } => vec![unit, target],
+        Event::Death { unit, by, .. } => vec![unit, by]
+    }
+}
+
+fn event_positions(e: &Event) -> Vec<Pos> {
+    match *e {
+        Event::Move { from, to, .. } => vec![from, to],
+        Event::Attack { .. } => vec![],
+        Event::Death { pos, .. } => vec![pos]
+    }
+}
+
+fn number<T: FromStr>(w: &str) -> Result<T, ParseError> {
+    w.parse().map_err(|_| ParseError::expected("a number", w))
+}
+
+fn parse_event(words: &[&str]) -> Result<Event, ParseError> {
+    match *words {
+        [_, "move", unit, from, to] => Ok(Event::Move { unit: number(unit)?, from: from.parse()?, to: to.parse()? }),
+        [_, "attack", unit, target, damage] => Ok(Event::Attack { unit: number(unit)?, target: number(target)?, damage: number(damage)? }),
+        [_, "death", unit, by, pos] => Ok(Event::Death { unit: number(unit)?, by: number(by)?, pos: pos.parse()? }),
+        _ => Err(ParseError::expected("'<round> move <unit> <from> <to>', '<round> attack <unit> <target> <damage>' or '<round> death <unit> <by> <pos>'", &words.join(" ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use point::Point;
+
+    const INPUT: &str =
+        "#######
+         #.G...#
+         #...EG#
+         #.#.#G#
+         #..G#E#
+         #.....#
+         #######";
+
+    #[test]
+    fn test_round_trip() {
+        let map = Map::parse(INPUT, &Rules::elves_and_goblins()).unwrap();
+        let replay = Replay::record(&map, Rules::elves_and_goblins());
+        assert_eq!(replay.outcome.score(), 27730);
+        assert_eq!(replay.last_round(), 47);
+
+        let text = replay.to_text();
+        assert!(text.starts_with("day15 replay 1\nfaction Elf E 200 3\nfaction Goblin G 200 3\nmap\n#######\n#.G...#\n"));
+        assert!(text.ends_with("outcome 47 590 over\n"));
+        let parsed = Replay::parse(&text).unwrap();
+        assert_eq!(parsed.to_text(), text);
+        assert_eq!(parsed.events, replay.events);
+
+        // The state after round 47 as in the example
+        let alive: Vec<(char, Point<2>, i32)> = parsed.units_at(47).iter()
+            .filter(|u| u.is_alive())
+            .map(|u| (parsed.rules.faction(u.faction).glyph, u.pos, u.hp))
+            .collect();
+        assert_eq!(alive, vec![('G', Point([1, 1]), 200), ('G', Point([2, 2]), 131), ('G', Point([5, 3]), 59), ('G', Point([5, 5]), 200)]);
+        // The elves die in rounds 23 and 47
+        assert_eq!(parsed.death_round(1), Some(23));
+        assert_eq!(parsed.death_round(5), Some(47));
+        assert_eq!(parsed.death_round(0), None);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let replay = |events: &str| Replay::parse(&format!("day15 replay 1\nfaction Elf E 200 3\nfaction Goblin G 200 3\nalliance 0 1\nmap\n#EG#\nend\n{}", events));
+        assert!(replay("outcome 0 400 stopped").is_ok());
+        assert!(!replay("outcome 0 400 stopped").unwrap().rules.are_enemies(0, 1));
+        assert_eq!(replay("").unwrap_err(), ParseError::new("Missing outcome"));
+        assert_eq!(replay("1 attack 0 1 x\noutcome 0 0 over").unwrap_err(), ParseError::expected("a number", "x").at_line(8).in_text("1 attack 0 1 x"));
+        assert_eq!(replay("1 jump 0\noutcome 0 0 over").unwrap_err(), ParseError::expected("an action or the outcome", "1 jump 0").at_line(8).in_text("1 jump 0"));
+        assert!(replay("1 attack 0 2 3\noutcome 0 0 over").is_err());
+        assert_eq!(replay("1 move 0 1,0 9,9\noutcome 0 0 over").unwrap_err(),
+                   ParseError::new("Position 9,9 is not on the open ground of the map").at_line(8).in_text("1 move 0 1,0 9,9"));
+        assert!(replay("1 move 0 1,0 0,0\noutcome 0 0 over").is_err());
+        assert!(replay("1 death 1 0 -1,0\noutcome 0 0 over").is_err());
+        assert!(replay("outcome 0 0 over\n1 attack 0 1 3").is_err());
+        assert_eq!(Replay::parse("day15 replay 2").unwrap_err(), ParseError::expected("'day15 replay 1'", "day15 replay 2").at_line(1));
+        let err = Replay::parse("day15 replay 1\nfaction Elf E 200 3\nmap\n#E#\n#X#\nend\noutcome 0 0 over").unwrap_err();
+        assert_eq!(err.line(), Some(5));
+    }
+}
@@ -1,222 +1,299 @@
-extern crate regex;
-#[macro_use] extern crate lazy_static;
-extern crate utils;
-
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::error::Error;
-use std::io::{self, BufReader};
-use std::io::prelude::*;
-use std::fs::File;
-use regex::Regex;
-use utils::*;
-
-fn parse_input(input: &Vec<String>) -> (Vec<Sample>, Vec<[u32; 4]>) {
-    lazy_static! {
-        static ref RE: Regex = Regex::new(r"^\D*(\d+)\D+(\d+)\D+(\d+)\D+(\d+)\D*$").unwrap();
-    }
+use std::fmt;
+use elfcode::{self, Device, Fault, Instruction, OpCode, Program, RawInstruction, Word};
+use {ParseError, Solution};
 
-    let get_vals = |s: &String| {
-        let caps = RE.captures(s).unwrap();
-        let get = |idx| caps.get(idx).unwrap().as_str().parse::<u32>().unwrap();
-        [get(1), get(2), get(3), get(4)]
+fn parse_input(input: &str) -> Result<(Vec<Sample>, Vec<RawInstruction>), ParseError> {
+    let input: Vec<&str> = input.lines().map(|l| l.trim()).collect();
+    // The four numbers of a line, which has to start with the prefix
+    let numbers = |idx: usize, prefix: &str| {
+        let l = input.get(idx).ok_or_else(|| ParseError::new("Incomplete sample").at_line(idx + 1))?;
+        if !l.starts_with(prefix) {
+            return Err(ParseError::expected(format!("a line starting with '{}'", prefix), l).at_line(idx + 1));
+        }
+        elfcode::parse_numeric_line(l).map_err(|e| e.at_line(idx + 1).in_text(l))
+    };
+    let instruction = |idx: usize| {
+        let raw = numbers(idx, "")?;
+        if raw[0] >= 16 {
+            return Err(ParseError::expected("an opcode number below 16", &raw[0].to_string()).at_line(idx + 1));
+        }
+        Ok(raw)
     };
-
     let mut samples: Vec<Sample> = vec![];
-    let mut instructions: Vec<[u32; 4]> = vec![];
+    let mut instructions: Vec<RawInstruction> = vec![];
 
     let mut idx = 0;
     while idx < input.len() {
-        let l = &input[idx];
+        let l = input[idx];
         if l.starts_with("Before:") {
             samples.push(Sample {
-                before: get_vals(l),
-                instruction: get_vals(&input[idx + 1]),
-                after: get_vals(&input[idx + 2])
+                before: numbers(idx, "Before:")?,
+                instruction: instruction(idx + 1)?,
+                after: numbers(idx + 2, "After:")?
             });
             idx += 3;
-        } else if l.trim().len() > 0 {
-            instructions.push(get_vals(l));
+        } else if l.len() > 0 {
+            instructions.push(instruction(idx)?);
 
         }
         idx += 1;
     }
-    (samples, instructions)
+    Ok((samples, instructions))
 }
 
-#[derive(Debug)]
-struct Sample {
+// The program following the samples, with the number of lines before it
+pub fn program_text(input: &str) -> (usize, &str) {
+    let (mut offset, mut start, mut pos) = (0, 0, 0);
+    for (ln, l) in input.split_inclusive('\n').enumerate() {
+        pos += l.len();
+        if l.trim().starts_with("After:") {
+            offset = ln + 1;
+            start = pos;
+        }
+    }
+    (offset, &input[start..])
+}
+
+#[derive(Clone, Debug)]
+pub struct Sample {
     before: [u32; 4],
-    instruction: [u32; 4],
+    instruction: RawInstruction,
     after: [u32; 4]
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
-enum OpCode {
-    Addr, Addi, Mulr, Muli, Banr, Bani, Borr, Bori, Setr, Seti, Gtir, Gtri, Gtrr, Eqir, Eqri, Eqrr
+impl Sample {
+    // Whether executing the sample's instruction as the given opcode gives the after state
+    fn matches(&self, opc: OpCode) -> bool {
+        let mut d = Device::with_registers(self.before.iter().map(|&r| r as Word).collect());
+        let ins = Instruction::new(opc, self.instruction[1], self.instruction[2], self.instruction[3]);
+        d.exec(&ins).is_ok() && d.registers.iter().zip(self.after.iter()).all(|(&r, &a)| r == a as Word)
+    }
 }
 
-impl OpCode {
-    fn all() -> Vec<OpCode> {
-        use OpCode::*;
-        vec![Addr, Addi, Mulr, Muli, Banr, Bani, Borr, Bori, Setr, Seti, Gtir, Gtri, Gtrr, Eqir, Eqri, Eqrr]
+pub struct Day16;
+
+impl Solution for Day16 {
+    type Input = (Vec<Sample>, Vec<RawInstruction>);
+    type Answer1 = usize;
+    type Answer2 = Result<Word, ProgramError>;
+
+    fn parse(input: &str) -> Result<(Vec<Sample>, Vec<RawInstruction>), ParseError> {
+        parse_input(input)
+    }
+
+    fn part1(input: &(Vec<Sample>, Vec<RawInstruction>)) -> usize {
+        let (samples, _) = input;
+
+        samples.iter()
+            .fold(0, |acc, s| {
+                let n_matching = OpCode::all().into_iter()
+                    .filter(|&oc| s.matches(oc))
+                    .count();
+
+                acc + if n_matching >= 3 { 1 } else { 0 }
+            })
     }
-}
 
-struct Instruction {
-    opc: OpCode,
-    inp_a: u32,
-    inp_b: u32,
-    out_c: u32
+    fn part2(input: &(Vec<Sample>, Vec<RawInstruction>)) -> Result<Word, ProgramError> {
+        let (samples, instructions) = input;
+        let mut device = Device::new(4, None);
+
+        let mapping = solve_opcodes(&candidates(samples));
+        debug!("Opcode matches: {}", mapping);
+        let opcode_lookup = match mapping {
+            Mapping::Complete(m) => m,
+            m => return Err(ProgramError::Unsolved(m))
+        };
+        let program = Program::new(None, instructions.iter().enumerate()
+            .map(|(idx, raw)| elfcode::decode(raw, &|n| opcode_lookup.get(&n).copied())
+                .ok_or(ProgramError::UnknownOpcode { idx, number: raw[0] }))
+            .collect::<Result<_, _>>()?);
+
+        device.run(&program).map_err(ProgramError::Fault)?;
+        Ok(device.registers[0])
+    }
 }
 
-struct Device {
-    registers: [u32; 4]
+// Why the program of part 2 can't be run to the end
+#[derive(PartialEq, Debug)]
+pub enum ProgramError {
+    // The samples don't give the opcode of every number
+    Unsolved(Mapping),
+    // An instruction, by index in the program, with an opcode number no sample has
+    UnknownOpcode { idx: usize, number: u32 },
+    Fault(Fault)
 }
 
-impl Device {
-    fn new() -> Device {
-        Device { registers: [0; 4] }
-    }
-
-    fn eval(&mut self, ins: Instruction) {
-        let r = &mut self.registers;
-        let a = ins.inp_a;
-        let ai = a as usize;
-        let b = ins.inp_b;
-        let bi = b as usize;
-        let c = ins.out_c;
-        let ci = c as usize;
-        match ins.opc {
-            OpCode::Addr => r[ci] = r[ai] + r[bi],
-            OpCode::Addi => r[ci] = r[ai] + b,
-            OpCode::Mulr => r[ci] = r[ai] * r[bi],
-            OpCode::Muli => r[ci] = r[ai] * b,
-            OpCode::Banr => r[ci] = r[ai] & r[bi],
-            OpCode::Bani => r[ci] = r[ai] & b,
-            OpCode::Borr => r[ci] = r[ai] | r[bi],
-            OpCode::Bori => r[ci] = r[ai] | b,
-            OpCode::Setr => r[ci] = r[ai],
-            OpCode::Seti => r[ci] = a,
-            OpCode::Gtir => r[ci] = if a > r[bi] { 1 } else { 0 },
-            OpCode::Gtri => r[ci] = if r[ai] > b { 1 } else { 0 },
-            OpCode::Gtrr => r[ci] = if r[ai] > r[bi] { 1 } else { 0 },
-            OpCode::Eqir => r[ci] = if a == r[bi] { 1 } else { 0 },
-            OpCode::Eqri => r[ci] = if r[ai] == b { 1 } else { 0 },
-            OpCode::Eqrr => r[ci] = if r[ai] == r[bi] { 1 } else { 0 }
+impl fmt::Display for ProgramError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProgramError::Unsolved(m) => m.fmt(f),
+            ProgramError::UnknownOpcode { idx, number } =>
+                write!(f, "No sample has opcode number {} of instruction {}", number, idx),
+            ProgramError::Fault(fault) => fault.fmt(f)
         }
     }
 }
 
-fn part1(input: &Vec<String>) -> usize {
-    let (samples, _) = parse_input(input);
-
-    samples.iter()
-        .fold(0, |acc, s| {
-            let n_matching = OpCode::all().iter()
-                .filter(|&oc| {
-                    let mut d = Device { registers: s.before };
-                    d.eval(Instruction {
-                        opc: *oc,
-                        inp_a: s.instruction[1],
-                        inp_b: s.instruction[2],
-                        out_c: s.instruction[3]
-                    });
-                    d.registers == s.after
-                })
-                .count();
-
-            acc + if n_matching >= 3 { 1 } else { 0 }
+impl Error for ProgramError {}
+
+// Result of deducing the opcode of every opcode number from the samples
+#[derive(PartialEq, Debug)]
+pub enum Mapping {
+    Complete(BTreeMap<u32, OpCode>),
+    // The samples leave more than one opcode for some numbers
+    Ambiguous { resolved: BTreeMap<u32, OpCode>, unresolved: BTreeMap<u32, BTreeSet<OpCode>> },
+    // No opcode is left for some numbers, given with the opcodes their samples alone allow
+    Contradiction { conflicts: BTreeMap<u32, BTreeSet<OpCode>> }
+}
+
+fn format_candidates(candidates: &BTreeMap<u32, BTreeSet<OpCode>>) -> String {
+    let numbers: Vec<String> = candidates.iter()
+        .map(|(n, ocs)| {
+            let names: Vec<&str> = ocs.iter().map(|oc| oc.name()).collect();
+            format!("{} ({})", n, if !names.is_empty() { names.join(", ") } else { "none".to_string() })
         })
+        .collect();
+    numbers.join(", ")
 }
 
-fn detect_opcodes(samples: &Vec<Sample>) -> BTreeMap<u32, OpCode> {
+impl fmt::Display for Mapping {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Mapping::Complete(mapping) => {
+                let pairs: Vec<String> = mapping.iter().map(|(n, oc)| format!("{}={}", n, oc)).collect();
+                write!(f, "{}", pairs.join(", "))
+            },
+            Mapping::Ambiguous { unresolved, .. } => write!(f, "Ambiguous opcode numbers: {}", format_candidates(unresolved)),
+            Mapping::Contradiction { conflicts } => write!(f, "Contradicting opcode numbers: {}", format_candidates(conflicts))
+        }
+    }
+}
 
-    // Calculate OpCode frequency per opcode-value
-    let mut ocs_freq: BTreeMap<u32, BTreeMap<OpCode, i32>> = BTreeMap::new();
+// Opcodes matching all samples of every opcode number
+pub fn candidates(samples: &Vec<Sample>) -> BTreeMap<u32, BTreeSet<OpCode>> {
+    let mut candidates: BTreeMap<u32, BTreeSet<OpCode>> = BTreeMap::new();
     for s in samples {
-        let ocs_cnt = &mut *ocs_freq.entry(s.instruction[0]).or_insert(BTreeMap::new());
-        for oc in OpCode::all() {
-            let mut d = Device { registers: s.before };
-            d.eval(Instruction {
-                opc: oc,
-                inp_a: s.instruction[1],
-                inp_b: s.instruction[2],
-                out_c: s.instruction[3]
-            });
+        let matching: BTreeSet<OpCode> = OpCode::all().into_iter().filter(|&oc| s.matches(oc)).collect();
+        let c = candidates.entry(s.instruction[0]).or_insert_with(|| matching.clone());
+        *c = c.intersection(&matching).cloned().collect();
+    }
+    candidates
+}
+
+// Constraint propagation, an opcode number with a single candidate claims it, removing it from
+// all other numbers, until nothing changes
+pub fn solve_opcodes(candidates: &BTreeMap<u32, BTreeSet<OpCode>>) -> Mapping {
+    let mut sets = candidates.clone();
+    let mut resolved: BTreeMap<u32, OpCode> = BTreeMap::new();
 
-            if d.registers == s.after {
-                *ocs_cnt.entry(oc).or_insert(0) += 1;
-            }
+    loop {
+        let single = sets.iter()
+            .find(|(n, ocs)| ocs.len() == 1 && !resolved.contains_key(n))
+            .map(|(&n, ocs)| (n, *ocs.iter().next().unwrap()));
+        let (n, oc) = match single {
+            Some(s) => s,
+            None => break
+        };
+        resolved.insert(n, oc);
+        for (_, ocs) in sets.iter_mut().filter(|(&other, _)| other != n) {
+            ocs.remove(&oc);
         }
     }
 
-    // Calculate OpCodes with highest frequency per opcode-value
-    let mut ocs_hfreq: BTreeMap<u32, Vec<OpCode>> = BTreeMap::new();
-    for i in ocs_freq.keys() {
-        let freq = ocs_freq.get(i).unwrap();
-        let mfreq = freq.iter().map(|(_, f)| *f).max().unwrap();
-        let ocs: Vec<_> = freq.iter().filter(|(_, &f)| f == mfreq).map(|(oc, _)| *oc).collect();
-        ocs_hfreq.insert(*i, ocs);
-    }
-
-    // Find opcode-value to OpCode matches by identifying those not already identified with only one match
-    // in the given iteration
-    let mut ocs_matches: BTreeMap<u32, OpCode> = BTreeMap::new();
-    while ocs_matches.len() < 15 {
-        for i in ocs_hfreq.keys() {
-            let freqs = ocs_hfreq.get(i).unwrap();
-            let matches: Vec<&OpCode> = freqs.iter().filter(|&oc| !ocs_matches.values().any(|moc| moc == oc)).collect();
-            if matches.len() == 1 {
-                ocs_matches.insert(*i, matches[0].to_owned());
-            }
-        }
+    let conflicts: BTreeMap<u32, BTreeSet<OpCode>> = sets.iter()
+        .filter(|(_, ocs)| ocs.is_empty())
+        .map(|(n, _)| (*n, candidates[n].clone()))
+        .collect();
+    if !conflicts.is_empty() {
+        return Mapping::Contradiction { conflicts };
+    }
+    if resolved.len() < sets.len() {
+        let unresolved = sets.into_iter().filter(|(n, _)| !resolved.contains_key(n)).collect();
+        return Mapping::Ambiguous { resolved, unresolved };
+    }
+    Mapping::Complete(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use elfcode::OpCode::*;
+
+    fn sample(before: [u32; 4], instruction: RawInstruction, after: [u32; 4]) -> Sample {
+        Sample { before, instruction, after }
     }
 
-    #[cfg(feature = "print")] {
-        println!("Opcode matches");
-        for (i, oc) in ocs_matches.iter() {
-            println!("{:2} = {:?}", i, oc);
-        }
+    fn set(ocs: &[OpCode]) -> BTreeSet<OpCode> {
+        ocs.iter().cloned().collect()
     }
 
-    ocs_matches
-}
+    #[test]
+    fn test_candidates() {
+        let samples = vec![
+            sample([3, 2, 1, 1], [9, 2, 1, 2], [3, 2, 2, 1]),
+            sample([0, 0, 0, 0], [9, 7, 0, 0], [7, 0, 0, 0])
+        ];
+        assert_eq!(candidates(&samples[..1].to_vec()), vec![(9, set(&[Mulr, Addi, Seti]))].into_iter().collect());
+        assert_eq!(candidates(&samples), vec![(9, set(&[Seti]))].into_iter().collect());
+    }
 
-fn part2(input: &Vec<String>) -> u32 {
-    let (samples, instructions) = parse_input(input);
-    let mut device = Device::new();
+    #[test]
+    fn test_solve_complete() {
+        let c = vec![(0, set(&[Seti])), (1, set(&[Seti, Addi])), (2, set(&[Addi, Mulr, Seti]))].into_iter().collect();
+        assert_eq!(solve_opcodes(&c), Mapping::Complete(vec![(0, Seti), (1, Addi), (2, Mulr)].into_iter().collect()));
+    }
 
-    let opcode_lookup = detect_opcodes(&samples);
+    #[test]
+    fn test_solve_underdetermined() {
+        // The single sample of the puzzle description leaves three opcodes
+        let samples = vec![sample([3, 2, 1, 1], [9, 2, 1, 2], [3, 2, 2, 1])];
+        let mapping = solve_opcodes(&candidates(&samples));
+        assert_eq!(mapping, Mapping::Ambiguous {
+            resolved: BTreeMap::new(),
+            unresolved: vec![(9, set(&[Addi, Mulr, Seti]))].into_iter().collect()
+        });
+        assert_eq!(mapping.to_string(), "Ambiguous opcode numbers: 9 (addi, mulr, seti)");
 
-    for i in instructions {
-        let ins = Instruction {
-            opc: *opcode_lookup.get(&i[0]).unwrap(),
-            inp_a: i[1],
-            inp_b: i[2],
-            out_c: i[3]
-        };
-        device.eval(ins);
+        let c = vec![(0, set(&[Seti])), (1, set(&[Addi, Mulr, Seti])), (2, set(&[Addi, Mulr]))].into_iter().collect();
+        assert_eq!(solve_opcodes(&c), Mapping::Ambiguous {
+            resolved: vec![(0, Seti)].into_iter().collect(),
+            unresolved: vec![(1, set(&[Addi, Mulr])), (2, set(&[Addi, Mulr]))].into_iter().collect()
+        });
     }
 
-    device.registers[0]
-}
+    #[test]
+    fn test_solve_inconsistent() {
+        // Two samples of 9 that no single opcode explains
+        let samples = vec![
+            sample([3, 2, 1, 1], [9, 2, 1, 2], [3, 2, 2, 1]),
+            sample([0, 0, 0, 0], [9, 0, 0, 3], [0, 0, 0, 1])
+        ];
+        let mapping = solve_opcodes(&candidates(&samples));
+        assert_eq!(mapping, Mapping::Contradiction { conflicts: vec![(9, set(&[]))].into_iter().collect() });
+        assert_eq!(mapping.to_string(), "Contradicting opcode numbers: 9 (none)");
 
-fn main() -> Result<(), Box<Error>> {
-    measure_exec(|| {
-        let result = part1(&input()?);
-        println!("Part1 result: {}", result);
-        Ok(())
-    })?;
-    measure_exec(|| {
-        let result = part2(&input()?);
-        println!("Part2 result: {}", result);
-        Ok(())
-    })?;
-    Ok(())
-}
+        // Both numbers can only be seti
+        let c = vec![(0, set(&[Seti])), (1, set(&[Seti])), (2, set(&[Addi, Mulr]))].into_iter().collect();
+        assert_eq!(solve_opcodes(&c), Mapping::Contradiction { conflicts: vec![(1, set(&[Seti]))].into_iter().collect() });
+    }
 
-fn input() -> io::Result<Vec<String>> {
-    let f = File::open("src/day16/input")?;
-    let f = BufReader::new(f);
-    Ok(f.lines().map(|l| l.unwrap()).collect())
+    #[test]
+    fn test_part2_errors() {
+        // Together the samples leave only seti for 9
+        let samples = vec![
+            sample([3, 2, 1, 1], [9, 2, 1, 2], [3, 2, 2, 1]),
+            sample([0, 0, 0, 0], [9, 7, 0, 0], [7, 0, 0, 0])
+        ];
+        assert_eq!(Day16::part2(&(samples.clone(), vec![[9, 7, 0, 0]])), Ok(7));
+        let e = Day16::part2(&(samples[..1].to_vec(), vec![[9, 7, 0, 0]])).unwrap_err();
+        assert_eq!(e.to_string(), "Ambiguous opcode numbers: 9 (addi, mulr, seti)");
+        let e = Day16::part2(&(samples.clone(), vec![[9, 7, 0, 0], [8, 1, 2, 3]])).unwrap_err();
+        assert_eq!(e, ProgramError::UnknownOpcode { idx: 1, number: 8 });
+        assert_eq!(e.to_string(), "No sample has opcode number 8 of instruction 1");
+        let e = Day16::part2(&(samples, vec![[9, 7, 0, 5]])).unwrap_err();
+        assert_eq!(e, ProgramError::Fault(Fault::InvalidRegister { ip: 0, register: 5 }));
+    }
 }
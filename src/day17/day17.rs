@@ -1,74 +1,69 @@
-extern crate regex;
-#[macro_use] extern crate lazy_static;
-extern crate utils;
-
-use std::collections::HashSet;
 use std::collections::HashMap;
 use std::collections::VecDeque;
-use std::error::Error;
-use std::io::{self, BufReader};
-use std::io::prelude::*;
-use std::fs::File;
+use std::io;
 use regex::Regex;
-use utils::*;
-
-#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
-struct Pos {
-    x: i32,
-    y: i32
-}
+use grid::Grid;
+use point::Point;
+use render::{Animation, Frames, Rgb};
+use {parse_lines, ParseError, Solution};
 
-impl Pos {
-    fn translate(&self, x: i32, y: i32) -> Pos {
-        Pos { x: self.x + x, y: self.y + y }
-    }
-}
+type Pos = Point<2>;
 
-#[derive(PartialEq, Eq, Hash, Debug)]
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
 enum WaterState {
     Resting, Flowing
 }
 
-struct Ground {
-    clay: HashSet<Pos>,
+#[derive(PartialEq, Eq, Debug, Clone)]
+enum Tile {
+    Sand, Clay, Water(WaterState)
+}
+
+#[derive(Clone)]
+pub struct Ground {
+    // Tiles of the scanned area, one column wider on each side than the clay, from top_left
+    tiles: Grid<Tile>,
+    top_left: Pos,
     spring: Pos,
-    water: HashMap<Pos, WaterState>,
-    min_x: i32,
-    min_y: i32,
-    max_x: i32,
-    max_y: i32
+    max_y: i64
 }
 
 impl Ground {
-    fn new(clay: HashSet<Pos>) -> Ground {
-        let min_x = clay.iter().map(|p| p.x).min().unwrap() - 1;
-        let min_y = clay.iter().map(|p| p.y).min().unwrap();
-        let max_x = clay.iter().map(|p| p.x).max().unwrap() + 1;
-        let max_y = clay.iter().map(|p| p.y).max().unwrap();
+    fn new(clay: Vec<Pos>) -> Ground {
+        let (min, max) = Point::bounding_box(&clay).unwrap();
+        let top_left = min - Point([1, 0]);
+        let size = max + Point([1, 0]) - top_left;
+
+        let mut tiles = Grid::new(size.x() as usize + 1, size.y() as usize + 1, Tile::Sand);
+        for &p in clay.iter() {
+            tiles[p - top_left] = Tile::Clay;
+        }
 
-        let spring = Pos { x: 500, y: -10 };
+        let spring = Point([500, -10]);
         Ground {
-            clay: clay,
+            tiles,
+            top_left,
             spring: spring,
-            water: HashMap::new(),
-            min_x: min_x,
-            min_y: min_y,
-            max_x: max_x,
-            max_y: max_y
+            max_y: max.y()
         }
     }
 
-    fn is_occupied(&self, pos: &Pos) -> bool {
-        self.clay.contains(pos) || self.water.get(pos).map(|ws| *ws == WaterState::Resting).unwrap_or(false)
+    // Tile at the position, sand outside of the scanned area
+    fn tile(&self, pos: &Pos) -> &Tile {
+        let p = *pos - self.top_left;
+        self.tiles.get_signed(p.x(), p.y()).unwrap_or(&Tile::Sand)
     }
 
-    fn within_bound(&self, p: &Pos) -> bool {
-        p.x >= self.min_x && p.x <= self.max_x && p.y >= self.min_y && p.y <= self.max_y
+    fn is_occupied(&self, pos: &Pos) -> bool {
+        matches!(self.tile(pos), Tile::Clay | Tile::Water(WaterState::Resting))
     }
 
     fn set_water(&mut self, pos: Pos, ws: WaterState) {
-        if self.within_bound(&pos) {
-            self.water.insert(pos, ws);
+        let p = pos - self.top_left;
+        if let Some(t) = self.tiles.get_signed_mut(p.x(), p.y()) {
+            if *t != Tile::Clay {
+                *t = Tile::Water(ws);
+            }
         }
     }
 
@@ -76,8 +71,8 @@ impl Ground {
         let mut flowed_out = false;
         let mut next_pos = *flow;
         for y in 1.. {
-            let np = flow.translate(0, y);
-            if np.y > self.max_y {
+            let np = *flow + Point([0, y]);
+            if np.y() > self.max_y {
                 flowed_out = true;
                 break;
             }
@@ -95,12 +90,12 @@ impl Ground {
         for &d in [-1, 1].iter() {
             let mut lp = *flow;
             for x in 1.. {
-                let np = flow.translate(x * d, 0);
+                let np = *flow + Point([x * d, 0]);
                 if self.is_occupied(&np) {
                     next.push((lp, false));
                     break;
                 }
-                if !self.is_occupied(&np.translate(0, 1)) {
+                if !self.is_occupied(&(np + Point([0, 1]))) {
                     next.push((np, true));
                     break;
                 }
@@ -111,9 +106,9 @@ impl Ground {
         let open_flows: Vec<_> = next.iter().filter(|(_, o)| *o).map(|(p, _)| *p).collect();
         let (fl, _) = next[0];
         let (fr, _) = next[1];
-        for x in fl.x..=fr.x {
+        for x in fl.x()..=fr.x() {
             self.set_water(
-                Pos { x: x, y: flow.y },
+                Point([x, flow.y()]),
                 if open_flows.len() > 0 { WaterState::Flowing } else { WaterState::Resting }
             );
         }
@@ -121,6 +116,11 @@ impl Ground {
     }
 
     fn fill_water(&mut self) {
+        self.fill_water_with(|_| {});
+    }
+
+    // Fills the ground with water, calling on_step with the ground after every flow step
+    fn fill_water_with<F: FnMut(&Ground)>(&mut self, mut on_step: F) {
         let mut flow_origin = HashMap::new();
         let mut open_flows =  VecDeque::new();
         open_flows.push_back(self.spring);
@@ -150,108 +150,116 @@ impl Ground {
                         }
                     }
                 }
+                on_step(self);
             }
         }
     }
 
     fn water_count(&self) -> usize {
-        self.water.len()
+        self.tiles.iter().filter(|t| matches!(t, Tile::Water(_))).count()
     }
 
     fn resting_water_count(&self) -> usize {
-        self.water.values().filter(|&ws| *ws == WaterState::Resting).count()
+        self.tiles.iter().filter(|&t| *t == Tile::Water(WaterState::Resting)).count()
     }
 
-    #[cfg(feature = "print")]
-    fn print(&self) {
-        println!("({}, {}) - ({}, {})", self.min_x, self.min_y, self.max_x, self.max_y);
-        let mut w_rest = 0;
-        let mut w_flow = 0;
-        for y in self.min_y..=self.max_y {
-            for x in self.min_x..=self.max_x {
-                let p = Pos { x: x, y: y };
-
-                let mut c = if self.clay.contains(&p) { '#' } else { '.' };
-                if self.spring == p {
-                    c = '+';
-                }
-
-                c = self.water.get(&p)
-                    .map(|ws| {
-                        match ws {
-                            WaterState::Resting => { w_rest += 1; '~' },
-                            WaterState::Flowing => { w_flow += 1; '|' }
-                        }
-                    })
-                    .unwrap_or(c);
+    fn render(&self) -> Grid<Rgb> {
+        self.tiles.map(|t| match t {
+            Tile::Sand => Rgb(225, 205, 150),
+            Tile::Clay => Rgb(120, 70, 40),
+            Tile::Water(WaterState::Resting) => Rgb(30, 80, 200),
+            Tile::Water(WaterState::Flowing) => Rgb(120, 180, 240)
+        })
+    }
 
-                print!("{}", c);
-            }
-            println!();
+    fn log_state(&self) {
+        if log_enabled!(Trace) {
+            let bottom_right = self.top_left + Point([self.tiles.width() as i64 - 1, self.tiles.height() as i64 - 1]);
+            trace!("({}) - ({})\n{}", self.top_left, bottom_right, self.tiles.render(|t| match t {
+                Tile::Sand => '.',
+                Tile::Clay => '#',
+                Tile::Water(WaterState::Resting) => '~',
+                Tile::Water(WaterState::Flowing) => '|'
+            }));
         }
-        println!("Water, resting: {}, flowing={}, all={}", w_rest, w_flow, w_rest + w_flow);
+        let (w_rest, w_all) = (self.resting_water_count(), self.water_count());
+        debug!("Water, resting: {}, flowing={}, all={}", w_rest, w_all - w_rest, w_all);
     }
 }
 
-fn parse_input(input: &Vec<String>) -> Ground {
+fn parse_input(input: &str) -> Result<Ground, ParseError> {
     lazy_static! {
-        static ref RE: Regex = Regex::new(r"^(.)=(\d+), .=(\d+)..(\d+)$").unwrap();
+        static ref RE: Regex = Regex::new(r"^(x=(\d+), y|y=(\d+), x)=(\d+)\.\.(\d+)$").unwrap();
     }
-    let mut clay: HashSet<Pos> = HashSet::new();
-    for l in input {
-        let caps = RE.captures(l).unwrap();
-        let get_c = |idx| caps.get(idx).unwrap().as_str().chars().next().unwrap();
-        let get_i = |idx| caps.get(idx).unwrap().as_str().parse::<i32>().unwrap();
-
-        let ax_a = get_c(1);
-        let a = get_i(2);
-        let b1 = get_i(3);
-        let b2 = get_i(4);
+    let mut clay: Vec<Pos> = vec![];
+    let veins = parse_lines(input, |l| {
+        let caps = RE.captures(l)
+            .ok_or_else(|| ParseError::expected("a clay vein like 'x=495, y=2..7'", l))?;
+        let get_i = |idx| caps.get(idx).unwrap().as_str().parse::<i64>();
+
+        let ax_a = if caps.get(2).is_some() { 'x' } else { 'y' };
+        let a = get_i(if ax_a == 'x' { 2 } else { 3 })?;
+        let b1 = get_i(4)?;
+        let b2 = get_i(5)?;
+        if b2 < b1 {
+            return Err(ParseError::expected("a range from low to high", &format!("{}..{}", b1, b2)));
+        }
+        Ok((ax_a, a, b1, b2))
+    })?;
+    for (ax_a, a, b1, b2) in veins {
         let (sx, mx) = if ax_a == 'x' { (a, 0) } else { (b1, 1) };
         let (sy, my) = if ax_a == 'y' { (a, 0) } else { (b1, 1) };
         for i in 0..=(b2 - b1) {
-            clay.insert(Pos { x: sx + i * mx , y: sy + i * my });
+            clay.push(Point([sx + i * mx , sy + i * my]));
         }
     }
-    Ground::new(clay)
+    if clay.len() == 0 {
+        return Err(ParseError::new("No clay veins"));
+    }
+    Ok(Ground::new(clay))
 }
 
-fn part1(input: &Vec<String>) -> usize {
-    let mut ground = parse_input(input);
+pub struct Day17;
 
-    ground.fill_water();
-    #[cfg(feature = "print")] ground.print();
+impl Solution for Day17 {
+    type Input = Ground;
+    type Answer1 = usize;
+    type Answer2 = usize;
 
-    ground.water_count()
-}
+    fn parse(input: &str) -> Result<Ground, ParseError> {
+        parse_input(input)
+    }
 
-fn part2(input: &Vec<String>) -> usize {
-    let mut ground = parse_input(input);
+    fn part1(ground: &Ground) -> usize {
+        let mut ground = ground.clone();
 
-    ground.fill_water();
-    #[cfg(feature = "print")] ground.print();
+        ground.fill_water();
+        ground.log_state();
 
-    ground.resting_water_count()
-}
+        ground.water_count()
+    }
 
-fn main() -> Result<(), Box<Error>> {
-    measure_exec(|| {
-        let result = part1(&input()?);
-        println!("Part1 result: {}", result);
-        Ok(())
-    })?;
-    measure_exec(|| {
-        let result = part2(&input()?);
-        println!("Part2 result: {}", result);
-        Ok(())
-    })?;
-    Ok(())
+    fn part2(ground: &Ground) -> usize {
+        let mut ground = ground.clone();
+
+        ground.fill_water();
+        ground.log_state();
+
+        ground.resting_water_count()
+    }
 }
 
-fn input() -> io::Result<Vec<String>> {
-    let f = File::open("src/day17/input")?;
-    let f = BufReader::new(f);
-    Ok(f.lines().map(|l| l.unwrap()).collect())
+// The water spreading through the ground, a frame per flow step
+impl Animation for Day17 {
+    fn animate(ground: &Ground, frames: &mut Frames) -> io::Result<()> {
+        let mut ground = ground.clone();
+        let mut res = Ok(());
+        ground.fill_water_with(|g| if res.is_ok() {
+            res = frames.tick(|| g.render());
+        });
+        res?;
+        frames.last(|| ground.render())
+    }
 }
 
 #[cfg(test)]
@@ -284,27 +292,27 @@ mod tests {
         x=507, y=11..13
         y=13, x=505..507";
 
-    fn as_input(s: &str) -> Vec<String> {
-        s.split('\n').map(|s| s.trim().into()).collect()
+    fn as_input(s: &str) -> Ground {
+        Day17::parse(s).unwrap()
     }
 
     #[test]
     fn test_part1() {
-        assert_eq!(part1(&as_input(INPUT)), 57);
+        assert_eq!(Day17::part1(&as_input(INPUT)), 57);
     }
 
     #[test]
     fn test_part1_trickier() {
-        assert_eq!(part1(&as_input(INPUT_TRICKIER)), 131);
+        assert_eq!(Day17::part1(&as_input(INPUT_TRICKIER)), 131);
     }
 
     #[test]
     fn test_part2() {
-        assert_eq!(part2(&as_input(INPUT)), 29);
+        assert_eq!(Day17::part2(&as_input(INPUT)), 29);
     }
 
     #[test]
     fn test_part2_trickier() {
-        assert_eq!(part2(&as_input(INPUT_TRICKIER)), 64);
+        assert_eq!(Day17::part2(&as_input(INPUT_TRICKIER)), 64);
     }
 }
\ No newline at end of file
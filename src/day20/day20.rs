@@ -1,24 +1,9 @@
-extern crate utils;
-
 use std::cmp::*;
 use std::collections::HashMap;
-use std::error::Error;
-use std::io::{self, BufReader};
-use std::io::prelude::*;
-use std::fs::File;
-use utils::*;
-
-#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
-struct Pos {
-    x: i32,
-    y: i32
-}
+use point::Point;
+use {ParseError, Solution};
 
-impl Pos {
-    fn transform(&self, x: i32, y: i32) -> Pos {
-        Pos { x: self.x + x, y: self.y + y}
-    }
-}
+type Pos = Point<2>;
 
 struct DirectionsResult {
     steps: usize,
@@ -27,7 +12,7 @@ struct DirectionsResult {
 
 fn follow_directions(r: &[char]) -> DirectionsResult {
     let mut rooms: HashMap<Pos, usize> = HashMap::new();
-    let (steps, _) = dir_iter(r, 0, Pos { x: 0, y: 0 }, &mut rooms);
+    let (steps, _) = dir_iter(r, 0, Point::origin(), &mut rooms);
     DirectionsResult {
         steps: steps,
         rooms: rooms
@@ -62,7 +47,7 @@ fn dir_iter(dir: &[char], psc: usize, p: Pos, rooms: &mut HashMap<Pos, usize>) -
                     'S' => ( 0,  1),
                     _ => unreachable!()
                 };
-                lp = lp.transform(tx, ty);
+                lp = lp + Point([tx, ty]);
                 let d = psc + scount;
                 if !rooms.contains_key(&lp) || *rooms.get(&lp).unwrap() > d {
                     rooms.insert(lp, d);
@@ -77,34 +62,38 @@ fn dir_iter(dir: &[char], psc: usize, p: Pos, rooms: &mut HashMap<Pos, usize>) -
     (if min_s == 0 { 0 } else { max_s }, i)
 }
 
-fn part1(input: &Vec<char>) -> usize {
-    follow_directions(&input[..]).steps
-}
-
-fn part2(input: &Vec<char>, threshold: usize) -> usize {
+fn rooms_at_least(input: &Vec<char>, threshold: usize) -> usize {
     let DirectionsResult { steps: _, rooms } =  follow_directions(&input[..]);
 
     rooms.values().filter(|&d| *d >= threshold).count()
 }
 
-fn main() -> Result<(), Box<Error>> {
-    measure_exec(|| {
-        let result = part1(&input()?);
-        println!("Part1 result: {}", result);
-        Ok(())
-    })?;
-    measure_exec(|| {
-        let result = part2(&input()?, 1000);
-        println!("Part2 result: {}", result);
-        Ok(())
-    })?;
-    Ok(())
-}
+pub struct Day20;
+
+impl Solution for Day20 {
+    type Input = Vec<char>;
+    type Answer1 = usize;
+    type Answer2 = usize;
 
-fn input() -> io::Result<Vec<char>> {
-    let f = File::open("src/day20/input")?;
-    let f = BufReader::new(f);
-    Ok(f.lines().next().unwrap()?.chars().skip(1).take_while(|&c| c != '$').collect())
+    fn parse(input: &str) -> Result<Vec<char>, ParseError> {
+        let regex = input.trim();
+        if !regex.starts_with('^') || !regex.ends_with('$') {
+            return Err(ParseError::expected("a route regex within ^...$", regex));
+        }
+        let dir: Vec<char> = regex.chars().skip(1).take_while(|&c| c != '$').collect();
+        if let Some(c) = dir.iter().find(|c| !"NSEW(|)".contains(**c)) {
+            return Err(ParseError::expected("directions N, S, E or W and branches", &c.to_string()));
+        }
+        Ok(dir)
+    }
+
+    fn part1(input: &Vec<char>) -> usize {
+        follow_directions(&input[..]).steps
+    }
+
+    fn part2(input: &Vec<char>) -> usize {
+        rooms_at_least(input, 1000)
+    }
 }
 
 #[cfg(test)]
@@ -112,26 +101,26 @@ mod tests {
     use super::*;
 
     fn as_input(s: &str) -> Vec<char> {
-        s.chars().skip(1).take_while(|&c| c != '$').collect()
+        Day20::parse(s).unwrap()
     }
 
     #[test]
     fn test_part1() {
-        assert_eq!(part1(&as_input("^WNE$")), 3);
-        assert_eq!(part1(&as_input("^ENWWW(NEEE|SSE(EE|N))$")), 10);
-        assert_eq!(part1(&as_input("^ENNWSWW(NEWS|)SSSEEN(WNSE|)EE(SWEN|)NNN$")), 18);
+        assert_eq!(Day20::part1(&as_input("^WNE$")), 3);
+        assert_eq!(Day20::part1(&as_input("^ENWWW(NEEE|SSE(EE|N))$")), 10);
+        assert_eq!(Day20::part1(&as_input("^ENNWSWW(NEWS|)SSSEEN(WNSE|)EE(SWEN|)NNN$")), 18);
     }
 
     #[test]
     fn test_part2() {
-        assert_eq!(part2(&as_input("^WNE$"), 3), 1);
-        assert_eq!(part2(&as_input("^WNE$"), 4), 0);
-        assert_eq!(part2(&as_input("^ENWWW(NEEE|SSE(EE|N))$"), 9), 4);
-        assert_eq!(part2(&as_input("^ENWWW(NEEE|SSE(EE|N))$"), 10), 1);
-        assert_eq!(part2(&as_input("^ENNWSWW(NEWS|)SSSEEN(WNSE|)EE(SWEN|)NNN$"), 2), 23);
-        assert_eq!(part2(&as_input("^ENNWSWW(NEWS|)SSSEEN(WNSE|)EE(SWEN|)NNN$"), 8), 17);
-        assert_eq!(part2(&as_input("^ENNWSWW(NEWS|)SSSEEN(WNSE|)EE(SWEN|)NNN$"), 10), 13);
-        assert_eq!(part2(&as_input("^ENNWSWW(NEWS|)SSSEEN(WNSE|)EE(SWEN|)NNN$"), 12), 11);
-        assert_eq!(part2(&as_input("^ENNWSWW(NEWS|)SSSEEN(WNSE|)EE(SWEN|)NNN$"), 18), 1);
+        assert_eq!(rooms_at_least(&as_input("^WNE$"), 3), 1);
+        assert_eq!(rooms_at_least(&as_input("^WNE$"), 4), 0);
+        assert_eq!(rooms_at_least(&as_input("^ENWWW(NEEE|SSE(EE|N))$"), 9), 4);
+        assert_eq!(rooms_at_least(&as_input("^ENWWW(NEEE|SSE(EE|N))$"), 10), 1);
+        assert_eq!(rooms_at_least(&as_input("^ENNWSWW(NEWS|)SSSEEN(WNSE|)EE(SWEN|)NNN$"), 2), 23);
+        assert_eq!(rooms_at_least(&as_input("^ENNWSWW(NEWS|)SSSEEN(WNSE|)EE(SWEN|)NNN$"), 8), 17);
+        assert_eq!(rooms_at_least(&as_input("^ENNWSWW(NEWS|)SSSEEN(WNSE|)EE(SWEN|)NNN$"), 10), 13);
+        assert_eq!(rooms_at_least(&as_input("^ENNWSWW(NEWS|)SSSEEN(WNSE|)EE(SWEN|)NNN$"), 12), 11);
+        assert_eq!(rooms_at_least(&as_input("^ENNWSWW(NEWS|)SSSEEN(WNSE|)EE(SWEN|)NNN$"), 18), 1);
     }
 }
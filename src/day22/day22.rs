@@ -1,41 +1,11 @@
-extern crate utils;
+use std::io;
+use grid::Grid;
+use point::Point;
+use render::{Animation, Frames, Rgb};
+use search;
+use {ParseError, Solution};
 
-use std::collections::HashSet;
-use std::collections::HashMap;
-use std::str::FromStr;
-use std::error::Error;
-use std::num::ParseIntError;
-use std::io::{self, BufReader};
-use std::io::prelude::*;
-use std::fs::File;
-use utils::*;
-
-#[derive(PartialEq, Eq, Hash, Clone, Copy)]
-struct Pos {
-    x: usize,
-    y: usize
-}
-
-impl Pos {
-    fn adjacent(&self) -> Vec<Pos> {
-        [(-1i32, 0i32), (0, -1), (1, 0), (0, 1)].iter()
-            .filter(|(mx, my)| !((self.x as i32 + mx) < 0) && !((self.y as i32 + my) < 0))
-            .map(|(mx, my)| Pos { x: (self.x as i32 + mx) as usize, y: (self.y as i32 + my) as usize })
-            .collect()
-    }
-}
-
-impl FromStr for Pos {
-    type Err = ParseIntError;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let vs: Vec<_> = s.split(',').map(|s| s.trim()).collect();
-        Ok(Pos {
-            x: vs[0].parse::<usize>()?,
-            y: vs[1].parse::<usize>()?
-        })
-    }
-}
+type Pos = Point<2>;
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 enum RegionType {
@@ -44,6 +14,7 @@ enum RegionType {
 
 #[derive(Clone, Debug)]
 struct Region {
+    #[allow(dead_code)]
     geo_idx: usize,
     er_levl: usize,
     rtype: RegionType
@@ -72,45 +43,59 @@ enum Tool {
     Torch, Gear, Neither
 }
 
-struct CaveSystem {
+pub struct CaveSystem {
     target: Pos,
-    regions: Vec<Vec<Region>>
+    regions: Grid<Region>
 }
 
 impl CaveSystem {
-    fn parse(input: &Vec<String>) -> CaveSystem {
-        let depth = input[0].split(':').skip(1).map(|s| s.trim().parse::<usize>().unwrap()).next().unwrap();
-        let target = input[1].split(':').skip(1).map(|s| s.trim().parse::<Pos>().unwrap()).next().unwrap();
+    fn parse(input: &str) -> Result<CaveSystem, ParseError> {
+        let mut lines = input.lines().enumerate().map(|(ln, l)| (ln + 1, l.trim())).filter(|(_, l)| !l.is_empty());
+        // The value of the next line, which has to be `<name>: <value>`
+        let mut value = |name: &str| {
+            let (ln, l) = lines.next().ok_or_else(|| ParseError::new(format!("Missing {}", name)))?;
+            l.strip_prefix(name)
+                .and_then(|l| l.strip_prefix(':'))
+                .map(|s| (ln, s.trim()))
+                .ok_or_else(|| ParseError::expected(format!("'{}: <value>'", name), l).at_line(ln))
+        };
+        let (ln, depth) = value("depth")?;
+        let depth = depth.parse::<usize>().map_err(|_| ParseError::expected("a depth", depth).at_line(ln))?;
+        let (ln, target) = value("target")?;
+        let target = target.parse::<Pos>().map_err(|e| e.at_line(ln))?;
+        if target.x() < 0 || target.y() < 0 {
+            return Err(ParseError::new(format!("Target outside of the cave: {}", target)));
+        }
         // Pre cslculate +1000 on both axises from target to handle part2
         let regions = CaveSystem::calc_regions(&target, &depth, 1000, 1000);
-        CaveSystem { target: target, regions: regions }
+        Ok(CaveSystem { target: target, regions: regions })
     }
 
     fn erosion_level(geo_index: usize, depth: &usize) -> usize {
         (geo_index + depth) % 20183
     }
 
-    fn calc_regions(target: &Pos, depth: &usize, wp: usize, hp: usize) -> Vec<Vec<Region>> {
-        let w = target.x + 1 + wp;
-        let h = target.y + 1 + hp;
-        let mut regions: Vec<Vec<Region>> = vec![vec![Region::empty(); w]; h];
+    fn calc_regions(target: &Pos, depth: &usize, wp: usize, hp: usize) -> Grid<Region> {
+        let w = target.x() as usize + 1 + wp;
+        let h = target.y() as usize + 1 + hp;
+        let mut regions = Grid::new(w, h, Region::empty());
 
-        regions[0][0] = Region::new(0, CaveSystem::erosion_level(0, depth));
+        regions[(0, 0)] = Region::new(0, CaveSystem::erosion_level(0, depth));
         for x in 1..w {
             let gi = x * 16807;
-            regions[0][x] = Region::new(gi, CaveSystem::erosion_level(gi, depth));
+            regions[(x, 0)] = Region::new(gi, CaveSystem::erosion_level(gi, depth));
         }
         for y in 1..h {
             let gi = y * 48271;
-            regions[y][0] = Region::new(gi, CaveSystem::erosion_level(gi, depth));
+            regions[(0, y)] = Region::new(gi, CaveSystem::erosion_level(gi, depth));
         }
         for y in 1..h {
             for x in 1..w {
-                if x == target.x && y == target.y {
-                    regions[y][x] = Region::new(0, CaveSystem::erosion_level(0, depth))
+                if x as i64 == target.x() && y as i64 == target.y() {
+                    regions[(x, y)] = Region::new(0, CaveSystem::erosion_level(0, depth))
                 } else {
-                    let gi = regions[y][x - 1].er_levl * regions[y - 1][x].er_levl;
-                    regions[y][x] = Region::new(gi, CaveSystem::erosion_level(gi, depth));
+                    let gi = regions[(x - 1, y)].er_levl * regions[(x, y - 1)].er_levl;
+                    regions[(x, y)] = Region::new(gi, CaveSystem::erosion_level(gi, depth));
                 }
             }
         }
@@ -118,30 +103,24 @@ impl CaveSystem {
     }
 
     fn risk_level(&self) -> usize {
-        let w = self.target.x + 1;
-        let h = self.target.y + 1;
-        let mut rls = 0;
-        for y in 0..h {
-            for x in 0..w {
-                rls += match self.regions[y][x].rtype {
-                    RegionType::Rocky => 0,
-                    RegionType::Wet => 1,
-                    RegionType::Narrow => 2
-                };
-            }
-        }
-        rls
+        self.regions.view(0, 0, self.target.x() as usize + 1, self.target.y() as usize + 1)
+            .iter()
+            .map(|r| match r.rtype {
+                RegionType::Rocky => 0,
+                RegionType::Wet => 1,
+                RegionType::Narrow => 2
+            })
+            .sum()
     }
 
-    fn quickest_to_target(&mut self) -> usize {
-        use Tool::*;
-        use RegionType::*;
+    fn quickest_to_target(&self) -> usize {
+        self.quickest_path().0
+    }
 
-        let mut visited: HashMap<(Pos, Tool), usize> = HashMap::new();
-        let mut to_eval: HashSet<(Pos, Tool, usize)> = HashSet::new();
-        let start_pos = Pos { x: 0, y: 0 };
-        visited.insert((start_pos, Torch), 0);
-        to_eval.insert((start_pos, Torch, 0));
+    // Minutes to reach the target with the torch, and the regions and tools on the way
+    fn quickest_path(&self) -> (usize, Vec<(Pos, Tool)>) {
+        use self::Tool::*;
+        use self::RegionType::*;
 
         let tool_valid = |rt: &RegionType, t: &Tool| {
             match (rt, t) {
@@ -152,111 +131,90 @@ impl CaveSystem {
             }
         };
 
-        while to_eval.len() > 0 {
-            let mut next_eval: HashSet<(Pos, Tool, usize)> = HashSet::new();
-
-            { // Block needed to handle borrowing of next_eval
-                let min_d = to_eval.iter().map(|(_, _, d)| d).min().unwrap();
-                let eval_now: Vec<_> = to_eval.iter().filter(|(_, _, d)| *d == *min_d).collect();
-                let eval_later: Vec<_> = to_eval.iter().filter(|(_, _, d)| *d != *min_d).map(|e| e.to_owned()).collect();
-
-                for (p, t, d) in eval_now {
-
-                    // Check if target is reached
-                    if *t == Torch && p.x == self.target.x && p.y == self.target.y {
-                        return *d;
-                    }
+        // Moving to adjacent regions keeping the tool, or changing tool in place
+        let successors = |&(p, t): &(Pos, Tool)| {
+            p.neighbours().into_iter()
+                .filter(|ap| self.regions.get_signed(ap.x(), ap.y()).is_some_and(|r| tool_valid(&r.rtype, &t)))
+                .map(|ap| ((ap, t), 1))
+                .chain([Torch, Gear, Neither].iter()
+                    .filter(|&&nt| nt != t && tool_valid(&self.regions[p].rtype, &nt))
+                    .map(|&nt| ((p, nt), 7)))
+                .collect::<Vec<_>>()
+        };
 
-                    // Add possible adjacent to later evaluation
-                    for &ap in p.adjacent().iter() {
-                        let r = &self.regions[ap.y][ap.x];
-                        if !tool_valid(&r.rtype, t) {
-                            continue;
-                        }
-                        let nd = *d + 1;
-                        let k = (ap, *t);
-                        if visited.contains_key(&k) && *visited.get(&k).unwrap() < nd {
-                            continue;
-                        }
-                        visited.insert(k, nd);
-                        next_eval.insert((ap, *t, nd));
-                    }
+        // At least the distance, plus a tool change unless holding the torch
+        let heuristic = |&(p, t): &(Pos, Tool)| {
+            p.manhattan(&self.target) as usize + if t == Torch { 0 } else { 7 }
+        };
 
-                    // Add possible tool changes to later evaluation
-                    for &nt in [Torch, Gear, Neither].iter() {
-                        let r = &self.regions[p.y][p.x];
-                        if nt == *t || !tool_valid(&r.rtype, &nt) {
-                            continue;
-                        }
-                        let nd = *d + 7;
-                        let k = (*p, nt);
-                        if visited.contains_key(&k) && *visited.get(&k).unwrap() < nd {
-                            continue;
-                        }
-                        visited.insert(k, nd);
-                        next_eval.insert((*p, nt, nd));
-                    }
-                }
+        search::astar((Point::origin(), Torch), successors, heuristic, |&s| s == (self.target, Torch))
+            .expect("Target not reachable")
+    }
 
-                for e in eval_later {
-                    next_eval.insert(e);
-                }
-            }
-            to_eval = next_eval;
+    // The cave around the quickest path, coloured by the tool used in each region
+    fn render(&self) -> Grid<Rgb> {
+        use self::RegionType::*;
+        let (_, path) = self.quickest_path();
+        let (_, max) = Point::bounding_box(path.iter().map(|(p, _)| p)).unwrap();
+        let mut grid = self.regions.view(0, 0, max.x() as usize + 3, max.y() as usize + 3).to_grid()
+            .map(|r| match r.rtype {
+                Rocky => Rgb(110, 110, 110),
+                Wet => Rgb(40, 70, 150),
+                Narrow => Rgb(40, 40, 40)
+            });
+        for (p, t) in path {
+            grid[p] = match t {
+                Tool::Torch => Rgb(250, 200, 40),
+                Tool::Gear => Rgb(240, 110, 30),
+                Tool::Neither => Rgb(240, 240, 240)
+            };
         }
-        unreachable!()
+        grid[Point::origin()] = Rgb(220, 30, 30);
+        grid[self.target] = Rgb(220, 30, 30);
+        grid
     }
 
-    #[cfg(feature = "print")]
-    fn print(&self) {
-        use RegionType::*;
-        for y in 0..self.target.y + 5 {
-            for x in 0..self.target.x + 5 {
-                let mut c = match self.regions[y][x].rtype {
-                    Rocky => '.',
-                    Wet => '=',
-                    Narrow => '|'
-                };
-                if x == self.target.x && y == self.target.y {
-                    c = 'T';
-                }
-                print!("{}", c);
-            }
-            println!();
-        }
+    fn draw(&self) -> Grid<char> {
+        use self::RegionType::*;
+        let (w, h) = (self.target.x() as usize + 5, self.target.y() as usize + 5);
+        let mut map = self.regions.view(0, 0, w, h).to_grid()
+            .map(|r| match r.rtype {
+                Rocky => '.',
+                Wet => '=',
+                Narrow => '|'
+            });
+        map[self.target] = 'T';
+        map
     }
 }
 
-fn part1(input: &Vec<String>) -> usize {
-    let cs = CaveSystem::parse(input);
-    #[cfg(feature = "print")] cs.print();
-    cs.risk_level()
-}
+pub struct Day22;
 
-fn part2(input: &Vec<String>) -> usize {
-    let mut cs = CaveSystem::parse(input);
-    #[cfg(feature = "print")] cs.print();
-    cs.quickest_to_target()
-}
+impl Solution for Day22 {
+    type Input = CaveSystem;
+    type Answer1 = usize;
+    type Answer2 = usize;
+
+    fn parse(input: &str) -> Result<CaveSystem, ParseError> {
+        CaveSystem::parse(input)
+    }
 
-fn main() -> Result<(), Box<Error>> {
-    measure_exec(|| {
-        let result = part1(&input()?);
-        println!("Part1 result: {}", result);
-        Ok(())
-    })?;
-    measure_exec(|| {
-        let result = part2(&input()?);
-        println!("Part2 result: {}", result);
-        Ok(())
-    })?;
-    Ok(())
+    fn part1(cs: &CaveSystem) -> usize {
+        trace!("Cave:\n{}", cs.draw());
+        cs.risk_level()
+    }
+
+    fn part2(cs: &CaveSystem) -> usize {
+        trace!("Cave:\n{}", cs.draw());
+        cs.quickest_to_target()
+    }
 }
 
-fn input() -> io::Result<Vec<String>> {
-    let f = File::open("src/day22/input")?;
-    let f = BufReader::new(f);
-    Ok(f.lines().map(|l| l.unwrap()).collect())
+// A single frame of the quickest way to the target
+impl Animation for Day22 {
+    fn animate(cs: &CaveSystem, frames: &mut Frames) -> io::Result<()> {
+        frames.last(|| cs.render())
+    }
 }
 
 #[cfg(test)]
@@ -267,17 +225,17 @@ mod tests {
        "depth: 510
         target: 10,10";
 
-    fn as_input(s: &str) -> Vec<String> {
-        s.split('\n').map(|s| s.trim().into()).collect()
+    fn as_input(s: &str) -> CaveSystem {
+        Day22::parse(s).unwrap()
     }
 
     #[test]
     fn test_part1() {
-        assert_eq!(part1(&as_input(INPUT)), 114);
+        assert_eq!(Day22::part1(&as_input(INPUT)), 114);
     }
 
     #[test]
     fn test_part2() {
-        assert_eq!(part2(&as_input(INPUT)), 45);
+        assert_eq!(Day22::part2(&as_input(INPUT)), 45);
     }
 }
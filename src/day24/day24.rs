@@ -1,15 +1,7 @@
-extern crate regex;
-#[macro_use] extern crate lazy_static;
-extern crate utils;
-
 use std::cmp;
 use std::collections::HashSet;
-use std::error::Error;
-use std::io::{self, BufReader};
-use std::io::prelude::*;
-use std::fs::File;
 use regex::Regex;
-use utils::*;
+use {ParseError, Solution};
 
 #[derive(PartialEq, Eq, Clone, Debug)]
 struct Group {
@@ -24,13 +16,15 @@ struct Group {
 }
 
 impl Group {
-    fn parse(input: &str) -> Group {
+    fn parse(input: &str) -> Result<Group, ParseError> {
         lazy_static! {
             static ref RE: Regex = Regex::new(r"^(\d+)\s.+\s(\d+)\s.+\((.+)\).+\s(\d+)\s(\w+)\s.+\s(\d+)$").unwrap();
             static ref RE2: Regex = Regex::new(r"^(\d+)\s.+\s(\d+)\s.+(with).+\s(\d+)\s(\w+)\s.+\s(\d+)$").unwrap();
         }
-        let caps = if RE.is_match(input) { RE.captures(input).unwrap() } else { RE2.captures(input).unwrap() };
-        let geti = |idx| caps.get(idx).unwrap().as_str().parse::<i32>().unwrap();
+        let caps = RE.captures(input)
+            .or_else(|| RE2.captures(input))
+            .ok_or_else(|| ParseError::expected("a group like '<n> units each with <n> hit points ... at initiative <n>'", input))?;
+        let geti = |idx| caps.get(idx).unwrap().as_str().parse::<i32>();
         let gets = |idx| caps.get(idx).unwrap().as_str().to_string();
 
         let mut weaknesses: HashSet<String> = HashSet::new();
@@ -43,16 +37,16 @@ impl Group {
             }
         }
 
-        Group {
-            n_units: geti(1),
-            hp: geti(2),
-            attack_d: geti(4),
+        Ok(Group {
+            n_units: geti(1)?,
+            hp: geti(2)?,
+            attack_d: geti(4)?,
             attack_boost: 0,
             attack_t: gets(5),
-            initiative: geti(6),
+            initiative: geti(6)?,
             weaknesses: weaknesses,
             immunities: immunities
-        }
+        })
     }
 
     fn effective_power(&self) -> i32 {
@@ -84,7 +78,7 @@ enum ArmyType {
 }
 
 #[derive(Clone, Debug)]
-struct Army {
+pub struct Army {
     t: ArmyType,
     groups: Vec<Group>
 }
@@ -120,13 +114,23 @@ impl Army {
             result.push((i, ti));
             t_taken.insert(ti);
 
-            #[cfg(feature = "print")] for (ti, tg) in ts {
-                println!("{:?} group {} would deal defending group {} {} damage", self.t, i + 1, ti + 1, g.max_damage_to(tg));
+            if log_enabled!(Trace) {
+                for (ti, tg) in ts {
+                    trace!("{:?} group {} would deal defending group {} {} damage", self.t, i + 1, ti + 1, g.max_damage_to(tg));
+                }
             }
         }
         result
     }
 
+    fn describe(&self) -> String {
+        let mut s = format!("{:?}:", self.t);
+        for (i, g) in self.groups.iter().enumerate().filter(|(_, g)| !g.beaten()) {
+            s += &format!("\nGroup {} contains {} units", i + 1, g.n_units);
+        }
+        s
+    }
+
     fn beaten(&self) -> bool {
         self.groups.iter().all(|g| g.beaten())
     }
@@ -142,47 +146,26 @@ impl Army {
     }
 }
 
-fn parse_input(input: &Vec<String>) -> (Army, Army) {
+fn parse_input(input: &str) -> Result<(Army, Army), ParseError> {
     let mut im_grps = vec![];
     let mut in_grps = vec![];
 
-    let mut in_im = true;
-    let mut i = 1;
-    while i < input.len() {
-        if input[i].len() == 0 {
-            i += 2;
-            in_im = false;
-        }
-        if in_im {
-            im_grps.push(Group::parse(&input[i]));
-        } else {
-            in_grps.push(Group::parse(&input[i]));
+    let mut grps = None;
+    for (ln, l) in input.lines().enumerate().map(|(ln, l)| (ln + 1, l.trim())).filter(|(_, l)| l.len() > 0) {
+        match l {
+            "Immune System:" => grps = Some(&mut im_grps),
+            "Infection:" => grps = Some(&mut in_grps),
+            _ => grps.as_mut()
+                .ok_or_else(|| ParseError::expected("'Immune System:' or 'Infection:' before the groups", l).at_line(ln))?
+                .push(Group::parse(l).map_err(|e| e.at_line(ln).in_text(l))?)
         }
-        i += 1;
     }
 
-    ( Army { t: ArmyType::ImmuneSystem, groups: im_grps }, Army { t: ArmyType::Infection, groups: in_grps })
+    Ok(( Army { t: ArmyType::ImmuneSystem, groups: im_grps }, Army { t: ArmyType::Infection, groups: in_grps }))
 }
 
 fn fight(armies: [&mut Army; 2]) {
-    #[cfg(feature = "print")] {
-        println!("{:?}:", armies[0].t);
-        for i in 0..armies[0].groups.len() {
-            if armies[0].groups[i].beaten() {
-                continue;
-            }
-            println!("Group {} contains {} units", i + 1, armies[0].groups[i].n_units);
-        }
-
-        println!("{:?}:", armies[1].t);
-        for i in 0..armies[1].groups.len() {
-            if armies[1].groups[i].beaten() {
-                continue;
-            }
-            println!("Group {} contains {} units", i + 1, armies[1].groups[i].n_units);
-        }
-        println!();
-    }
+    trace!("{}\n{}", armies[0].describe(), armies[1].describe());
 
     let mut attacks: Vec<_> = (0..armies.len()).zip((0..armies.len()).cycle().skip(1))
         .flat_map(|(aai, dai)| {
@@ -196,86 +179,69 @@ fn fight(armies: [&mut Army; 2]) {
         armies[*bai].groups[*bgi].initiative.cmp(&armies[*aai].groups[*agi].initiative)
     });
 
-    #[cfg(feature = "print")] println!();
-
     for ((aai, agi), (dai, dgi)) in attacks {
-        let _dgu_bef = armies[dai].groups[dgi].n_units;
+        let dgu_bef = armies[dai].groups[dgi].n_units;
         let damage_dealt = armies[aai].groups[agi].max_damage_to(&armies[dai].groups[dgi]);
         armies[dai].groups[dgi].take_damage(damage_dealt);
-        #[cfg(feature = "print")] {
-            println!("{:?} group {} attacks defending group {}, killing {} units", armies[aai].t, agi + 1, dgi + 1, _dgu_bef - armies[dai].groups[dgi].n_units);
-        }
+        trace!("{:?} group {} attacks defending group {}, killing {} units", armies[aai].t, agi + 1, dgi + 1, dgu_bef - armies[dai].groups[dgi].n_units);
     }
 }
 
-fn part1(input: &Vec<String>) -> i32 {
-    let (mut imsys, mut infec) = parse_input(input);
+pub struct Day24;
 
-    for _r in 0.. {
-        #[cfg(feature = "print")] {
-            println!("-----------------------------------------------------------------");
-            println!("Round: {}", _r + 1);
-        }
-        fight([&mut infec, &mut imsys]);
+impl Solution for Day24 {
+    type Input = (Army, Army);
+    type Answer1 = i32;
+    type Answer2 = i32;
 
-        if imsys.beaten() || infec.beaten() {
-            break;
-        }
+    fn parse(input: &str) -> Result<(Army, Army), ParseError> {
+        parse_input(input)
     }
 
-    if imsys.beaten() { infec.units_left() } else { imsys.units_left() }
-}
+    fn part1((imsys, infec): &(Army, Army)) -> i32 {
+        let (mut imsys, mut infec) = (imsys.clone(), infec.clone());
 
-fn part2(input: &Vec<String>) -> i32 {
-    let (imsys, infec) = parse_input(input);
-
-    let mut units_left = 0;
-    for boost in 1.. {
-        let mut ims = imsys.clone();
-        let mut inf = infec.clone();
-        ims.boost(boost);
-        #[cfg(feature = "print")] println!("Trying boost {}", boost);
-
-        let mut tie = false;
-        for _ in 0.. {
-            let units_before = inf.units_left() + ims.units_left();
-            fight([&mut inf, &mut ims]);
-            if units_before == inf.units_left() + ims.units_left() {
-                tie = true;
-                break;
-            }
+        for r in 0.. {
+            trace!("Round: {}", r + 1);
+            fight([&mut infec, &mut imsys]);
 
-            if ims.beaten() || inf.beaten() {
+            if imsys.beaten() || infec.beaten() {
                 break;
             }
         }
 
-        if !ims.beaten() && !tie {
-            units_left = ims.units_left();
-            break;
-        }
+        if imsys.beaten() { infec.units_left() } else { imsys.units_left() }
     }
-    units_left
-}
 
-fn main() -> Result<(), Box<Error>> {
-    measure_exec(|| {
-        let result = part1(&input()?);
-        println!("Part1 result: {}", result);
-        Ok(())
-    })?;
-    measure_exec(|| {
-        let result = part2(&input()?);
-        println!("Part2 result: {}", result);
-        Ok(())
-    })?;
-    Ok(())
-}
+    fn part2((imsys, infec): &(Army, Army)) -> i32 {
+        let mut units_left = 0;
+        for boost in 1.. {
+            let mut ims = imsys.clone();
+            let mut inf = infec.clone();
+            ims.boost(boost);
+            debug!("Trying boost {}", boost);
+
+            let mut tie = false;
+            for _ in 0.. {
+                let units_before = inf.units_left() + ims.units_left();
+                fight([&mut inf, &mut ims]);
+                if units_before == inf.units_left() + ims.units_left() {
+                    tie = true;
+                    break;
+                }
+
+                if ims.beaten() || inf.beaten() {
+                    break;
+                }
+            }
 
-fn input() -> io::Result<Vec<String>> {
-    let f = File::open("src/day24/input")?;
-    let f = BufReader::new(f);
-    Ok(f.lines().map(|l| l.unwrap()).collect())
+            if !ims.beaten() && !tie {
+                units_left = ims.units_left();
+                break;
+            }
+        }
+        units_left
+    }
 }
 
 #[cfg(test)]
@@ -291,14 +257,14 @@ mod tests {
         801 units each with 4706 hit points (weak to radiation) with an attack that does 116 bludgeoning damage at initiative 1
         4485 units each with 2961 hit points (immune to radiation; weak to fire, cold) with an attack that does 12 slashing damage at initiative 4";
 
-    fn as_input(s: &str) -> Vec<String> {
-        s.split('\n').map(|s| s.trim().into()).collect()
+    fn as_input(s: &str) -> (Army, Army) {
+        Day24::parse(s).unwrap()
     }
 
     #[test]
     fn test_parse_group() {
         let input = "18 units each with 729 hit points (weak to fire; immune to cold, slashing) with an attack that does 8 radiation damage at initiative 10";
-        let g = Group::parse(input);
+        let g = Group::parse(input).unwrap();
         assert_eq!(g, Group {
             n_units: 18,
             hp: 729,
@@ -310,7 +276,7 @@ mod tests {
             immunities: ["cold", "slashing"].iter().map(|s| s.to_string()).collect()
         });
         let input = "6799 units each with 3314 hit points with an attack that does 4 radiation damage at initiative 16";
-        let g = Group::parse(input);
+        let g = Group::parse(input).unwrap();
         assert_eq!(g, Group {
             n_units: 6799,
             hp: 3314,
@@ -323,13 +289,21 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_parse_error() {
+        let e = Day24::parse("Immune System:\n17 units each with 5390 hit points").unwrap_err();
+        assert_eq!(e.line(), Some(2));
+        let e = Day24::parse("17 units each with 5390 hit points with an attack that does 4507 fire damage at initiative 2").unwrap_err();
+        assert_eq!(e.expectation(), Some("'Immune System:' or 'Infection:' before the groups"));
+    }
+
     #[test]
     fn test_part1() {
-        assert_eq!(part1(&as_input(INPUT)), 5216);
+        assert_eq!(Day24::part1(&as_input(INPUT)), 5216);
     }
 
     #[test]
     fn test_part2() {
-        assert_eq!(part2(&as_input(INPUT)), 51);
+        assert_eq!(Day24::part2(&as_input(INPUT)), 51);
     }
 }
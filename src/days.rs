@@ -0,0 +1,325 @@
+use std::any::Any;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::str::FromStr;
+use render::{Animation, Frames};
+use {Answer, ParseError, Solution};
+
+use day01;
+use day02;
+use day03;
+use day04;
+use day05;
+use day06;
+use day07;
+use day08;
+use day09;
+use day10;
+use day11;
+use day12;
+use day13;
+use day14;
+use day15;
+use day16;
+use day17;
+use day18;
+use day19;
+use day20;
+use day21;
+use day22;
+use day23;
+use day24;
+use day25;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Part {
+    One, Two
+}
+
+impl Part {
+    pub fn all() -> Vec<Part> {
+        vec![Part::One, Part::Two]
+    }
+}
+
+impl FromStr for Part {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "1" => Ok(Part::One),
+            "2" => Ok(Part::Two),
+            _ => Err(format!("Invalid part '{}', expected 1 or 2", s))
+        }
+    }
+}
+
+impl fmt::Display for Part {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(match self {
+            Part::One => "1",
+            Part::Two => "2"
+        })
+    }
+}
+
+// Environment variable pointing to a directory laid out like src/, i.e. with dayNN/input files
+pub const INPUT_DIR_VAR: &str = "AOC_INPUT_DIR";
+
+// Where to read the puzzle input of a day from
+#[derive(PartialEq, Clone, Debug)]
+pub enum InputSource {
+    Default,
+    File(PathBuf),
+    Stdin
+}
+
+impl FromStr for InputSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "" => Err("Empty input path".to_string()),
+            "-" => Ok(InputSource::Stdin),
+            _ => Ok(InputSource::File(PathBuf::from(s)))
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct InputError {
+    day: u32,
+    path: Option<PathBuf>,
+    cause: io::Error
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.path {
+            Some(ref path) if self.cause.kind() == io::ErrorKind::NotFound =>
+                write!(f, "No input for day {:02} at '{}', pass --input <path> or set {}",
+                       self.day, path.display(), INPUT_DIR_VAR),
+            Some(ref path) =>
+                write!(f, "Could not read input for day {:02} from '{}': {}", self.day, path.display(), self.cause),
+            None =>
+                write!(f, "Could not read input for day {:02} from stdin: {}", self.day, self.cause)
+        }
+    }
+}
+
+impl Error for InputError {}
+
+// Parsed input of a day, the concrete type is only known by the day's solution
+pub struct ParsedInput(Box<dyn Any>);
+
+pub struct Day {
+    pub num: u32,
+    pub title: &'static str,
+    parse: fn(&str) -> Result<ParsedInput, ParseError>,
+    part1: fn(&ParsedInput) -> Result<String, Box<dyn Error>>,
+    part2: fn(&ParsedInput) -> Result<String, Box<dyn Error>>,
+    animate: Option<fn(&ParsedInput, &mut Frames) -> io::Result<()>>
+}
+
+fn parse_with<S: Solution>(input: &str) -> Result<ParsedInput, ParseError>
+where S::Input: 'static {
+    Ok(ParsedInput(Box::new(S::parse(input)?)))
+}
+
+fn part1_with<S: Solution>(input: &ParsedInput) -> Result<String, Box<dyn Error>>
+where S::Input: 'static {
+    S::part1(input.0.downcast_ref().unwrap()).into_result()
+}
+
+fn part2_with<S: Solution>(input: &ParsedInput) -> Result<String, Box<dyn Error>>
+where S::Input: 'static {
+    S::part2(input.0.downcast_ref().unwrap()).into_result()
+}
+
+fn animate_with<A: Animation>(input: &ParsedInput, frames: &mut Frames) -> io::Result<()>
+where A::Input: 'static {
+    A::animate(input.0.downcast_ref().unwrap(), frames)
+}
+
+impl Day {
+    const fn new<S: Solution>(num: u32, title: &'static str) -> Day
+    where S::Input: 'static {
+        Day {
+            num,
+            title,
+            parse: parse_with::<S>,
+            part1: part1_with::<S>,
+            part2: part2_with::<S>,
+            animate: None
+        }
+    }
+
+    // For days whose solution can also draw its simulation
+    const fn with_animation<A: Animation>(self) -> Day
+    where A::Input: 'static {
+        Day { animate: Some(animate_with::<A>), ..self }
+    }
+
+    pub fn has_animation(&self) -> bool {
+        self.animate.is_some()
+    }
+
+    // The input is looked up in AOC_INPUT_DIR when set, otherwise in the src/ dir of the crate so
+    // that it's found regardless of the working directory
+    pub fn input_path(&self) -> PathBuf {
+        let dir = env::var_os(INPUT_DIR_VAR)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src"));
+        dir.join(format!("day{:02}", self.num)).join("input")
+    }
+
+    pub fn read_input(&self, source: &InputSource) -> Result<String, InputError> {
+        let path = match source {
+            InputSource::Default => Some(self.input_path()),
+            InputSource::File(path) => Some(path.to_owned()),
+            InputSource::Stdin => None
+        };
+        let res = match path {
+            Some(ref path) => fs::read_to_string(path),
+            None => {
+                let mut input = String::new();
+                io::stdin().read_to_string(&mut input).map(|_| input)
+            }
+        };
+        res.map_err(|e| InputError { day: self.num, path, cause: e })
+    }
+
+    pub fn parse(&self, input: &str) -> Result<ParsedInput, ParseError> {
+        (self.parse)(input)
+    }
+
+    // The answer of the part, or why the input has none
+    pub fn solve(&self, input: &ParsedInput, part: Part) -> Result<String, Box<dyn Error>> {
+        match part {
+            Part::One => (self.part1)(input),
+            Part::Two => (self.part2)(input)
+        }
+    }
+
+    // Writes the frames of the day's simulation, None when the day has no animation
+    pub fn animate(&self, input: &ParsedInput, frames: &mut Frames) -> Option<io::Result<()>> {
+        self.animate.map(|a| a(input, frames))
+    }
+}
+
+pub const DAYS: [Day; 25] = [
+    Day::new::<day01::Day01>(1,  "Chronal Calibration"),
+    Day::new::<day02::Day02>(2,  "Inventory Management System"),
+    Day::new::<day03::Day03>(3,  "No Matter How You Slice It"),
+    Day::new::<day04::Day04>(4,  "Repose Record"),
+    Day::new::<day05::Day05>(5,  "Alchemical Reduction"),
+    Day::new::<day06::Day06>(6,  "Chronal Coordinates"),
+    Day::new::<day07::Day07>(7,  "The Sum of Its Parts"),
+    Day::new::<day08::Day08>(8,  "Memory Maneuver"),
+    Day::new::<day09::Day09>(9,  "Marble Mania"),
+    Day::new::<day10::Day10>(10, "The Stars Align"),
+    Day::new::<day11::Day11>(11, "Chronal Charge"),
+    Day::new::<day12::Day12>(12, "Subterranean Sustainability"),
+    Day::new::<day13::Day13>(13, "Mine Cart Madness").with_animation::<day13::Day13>(),
+    Day::new::<day14::Day14>(14, "Chocolate Charts"),
+    Day::new::<day15::Day15>(15, "Beverage Bandits").with_animation::<day15::Day15>(),
+    Day::new::<day16::Day16>(16, "Chronal Classification"),
+    Day::new::<day17::Day17>(17, "Reservoir Research").with_animation::<day17::Day17>(),
+    Day::new::<day18::Day18>(18, "Settlers of The North Pole").with_animation::<day18::Day18>(),
+    Day::new::<day19::Day19>(19, "Go With The Flow"),
+    Day::new::<day20::Day20>(20, "A Regular Map"),
+    Day::new::<day21::Day21>(21, "Chronal Conversion"),
+    Day::new::<day22::Day22>(22, "Mode Maze").with_animation::<day22::Day22>(),
+    Day::new::<day23::Day23>(23, "Experimental Emergency Teleportation"),
+    Day::new::<day24::Day24>(24, "Immune System Simulator 20XX"),
+    Day::new::<day25::Day25>(25, "Four-Dimensional Adventure"),
+];
+
+pub fn find(num: u32) -> Option<&'static Day> {
+    DAYS.iter().find(|d| d.num == num)
+}
+
+// Select days from a spec like "all", "15", "1-5" or "1,3,10-12"
+pub fn select(spec: &str) -> Result<Vec<&'static Day>, String> {
+    if spec.trim() == "all" {
+        return Ok(DAYS.iter().collect());
+    }
+
+    let parse_num = |s: &str| {
+        s.trim().parse::<u32>()
+            .ok()
+            .and_then(find)
+            .map(|d| d.num)
+            .ok_or_else(|| format!("Invalid day '{}', expected 1-{}", s.trim(), DAYS.len()))
+    };
+
+    let mut selected = vec![];
+    for range in spec.split(',') {
+        let mut ends = range.splitn(2, '-');
+        let from = parse_num(ends.next().unwrap())?;
+        let to = match ends.next() {
+            Some(s) => parse_num(s)?,
+            None => from
+        };
+        if from > to {
+            return Err(format!("Invalid range '{}', the first day is after the last", range.trim()));
+        }
+        for num in from..=to {
+            let day = find(num).unwrap();
+            if !selected.iter().any(|d: &&Day| d.num == num) {
+                selected.push(day);
+            }
+        }
+    }
+    Ok(selected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nums(days: Vec<&Day>) -> Vec<u32> {
+        days.iter().map(|d| d.num).collect()
+    }
+
+    #[test]
+    fn test_days_in_order() {
+        assert_eq!(nums(DAYS.iter().collect()), (1..=25).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_select() {
+        assert_eq!(nums(select("15").unwrap()), vec![15]);
+        assert_eq!(nums(select("1-3").unwrap()), vec![1, 2, 3]);
+        assert_eq!(nums(select("3,1,2-4").unwrap()), vec![3, 1, 2, 4]);
+        assert_eq!(select("all").unwrap().len(), 25);
+        assert!(select("26").is_err());
+        assert!(select("x").is_err());
+        assert_eq!(select("5-3").err(), Some("Invalid range '5-3', the first day is after the last".to_string()));
+    }
+
+    #[test]
+    fn test_input_source_parse() {
+        assert_eq!("-".parse::<InputSource>(), Ok(InputSource::Stdin));
+        assert_eq!("in.txt".parse::<InputSource>(), Ok(InputSource::File(PathBuf::from("in.txt"))));
+        assert!("".parse::<InputSource>().is_err());
+    }
+
+    #[test]
+    fn test_missing_input() {
+        let day = find(15).unwrap();
+        let err = day.read_input(&InputSource::File(PathBuf::from("no/such/input"))).unwrap_err();
+        assert_eq!(err.to_string(), "No input for day 15 at 'no/such/input', pass --input <path> or set AOC_INPUT_DIR");
+    }
+
+    #[test]
+    fn test_part_parse() {
+        assert_eq!("1".parse::<Part>(), Ok(Part::One));
+        assert_eq!("2".parse::<Part>(), Ok(Part::Two));
+        assert!("3".parse::<Part>().is_err());
+    }
+}
@@ -0,0 +1,296 @@
+// Assembler and linter for the textual form of ElfCode, reporting problems with their line and
+// column, and a disassembler for the numeric form of day 16
+use std::fmt;
+use elfcode::decompile::{flow, Flow};
+use elfcode::{Instruction, OpCode, Program};
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Severity {
+    Warning, Error
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub column: usize,
+    pub severity: Severity,
+    pub message: String
+}
+
+impl Diagnostic {
+    fn error<S: Into<String>>(line: usize, column: usize, message: S) -> Diagnostic {
+        Diagnostic { line, column, severity: Severity::Error, message: message.into() }
+    }
+
+    fn warning<S: Into<String>>(line: usize, column: usize, message: S) -> Diagnostic {
+        Diagnostic { line, column, severity: Severity::Warning, message: message.into() }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error"
+        };
+        write!(f, "{}:{}: {}: {}", self.line, self.column, severity, self.message)
+    }
+}
+
+// Whitespace separated tokens of the line with their 1-based column
+fn tokens(line: &str) -> Vec<(usize, &str)> {
+    let mut tokens = vec![];
+    let mut start = None;
+    for (i, c) in line.char_indices().chain(Some((line.len(), ' '))) {
+        match (start, c.is_whitespace()) {
+            (None, false) => start = Some(i),
+            (Some(s), true) => {
+                tokens.push((line[..s].chars().count() + 1, &line[s..i]));
+                start = None;
+            },
+            _ => ()
+        }
+    }
+    tokens
+}
+
+// Instruction assembled from a source line, with the location of the output operand
+struct Assembled {
+    ins: Instruction,
+    line: usize,
+    out_column: usize
+}
+
+pub struct Assembler {
+    // Size of the register file, register operands must be below it
+    pub registers: usize
+}
+
+impl Default for Assembler {
+    // The six registers of the device in days 19 and 21
+    fn default() -> Assembler {
+        Assembler::new(6)
+    }
+}
+
+impl Assembler {
+    pub fn new(registers: usize) -> Assembler {
+        Assembler { registers }
+    }
+
+    fn register(&self, line: usize, column: usize, r: u32, diags: &mut Vec<Diagnostic>) {
+        if r as usize >= self.registers {
+            diags.push(Diagnostic::error(line, column, format!("Register {} out of range, the device has {} registers", r, self.registers)));
+        }
+    }
+
+    // Checks the operands following the opcode, which starts at column
+    fn operands(&self, opc: OpCode, ln: usize, column: usize, ops: &[(usize, &str)], diags: &mut Vec<Diagnostic>) -> Option<Assembled> {
+        if ops.len() != 3 {
+            let col = ops.get(3).map(|&(c, _)| c).unwrap_or(column);
+            diags.push(Diagnostic::error(ln, col, format!("{} expects 3 operands, found {}", opc.name(), ops.len())));
+            return None;
+        }
+
+        let mut values = [0; 3];
+        let mut valid = true;
+        for (i, &(col, op)) in ops.iter().enumerate() {
+            match op.parse::<u32>() {
+                Ok(v) => values[i] = v,
+                Err(_) => {
+                    diags.push(Diagnostic::error(ln, col, format!("Expected a number, found '{}'", op)));
+                    valid = false;
+                }
+            }
+        }
+        if !valid {
+            return None;
+        }
+
+        let (reg_a, reg_b) = opc.reg_operands();
+        let n_errors = diags.len();
+        for (i, &is_reg) in [reg_a, reg_b, true].iter().enumerate() {
+            if is_reg {
+                self.register(ln, ops[i].0, values[i], diags);
+            }
+        }
+        if diags.len() > n_errors {
+            return None;
+        }
+        Some(Assembled { ins: Instruction::new(opc, values[0], values[1], values[2]), line: ln, out_column: ops[2].0 })
+    }
+
+    fn parse(&self, source: &str) -> (Program, Vec<Assembled>, Vec<Diagnostic>) {
+        let mut ip_reg = None;
+        let mut assembled = vec![];
+        let mut diags = vec![];
+
+        for (ln, l) in source.lines().enumerate().map(|(ln, l)| (ln + 1, l)) {
+            let toks = tokens(l);
+            let (col, first) = match toks.first() {
+                Some(&t) => t,
+                None => continue
+            };
+
+            if first.starts_with('#') {
+                match (first, toks.get(1)) {
+                    ("#ip", _) if ip_reg.is_some() => diags.push(Diagnostic::error(ln, col, "Duplicate #ip directive")),
+                    ("#ip", Some(&(rc, reg))) if toks.len() == 2 => match reg.parse::<u32>() {
+                        Ok(r) => {
+                            self.register(ln, rc, r, &mut diags);
+                            ip_reg = Some(r as usize);
+                        },
+                        Err(_) => diags.push(Diagnostic::error(ln, rc, format!("Expected a register number, found '{}'", reg)))
+                    },
+                    ("#ip", _) => diags.push(Diagnostic::error(ln, col, "#ip expects a single register")),
+                    _ => diags.push(Diagnostic::error(ln, col, format!("Unknown directive '{}'", first)))
+                }
+                continue;
+            }
+
+            match first.parse::<OpCode>() {
+                Ok(opc) => assembled.extend(self.operands(opc, ln, col, &toks[1..], &mut diags)),
+                Err(_) => diags.push(Diagnostic::error(ln, col, format!("Unknown opcode '{}'", first)))
+            }
+        }
+
+        let program = Program::new(ip_reg, assembled.iter().map(|a| a.ins).collect());
+        (program, assembled, diags)
+    }
+
+    // Jumps through the ip register past the end of the program, which halt it
+    fn lint_jumps(program: &Program, assembled: &[Assembled]) -> Vec<Diagnostic> {
+        let len = program.len();
+        let mut diags = vec![];
+        for (addr, a) in assembled.iter().enumerate() {
+            let out_of_bounds = match flow(program, addr) {
+                Flow::Jump(t) if t > len => Some(t.to_string()),
+                Flow::Skip { .. } if addr + 2 > len => Some((addr + 2).to_string()),
+                _ => None
+            };
+            if let Some(t) = out_of_bounds {
+                diags.push(Diagnostic::warning(a.line, a.out_column,
+                    format!("Write to the ip makes the next address {}, past the end of the program at {}", t, len)));
+            }
+        }
+        diags
+    }
+
+    // Assembles the program, failing with the errors found
+    pub fn assemble(&self, source: &str) -> Result<Program, Vec<Diagnostic>> {
+        let (program, _, diags) = self.parse(source);
+        if !diags.is_empty() {
+            return Err(diags);
+        }
+        Ok(program)
+    }
+
+    // Errors and warnings for the program, in source order
+    pub fn lint(&self, source: &str) -> Vec<Diagnostic> {
+        let (program, assembled, mut diags) = self.parse(source);
+        if diags.is_empty() {
+            diags = Assembler::lint_jumps(&program, &assembled);
+        }
+        diags.sort_by_key(|d| (d.line, d.column));
+        diags
+    }
+
+    // Translates the numeric form of day 16, `<opcode number> <a> <b> <c>` per line, given the
+    // opcode number to opcode mapping
+    pub fn disassemble(&self, input: &str, mapping: &dyn Fn(u32) -> Option<OpCode>) -> Result<Program, Vec<Diagnostic>> {
+        let mut instructions = vec![];
+        let mut diags = vec![];
+        for (ln, l) in input.lines().enumerate().map(|(ln, l)| (ln + 1, l)) {
+            let toks = tokens(l);
+            let (col, first) = match toks.first() {
+                Some(&t) => t,
+                None => continue
+            };
+            let opc = match first.parse::<u32>() {
+                Ok(n) => match mapping(n) {
+                    Some(opc) => opc,
+                    None => {
+                        diags.push(Diagnostic::error(ln, col, format!("No opcode known for number {}", n)));
+                        continue;
+                    }
+                },
+                Err(_) => {
+                    diags.push(Diagnostic::error(ln, col, format!("Expected an opcode number, found '{}'", first)));
+                    continue;
+                }
+            };
+            instructions.extend(self.operands(opc, ln, col, &toks[1..], &mut diags).map(|a| a.ins));
+        }
+
+        if !diags.is_empty() {
+            return Err(diags);
+        }
+        Ok(Program::new(None, instructions))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(source: &str) -> Vec<String> {
+        Assembler::default().lint(source).iter().map(|d| d.to_string()).collect()
+    }
+
+    #[test]
+    fn test_tokens() {
+        assert_eq!(tokens("  addi 4\t16  4 "), vec![(3, "addi"), (8, "4"), (10, "16"), (14, "4")]);
+        assert_eq!(tokens(""), vec![]);
+    }
+
+    #[test]
+    fn test_assemble() {
+        let program = Assembler::default().assemble("#ip 4\naddi 4 16 4\n\nseti 1 5 1\n").unwrap();
+        assert_eq!(program.ip_reg, Some(4));
+        assert_eq!(program.instructions, vec![Instruction::new(OpCode::Addi, 4, 16, 4), Instruction::new(OpCode::Seti, 1, 5, 1)]);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(errors("seti 1 2 3\n  sett 1 2 3"), vec!["2:3: error: Unknown opcode 'sett'"]);
+        assert_eq!(errors("addr 1 2"), vec!["1:1: error: addr expects 3 operands, found 2"]);
+        assert_eq!(errors("addr 1 2 3 4"), vec!["1:12: error: addr expects 3 operands, found 4"]);
+        assert_eq!(errors("addi 1 x 3"), vec!["1:8: error: Expected a number, found 'x'"]);
+        // B of addi is an immediate, A and C are registers
+        assert_eq!(errors("addi 1 99 3\naddi 6 1 7"),
+                   vec!["2:6: error: Register 6 out of range, the device has 6 registers",
+                        "2:10: error: Register 7 out of range, the device has 6 registers"]);
+        assert_eq!(errors("#ip 6\n#ip 1\n#foo"),
+                   vec!["1:5: error: Register 6 out of range, the device has 6 registers",
+                        "2:1: error: Duplicate #ip directive",
+                        "3:1: error: Unknown directive '#foo'"]);
+        assert!(Assembler::default().assemble("sett 1 2 3").is_err());
+    }
+
+    #[test]
+    fn test_lint_jumps() {
+        // Day 19 halts by squaring the ip, an unconditional skip at the end leaves the program too
+        assert_eq!(errors("#ip 4\nseti 0 0 0\nseti 1 0 0\nmulr 4 4 4\naddr 4 0 4"),
+                   vec!["4:10: warning: Write to the ip makes the next address 5, past the end of the program at 4",
+                        "5:10: warning: Write to the ip makes the next address 5, past the end of the program at 4"]);
+        assert_eq!(errors("#ip 4\nseti 0 0 0\nseti 1 0 4\nseti 1 0 0"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let mapping = |n| match n {
+            0 => Some(OpCode::Seti),
+            1 => Some(OpCode::Addr),
+            _ => None
+        };
+        let asm = Assembler::new(4);
+        let program = asm.disassemble("0 3 0 0\n1 0 0 1\n", &mapping).unwrap();
+        assert_eq!(program.to_string(), "seti 3 0 0\naddr 0 0 1\n");
+
+        let errs: Vec<String> = asm.disassemble("2 3 0 0\n1 0 0 4\n1 0", &mapping).unwrap_err()
+            .iter().map(|d| d.to_string()).collect();
+        assert_eq!(errs, vec!["1:1: error: No opcode known for number 2",
+                              "2:7: error: Register 4 out of range, the device has 4 registers",
+                              "3:1: error: addr expects 3 operands, found 1"]);
+    }
+}
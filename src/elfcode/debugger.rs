@@ -0,0 +1,570 @@
+// Interactive step debugger for ElfCode programs
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+use elfcode::{parse_register, register_name, Device, Fault, Program, Word};
+use ParseError;
+
+pub const HELP: &str = "Commands:
+  b, break <addr> [if <reg> <op> <value>]  Stop before executing the instruction at addr
+  b, break if <reg> <op> <value>           Stop when the condition becomes true, op is one of
+                                           ==, !=, <, <=, >, >=
+  w, watch <reg>                           Stop when the register changes
+  d, delete [<id>]                         Delete a breakpoint or watchpoint, all when no id given
+  s, step [<n>]                            Execute one or n instructions
+  c, continue                              Run until a breakpoint, watchpoint or the program halts
+  r, regs                                  Dump the registers
+     set <reg> <value>                     Set a register
+  l, list                                  List the program, => marks the ip and * breakpoints
+  p, profile                               Execution count of every instruction
+  i, info                                  List breakpoints and watchpoints
+     reset                                 Restore the initial registers and clear the profile
+  h, help                                  Show this help
+  q, quit                                  Exit the debugger
+An empty line repeats the last command, registers are given as a, b, c... or by number";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Cmp {
+    Eq, Ne, Lt, Le, Gt, Ge
+}
+
+impl Cmp {
+    pub fn holds(self, l: Word, r: Word) -> bool {
+        match self {
+            Cmp::Eq => l == r,
+            Cmp::Ne => l != r,
+            Cmp::Lt => l < r,
+            Cmp::Le => l <= r,
+            Cmp::Gt => l > r,
+            Cmp::Ge => l >= r
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            Cmp::Eq => "==",
+            Cmp::Ne => "!=",
+            Cmp::Lt => "<",
+            Cmp::Le => "<=",
+            Cmp::Gt => ">",
+            Cmp::Ge => ">="
+        }
+    }
+}
+
+impl FromStr for Cmp {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [Cmp::Eq, Cmp::Ne, Cmp::Lt, Cmp::Le, Cmp::Gt, Cmp::Ge].iter()
+            .find(|c| c.symbol() == s)
+            .cloned()
+            .ok_or_else(|| ParseError::new(format!("Unknown comparison: {}", s)))
+    }
+}
+
+// Comparison of a register with a value, e.g. `c > 10`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Condition {
+    pub register: usize,
+    pub cmp: Cmp,
+    pub value: Word
+}
+
+impl Condition {
+    pub fn holds(&self, registers: &[Word]) -> bool {
+        registers.get(self.register).is_some_and(|&r| self.cmp.holds(r, self.value))
+    }
+}
+
+impl FromStr for Condition {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split_whitespace().collect();
+        if parts.len() != 3 {
+            return Err(ParseError::new(format!("Expected a condition like 'a == 5': {}", s)));
+        }
+        Ok(Condition { register: parse_register(parts[0])?, cmp: parts[1].parse()?, value: parts[2].parse()? })
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} {}", register_name(self.register, None), self.cmp.symbol(), self.value)
+    }
+}
+
+// Breakpoint on an address, a condition or both. A breakpoint with only a condition triggers
+// when the condition becomes true, not on every instruction while it holds
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Breakpoint {
+    pub ip: Option<usize>,
+    pub cond: Option<Condition>
+}
+
+impl Breakpoint {
+    pub fn at(ip: usize) -> Breakpoint {
+        Breakpoint { ip: Some(ip), cond: None }
+    }
+
+    pub fn when(cond: Condition) -> Breakpoint {
+        Breakpoint { ip: None, cond: Some(cond) }
+    }
+
+    fn triggers(&self, before: &[Word], device: &Device) -> bool {
+        match (self.ip, self.cond) {
+            (Some(ip), cond) => device.ip == ip && cond.is_none_or(|c| c.holds(&device.registers)),
+            (None, Some(c)) => !c.holds(before) && c.holds(&device.registers),
+            (None, None) => false
+        }
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.ip, self.cond) {
+            (Some(ip), Some(c)) => write!(f, "at {} if {}", ip, c),
+            (Some(ip), None) => write!(f, "at {}", ip),
+            (None, Some(c)) => write!(f, "when {}", c),
+            (None, None) => write!(f, "never")
+        }
+    }
+}
+
+// Why execution stopped
+#[derive(PartialEq, Debug)]
+pub enum Stop {
+    Stepped,
+    Breakpoint(usize),
+    Watch { id: usize, register: usize, old: Word, new: Word },
+    Halted,
+    Fault(Fault)
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Command {
+    Break(Breakpoint),
+    Watch(usize),
+    Delete(Option<usize>),
+    Step(u64),
+    Continue,
+    Registers,
+    Set(usize, Word),
+    List,
+    Profile,
+    Info,
+    Reset,
+    Help,
+    Quit
+}
+
+impl FromStr for Command {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (cmd, args) = match s.find(char::is_whitespace) {
+            Some(i) => (&s[..i], s[i..].trim()),
+            None => (s, "")
+        };
+        let no_args = |c: Command| if args.is_empty() {
+            Ok(c)
+        } else {
+            Err(ParseError::new(format!("Unexpected arguments for {}: {}", cmd, args)))
+        };
+
+        match cmd {
+            "b" | "break" => {
+                if let Some(cond) = args.strip_prefix("if ") {
+                    return Ok(Command::Break(Breakpoint::when(cond.parse()?)));
+                }
+                let mut parts = args.splitn(2, " if ");
+                let ip = parts.next().unwrap().trim().parse::<usize>()
+                    .map_err(|_| ParseError::new(format!("Expected an address or 'if <condition>': {}", args)))?;
+                let cond = match parts.next() {
+                    Some(c) => Some(c.parse()?),
+                    None => None
+                };
+                Ok(Command::Break(Breakpoint { ip: Some(ip), cond }))
+            },
+            "w" | "watch" => Ok(Command::Watch(parse_register(args)?)),
+            "d" | "delete" if args.is_empty() => Ok(Command::Delete(None)),
+            "d" | "delete" => Ok(Command::Delete(Some(args.parse()?))),
+            "s" | "step" if args.is_empty() => Ok(Command::Step(1)),
+            "s" | "step" => Ok(Command::Step(args.parse()?)),
+            "set" => {
+                let parts: Vec<&str> = args.split_whitespace().collect();
+                if parts.len() != 2 {
+                    return Err(ParseError::new(format!("Expected a register and a value: {}", args)));
+                }
+                Ok(Command::Set(parse_register(parts[0])?, parts[1].parse()?))
+            },
+            "c" | "continue" => no_args(Command::Continue),
+            "r" | "regs" => no_args(Command::Registers),
+            "l" | "list" => no_args(Command::List),
+            "p" | "profile" => no_args(Command::Profile),
+            "i" | "info" => no_args(Command::Info),
+            "reset" => no_args(Command::Reset),
+            "h" | "help" => no_args(Command::Help),
+            "q" | "quit" => no_args(Command::Quit),
+            _ => Err(ParseError::new(format!("Unknown command: {}, try help", cmd)))
+        }
+    }
+}
+
+pub struct Debugger {
+    pub program: Program,
+    pub device: Device,
+    initial: Device,
+    breakpoints: Vec<(usize, Breakpoint)>,
+    watches: Vec<(usize, usize)>,
+    next_id: usize,
+    // Execution count per instruction address
+    profile: Vec<u64>,
+    pub executed: u64
+}
+
+impl Debugger {
+    pub fn new(program: Program, device: Device) -> Debugger {
+        let profile = vec![0; program.len()];
+        Debugger {
+            program,
+            initial: device.clone(),
+            device,
+            breakpoints: vec![],
+            watches: vec![],
+            next_id: 1,
+            profile,
+            executed: 0
+        }
+    }
+
+    fn check_register(&self, register: usize) -> Result<(), String> {
+        if register < self.device.registers.len() {
+            Ok(())
+        } else {
+            Err(format!("No register {}, the device has {}", register, self.device.registers.len()))
+        }
+    }
+
+    // Adds the breakpoint, returning its id
+    pub fn add_breakpoint(&mut self, bp: Breakpoint) -> Result<usize, String> {
+        if let Some(ip) = bp.ip.filter(|&ip| ip >= self.program.len()) {
+            return Err(format!("No instruction at {}, the program has {}", ip, self.program.len()));
+        }
+        if let Some(c) = bp.cond {
+            self.check_register(c.register)?;
+        }
+        self.next_id += 1;
+        self.breakpoints.push((self.next_id - 1, bp));
+        Ok(self.next_id - 1)
+    }
+
+    // Adds a watchpoint on the register, returning its id
+    pub fn add_watch(&mut self, register: usize) -> Result<usize, String> {
+        self.check_register(register)?;
+        self.next_id += 1;
+        self.watches.push((self.next_id - 1, register));
+        Ok(self.next_id - 1)
+    }
+
+    // Deletes the breakpoint or watchpoint, returns false if there was none with the id
+    pub fn delete(&mut self, id: usize) -> bool {
+        let n = self.breakpoints.len() + self.watches.len();
+        self.breakpoints.retain(|&(i, _)| i != id);
+        self.watches.retain(|&(i, _)| i != id);
+        n != self.breakpoints.len() + self.watches.len()
+    }
+
+    pub fn profile(&self) -> &[u64] {
+        &self.profile
+    }
+
+    pub fn reset(&mut self) {
+        self.device = self.initial.clone();
+        self.profile = vec![0; self.program.len()];
+        self.executed = 0;
+    }
+
+    // Executes instructions until a breakpoint or watchpoint triggers, the program halts or
+    // limit instructions have been executed
+    pub fn run(&mut self, limit: Option<u64>) -> Stop {
+        let mut n = 0;
+        loop {
+            if self.device.halted(&self.program) {
+                return Stop::Halted;
+            }
+            if limit.is_some_and(|l| n >= l) {
+                return Stop::Stepped;
+            }
+
+            let ip = self.device.ip;
+            let before = self.device.registers.clone();
+            if let Err(f) = self.device.step(&self.program) {
+                return Stop::Fault(f);
+            }
+            self.profile[ip] += 1;
+            self.executed += 1;
+            n += 1;
+
+            for &(id, r) in self.watches.iter() {
+                if before[r] != self.device.registers[r] {
+                    return Stop::Watch { id, register: r, old: before[r], new: self.device.registers[r] };
+                }
+            }
+            if let Some(&(id, _)) = self.breakpoints.iter().find(|(_, bp)| bp.triggers(&before, &self.device)) {
+                return Stop::Breakpoint(id);
+            }
+        }
+    }
+
+    pub fn registers(&self) -> String {
+        let regs: Vec<String> = self.device.registers.iter()
+            .enumerate()
+            .map(|(i, r)| format!("{}={}", register_name(i, self.device.ip_reg), r))
+            .collect();
+        format!("ip={} {}", self.device.ip, regs.join(" "))
+    }
+
+    fn line(&self, ip: usize) -> String {
+        let ins = &self.program.instructions[ip];
+        format!("{:3}  {:<20}  {}", ip, ins.to_string(), ins.explain(self.program.ip_reg))
+    }
+
+    // The instruction about to be executed
+    pub fn location(&self) -> String {
+        if self.device.halted(&self.program) {
+            format!("ip={} outside of the program", self.device.ip)
+        } else {
+            format!("=> {}", self.line(self.device.ip))
+        }
+    }
+
+    pub fn listing(&self) -> String {
+        (0..self.program.len())
+            .map(|ip| {
+                let current = if ip == self.device.ip { "=>" } else { "  " };
+                let bp = if self.breakpoints.iter().any(|(_, bp)| bp.ip == Some(ip)) { "*" } else { " " };
+                format!("{}{}{}\n", current, bp, self.line(ip))
+            })
+            .collect()
+    }
+
+    // Execution count and share of every instruction
+    pub fn profile_report(&self) -> String {
+        let total = self.executed.max(1) as f64;
+        let mut out = format!("Executed {} instructions\n", self.executed);
+        for (ip, &count) in self.profile.iter().enumerate() {
+            out.push_str(&format!("{:12} {:5.1}%  {}\n", count, count as f64 * 100.0 / total, self.line(ip)));
+        }
+        out
+    }
+
+    fn info(&self) -> String {
+        let mut out = String::new();
+        for (id, bp) in self.breakpoints.iter() {
+            out.push_str(&format!("{:3}  breakpoint {}\n", id, bp));
+        }
+        for (id, r) in self.watches.iter() {
+            out.push_str(&format!("{:3}  watchpoint on {}\n", id, register_name(*r, self.device.ip_reg)));
+        }
+        if out.is_empty() {
+            out.push_str("No breakpoints or watchpoints\n");
+        }
+        out
+    }
+
+    fn describe(&self, stop: &Stop) -> String {
+        let stopped = match stop {
+            Stop::Stepped => String::new(),
+            Stop::Breakpoint(id) => format!("Breakpoint {} hit\n", id),
+            Stop::Watch { id, register, old, new } =>
+                format!("Watchpoint {}: {} changed {} -> {}\n", id, register_name(*register, self.device.ip_reg), old, new),
+            Stop::Halted => return format!("Program halted after {} instructions\n{}\n", self.executed, self.registers()),
+            Stop::Fault(f) => format!("Fault: {}\n", f)
+        };
+        format!("{}{}\n", stopped, self.location())
+    }
+
+    // Executes the command, returning the output to show
+    pub fn execute(&mut self, cmd: &Command) -> String {
+        let added = |res: Result<usize, String>, what: &str| match res {
+            Ok(id) => format!("{} {} added\n", what, id),
+            Err(e) => format!("Error: {}\n", e)
+        };
+        match cmd {
+            Command::Break(bp) => {
+                let res = self.add_breakpoint(*bp);
+                added(res, "Breakpoint")
+            },
+            Command::Watch(r) => {
+                let res = self.add_watch(*r);
+                added(res, "Watchpoint")
+            },
+            Command::Delete(Some(id)) if self.delete(*id) => format!("Deleted {}\n", id),
+            Command::Delete(Some(id)) => format!("Error: No breakpoint or watchpoint {}\n", id),
+            Command::Delete(None) => {
+                self.breakpoints.clear();
+                self.watches.clear();
+                "Deleted all breakpoints and watchpoints\n".to_string()
+            },
+            Command::Step(n) => {
+                let stop = self.run(Some(*n));
+                self.describe(&stop)
+            },
+            Command::Continue => {
+                let stop = self.run(None);
+                self.describe(&stop)
+            },
+            Command::Registers => format!("{}\n", self.registers()),
+            Command::Set(r, v) => match self.check_register(*r) {
+                Ok(()) => {
+                    self.device.registers[*r] = *v;
+                    format!("{}\n", self.registers())
+                },
+                Err(e) => format!("Error: {}\n", e)
+            },
+            Command::List => self.listing(),
+            Command::Profile => self.profile_report(),
+            Command::Info => self.info(),
+            Command::Reset => {
+                self.reset();
+                format!("{}\n", self.location())
+            },
+            Command::Help => format!("{}\n", HELP),
+            Command::Quit => String::new()
+        }
+    }
+
+    // Reads commands line by line until quit or the end of the input
+    pub fn repl<R: BufRead, W: Write>(&mut self, input: R, output: &mut W, prompt: bool) -> io::Result<()> {
+        let mut last = None;
+        writeln!(output, "{}", self.location())?;
+        let mut lines = input.lines();
+        loop {
+            if prompt {
+                write!(output, "(edb) ")?;
+                output.flush()?;
+            }
+            let line = match lines.next() {
+                Some(l) => l?,
+                None => return Ok(())
+            };
+
+            let cmd = if line.trim().is_empty() {
+                match last.clone() {
+                    Some(c) => c,
+                    None => continue
+                }
+            } else {
+                match line.parse::<Command>() {
+                    Ok(c) => c,
+                    Err(e) => {
+                        writeln!(output, "Error: {}", e.message())?;
+                        continue;
+                    }
+                }
+            };
+            if cmd == Command::Quit {
+                return Ok(());
+            }
+            write!(output, "{}", self.execute(&cmd))?;
+            last = Some(cmd);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Counts a up to 5 and then sets c to 9
+    const PROGRAM: &str =
+       "#ip 5
+        seti 0 0 0
+        addi 0 1 0
+        gtri 0 4 1
+        addr 5 1 5
+        seti 0 0 5
+        seti 9 0 2";
+
+    fn debugger() -> Debugger {
+        let program = Program::parse(PROGRAM).unwrap();
+        let device = Device::for_program(&program);
+        Debugger::new(program, device)
+    }
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!("break 4".parse(), Ok(Command::Break(Breakpoint::at(4))));
+        let cond = Condition { register: 2, cmp: Cmp::Ge, value: 10 };
+        assert_eq!("b 4 if c >= 10".parse(), Ok(Command::Break(Breakpoint { ip: Some(4), cond: Some(cond) })));
+        assert_eq!("b if c >= 10".parse(), Ok(Command::Break(Breakpoint::when(cond))));
+        assert_eq!("watch e(ip)".parse(), Ok(Command::Watch(4)));
+        assert_eq!("s".parse(), Ok(Command::Step(1)));
+        assert_eq!("step 20".parse(), Ok(Command::Step(20)));
+        assert_eq!("set a 1".parse(), Ok(Command::Set(0, 1)));
+        assert_eq!("d".parse(), Ok(Command::Delete(None)));
+        assert_eq!("foo".parse::<Command>(), Err(ParseError::new("Unknown command: foo, try help")));
+        assert!("b 4 if c =< 10".parse::<Command>().is_err());
+        assert!("continue 3".parse::<Command>().is_err());
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let mut dbg = debugger();
+        let id = dbg.add_breakpoint(Breakpoint::at(4)).unwrap();
+        assert_eq!(dbg.run(None), Stop::Breakpoint(id));
+        assert_eq!((dbg.device.ip, dbg.device.registers[0]), (4, 1));
+        assert_eq!(dbg.run(None), Stop::Breakpoint(id));
+        assert_eq!(dbg.device.registers[0], 2);
+
+        assert!(dbg.delete(id));
+        let cond = Condition { register: 0, cmp: Cmp::Eq, value: 4 };
+        let id = dbg.add_breakpoint(Breakpoint::when(cond)).unwrap();
+        assert_eq!(dbg.run(None), Stop::Breakpoint(id));
+        assert_eq!((dbg.device.ip, dbg.device.registers[0]), (2, 4));
+        assert_eq!(dbg.run(None), Stop::Halted);
+
+        assert!(dbg.add_breakpoint(Breakpoint::at(6)).is_err());
+    }
+
+    #[test]
+    fn test_watch_and_step() {
+        let mut dbg = debugger();
+        assert_eq!(dbg.run(Some(3)), Stop::Stepped);
+        assert_eq!((dbg.device.ip, dbg.executed), (3, 3));
+
+        let id = dbg.add_watch(2).unwrap();
+        assert_eq!(dbg.run(None), Stop::Watch { id, register: 2, old: 0, new: 9 });
+        assert_eq!(dbg.registers(), "ip=6 a=5 b=1 c=9 d=0 e=0 f(ip)=5");
+        assert_eq!(dbg.run(Some(1)), Stop::Halted);
+    }
+
+    #[test]
+    fn test_profile() {
+        let mut dbg = debugger();
+        assert_eq!(dbg.run(None), Stop::Halted);
+        assert_eq!(dbg.profile(), &[1, 5, 5, 5, 4, 1]);
+        assert_eq!(dbg.executed, 21);
+        dbg.reset();
+        assert_eq!(dbg.profile(), &[0; 6]);
+        assert_eq!(dbg.device.ip, 0);
+    }
+
+    #[test]
+    fn test_repl() {
+        let mut dbg = debugger();
+        let mut out = vec![];
+        dbg.repl("b 3 if a == 2\nc\n\nset z 1\nquit\nc\n".as_bytes(), &mut out, false).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "=>   0  seti 0 0 0            a = 0\n\
+                    Breakpoint 1 added\n\
+                    Breakpoint 1 hit\n\
+                    =>   3  addr 5 1 5            f(ip) = f(ip) + b\n\
+                    Program halted after 21 instructions\n\
+                    ip=6 a=5 b=1 c=9 d=0 e=0 f(ip)=5\n\
+                    Error: No register 25, the device has 6\n");
+    }
+}
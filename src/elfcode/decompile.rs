@@ -0,0 +1,568 @@
+// Decompiler recovering loops and conditionals from the ip writes of ElfCode programs
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use elfcode::{register_name, Device, Instruction, OpCode, Program};
+
+// Effect of an instruction on the control flow
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Flow {
+    // Doesn't write the ip register, continues with the next instruction
+    Next,
+    // Jumps to a constant address, at or beyond the end of the program it halts
+    Jump(usize),
+    // Adds the flag register to the ip, skipping the next instruction if the flag is 1
+    Skip { flag: usize },
+    // Jumps to an address that depends on registers other than the ip
+    Computed
+}
+
+fn reads(ins: &Instruction) -> Vec<u32> {
+    let (ra, rb) = ins.opc.reg_operands();
+    let mut regs = vec![];
+    if ra {
+        regs.push(ins.inp_a);
+    }
+    if rb {
+        regs.push(ins.inp_b);
+    }
+    regs
+}
+
+pub fn flow(program: &Program, addr: usize) -> Flow {
+    let ins = &program.instructions[addr];
+    let ipr = match program.ip_reg {
+        Some(ipr) if ins.out_c as usize == ipr => ipr,
+        _ => return Flow::Next
+    };
+
+    let regs = reads(ins);
+    if regs.iter().all(|&r| r as usize == ipr) {
+        // Only depends on the ip, which is known, so just execute it
+        let mut d: Device = Device::new((ipr + 1).max(6), Some(ipr));
+        d.ip = addr;
+        return match d.exec(ins) {
+            Ok(()) => Flow::Jump(d.ip),
+            Err(_) => Flow::Computed
+        };
+    }
+    match (ins.opc, regs.iter().find(|&&r| r as usize != ipr)) {
+        (OpCode::Addr, Some(&flag)) if regs.len() == 2 && regs.contains(&(ipr as u32)) =>
+            Flow::Skip { flag: flag as usize },
+        _ => Flow::Computed
+    }
+}
+
+// Maximal sequence of instructions only entered at the start and only left at the end
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Block {
+    pub start: usize,
+    // Exclusive
+    pub end: usize,
+    // Start addresses of the blocks control may continue with
+    pub successors: Vec<usize>,
+    // Whether control may leave the program, halting it
+    pub halts: bool
+}
+
+#[derive(Debug)]
+pub struct Cfg {
+    pub blocks: Vec<Block>
+}
+
+// Possible addresses after the instruction, None when unknown
+fn next_addrs(flow: Flow, addr: usize) -> Option<Vec<usize>> {
+    match flow {
+        Flow::Next => Some(vec![addr + 1]),
+        Flow::Jump(t) => Some(vec![t]),
+        Flow::Skip { .. } => Some(vec![addr + 1, addr + 2]),
+        Flow::Computed => None
+    }
+}
+
+impl Cfg {
+    pub fn build(program: &Program) -> Cfg {
+        let len = program.len();
+        let flows: Vec<Flow> = (0..len).map(|i| flow(program, i)).collect();
+
+        let mut leaders = BTreeSet::new();
+        leaders.insert(0);
+        for (i, &f) in flows.iter().enumerate() {
+            if f != Flow::Next {
+                leaders.insert(i + 1);
+                leaders.extend(next_addrs(f, i).unwrap_or_default());
+            }
+        }
+        let leaders: Vec<usize> = leaders.into_iter().filter(|&l| l < len).collect();
+
+        let blocks = leaders.iter()
+            .enumerate()
+            .map(|(bi, &start)| {
+                let end = leaders.get(bi + 1).cloned().unwrap_or(len);
+                let (successors, halts) = match next_addrs(flows[end - 1], end - 1) {
+                    Some(addrs) => (addrs.iter().filter(|&&a| a < len).cloned().collect(), addrs.iter().any(|&a| a >= len)),
+                    None => (vec![], true)
+                };
+                Block { start, end, successors, halts }
+            })
+            .collect();
+        Cfg { blocks }
+    }
+
+    pub fn block_at(&self, addr: usize) -> Option<&Block> {
+        self.blocks.iter().find(|b| b.start <= addr && addr < b.end)
+    }
+
+    // Edges (from block start, to block start) closing a loop, found by a depth first search from
+    // the entry, the target of a back edge is the head of a loop
+    pub fn back_edges(&self) -> Vec<(usize, usize)> {
+        fn visit(cfg: &Cfg, start: usize, on_stack: &mut Vec<usize>, seen: &mut BTreeSet<usize>, edges: &mut Vec<(usize, usize)>) {
+            seen.insert(start);
+            on_stack.push(start);
+            for &s in cfg.block_at(start).unwrap().successors.iter() {
+                if on_stack.contains(&s) {
+                    edges.push((start, s));
+                } else if !seen.contains(&s) {
+                    visit(cfg, s, on_stack, seen, edges);
+                }
+            }
+            on_stack.pop();
+        }
+
+        let mut edges = vec![];
+        if !self.blocks.is_empty() {
+            visit(self, 0, &mut vec![], &mut BTreeSet::new(), &mut edges);
+        }
+        edges.sort();
+        edges
+    }
+}
+
+// Condition of a branch, e.g. `d > c`
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Cond {
+    pub lhs: String,
+    pub op: &'static str,
+    pub rhs: String
+}
+
+impl Cond {
+    pub fn negate(&self) -> Cond {
+        let op = match self.op {
+            "==" => "!=",
+            "!=" => "==",
+            ">" => "<=",
+            "<=" => ">",
+            "<" => ">=",
+            _ => "<"
+        };
+        Cond { lhs: self.lhs.clone(), op, rhs: self.rhs.clone() }
+    }
+}
+
+impl fmt::Display for Cond {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} {}", self.lhs, self.op, self.rhs)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Stmt {
+    Assign(String),
+    Goto(usize),
+    CondGoto(Cond, usize),
+    ComputedGoto(String)
+}
+
+impl Stmt {
+    fn target(&self) -> Option<usize> {
+        match self {
+            Stmt::Goto(t) | Stmt::CondGoto(_, t) => Some(*t),
+            _ => None
+        }
+    }
+}
+
+struct Decompiler<'a> {
+    program: &'a Program,
+    // Statement at every address, None where an instruction was merged into its predecessor
+    stmts: Vec<Option<Stmt>>,
+    // Address of the jumping instruction and the loop head of every back edge
+    back_edges: BTreeSet<(usize, usize)>,
+    // Targets of gotos which could not be turned into structured control flow
+    labels: BTreeSet<usize>
+}
+
+// Statement with its address, or a nested block like `loop { ... }`
+enum Node {
+    Line(usize, String),
+    Nested(usize, String, Vec<Node>, Vec<Node>)
+}
+
+fn is_jump(nodes: &[Node]) -> bool {
+    match nodes {
+        [Node::Line(_, s)] => s == "break" || s == "continue" || s == "halt" || s.starts_with("goto "),
+        _ => false
+    }
+}
+
+impl<'a> Decompiler<'a> {
+    // Registers by name, with reads of the ip register replaced by the address
+    fn operand(&self, addr: usize, r: u32) -> String {
+        if self.program.ip_reg == Some(r as usize) {
+            addr.to_string()
+        } else {
+            register_name(r as usize, None)
+        }
+    }
+
+    fn comparison(&self, addr: usize) -> Option<Cond> {
+        use elfcode::OpCode::*;
+        let ins = &self.program.instructions[addr];
+        let (reg_a, reg_b) = ins.opc.reg_operands();
+        let op = match ins.opc {
+            Gtir | Gtri | Gtrr => ">",
+            Eqir | Eqri | Eqrr => "==",
+            _ => return None
+        };
+        let operand = |is_reg, v| if is_reg { self.operand(addr, v) } else { v.to_string() };
+        Some(Cond { lhs: operand(reg_a, ins.inp_a), op, rhs: operand(reg_b, ins.inp_b) })
+    }
+
+    fn new(program: &'a Program) -> Decompiler<'a> {
+        let len = program.len();
+        let flows: Vec<Flow> = (0..len).map(|i| flow(program, i)).collect();
+        let mut targeted: HashMap<usize, usize> = HashMap::new();
+        for (i, &f) in flows.iter().enumerate() {
+            if f != Flow::Next {
+                for t in next_addrs(f, i).unwrap_or_default() {
+                    *targeted.entry(t).or_insert(0) += 1;
+                }
+            }
+        }
+        let targeted = |addr: usize| targeted.get(&addr).cloned().unwrap_or(0);
+
+        let cfg = Cfg::build(program);
+        let back_edges = cfg.back_edges().into_iter()
+            .map(|(from, head)| (cfg.block_at(from).unwrap().end - 1, head))
+            .collect();
+
+        let mut dec = Decompiler { program, stmts: vec![], back_edges, labels: BTreeSet::new() };
+        for (i, flow) in flows.iter().enumerate() {
+            let ins = &program.instructions[i];
+            let stmt = match *flow {
+                Flow::Next => Stmt::Assign(ins.render(&|r| dec.operand(i, r))),
+                Flow::Jump(t) => Stmt::Goto(t),
+                Flow::Skip { flag } => Stmt::ComputedGoto(format!("goto {} + {}", i + 1, register_name(flag, None))),
+                Flow::Computed => {
+                    let expr = ins.render(&|r| dec.operand(i, r));
+                    Stmt::ComputedGoto(format!("goto ({}) + 1", expr.split(" = ").nth(1).unwrap_or("?")))
+                }
+            };
+            dec.stmts.push(Some(stmt));
+        }
+
+        // Fold a comparison into a flag followed by adding the flag to the ip into a conditional
+        // goto, and a conditional skip of a goto into a conditional goto with the negated condition
+        for (i, flow) in flows.iter().enumerate().skip(1) {
+            let flag = match *flow {
+                Flow::Skip { flag } => flag,
+                _ => continue
+            };
+            let cond = match dec.comparison(i - 1) {
+                Some(c) if program.instructions[i - 1].out_c as usize == flag && targeted(i) == 0 => c,
+                _ => continue
+            };
+            dec.stmts[i] = None;
+            dec.stmts[i - 1] = Some(match dec.stmts.get(i + 1) {
+                Some(&Some(Stmt::Goto(t))) if targeted(i + 1) == 1 => {
+                    dec.stmts[i + 1] = None;
+                    Stmt::CondGoto(cond.negate(), t)
+                },
+                _ => Stmt::CondGoto(cond, i + 2)
+            });
+        }
+        dec
+    }
+
+    fn last_stmt(&self, lo: usize, hi: usize) -> Option<usize> {
+        (lo..hi).rev().find(|&i| self.stmts[i].is_some())
+    }
+
+    // Whether the statement at addr closes the loop starting at head, the statement covers the
+    // instructions merged into it
+    fn is_back_edge(&self, addr: usize, head: usize) -> bool {
+        let end = (addr + 1..self.stmts.len()).find(|&i| self.stmts[i].is_some()).unwrap_or(self.stmts.len());
+        self.stmts[addr].as_ref().and_then(|s| s.target()) == Some(head)
+            && self.back_edges.range((addr, 0)..(end, 0)).any(|&(_, h)| h == head)
+    }
+
+    fn jump(&mut self, target: usize, lp: Option<(usize, usize)>) -> String {
+        match lp {
+            _ if target >= self.program.len() => "halt".to_string(),
+            Some((_, exit)) if target == exit => "break".to_string(),
+            Some((head, _)) if target == head => "continue".to_string(),
+            _ => {
+                self.labels.insert(target);
+                format!("goto {}", target)
+            }
+        }
+    }
+
+    // Structures the statements in [lo, hi), lp is the head and exit of the innermost loop
+    fn structure(&mut self, lo: usize, hi: usize, lp: Option<(usize, usize)>) -> Vec<Node> {
+        let mut nodes = vec![];
+        let mut i = lo;
+        while i < hi {
+            let stmt = match self.stmts[i].clone() {
+                Some(s) => s,
+                None => {
+                    i += 1;
+                    continue;
+                }
+            };
+
+            // A loop starts at the head of the last back edge in the range
+            let in_loop_head = lp.is_some_and(|(head, _)| head == i);
+            if !in_loop_head {
+                if let Some(end) = (i..hi).rev().find(|&j| self.is_back_edge(j, i)) {
+                    let body = self.structure(i, end + 1, Some((i, end + 1)));
+                    nodes.push(Node::Nested(i, "loop".to_string(), body, vec![]));
+                    i = end + 1;
+                    continue;
+                }
+            }
+
+            let is_last = self.last_stmt(i, hi) == Some(i);
+            match stmt {
+                Stmt::Assign(s) | Stmt::ComputedGoto(s) => nodes.push(Node::Line(i, s)),
+                // Jumping back to the head at the end of the loop is implicit
+                Stmt::Goto(t) if is_last && lp.is_some_and(|(head, _)| head == t) => (),
+                Stmt::CondGoto(c, t) if is_last && lp.is_some_and(|(head, _)| head == t) =>
+                    nodes.push(Node::Line(i, format!("if {} {{ break }}", c.negate()))),
+                Stmt::Goto(t) => {
+                    let s = self.jump(t, lp);
+                    nodes.push(Node::Line(i, s));
+                },
+                Stmt::CondGoto(c, t) if t > i + 1 && t <= hi && lp.is_none_or(|(_, exit)| exit != t) => {
+                    // Skipping forward over statements is an if, with an else when the skipped
+                    // statements end by jumping over the following ones
+                    let else_end = self.last_stmt(i + 1, t).and_then(|k| match self.stmts[k] {
+                        Some(Stmt::Goto(e)) if e > t && e <= hi && lp.is_none_or(|(_, exit)| exit != e) => Some((k, e)),
+                        _ => None
+                    });
+                    match else_end {
+                        Some((k, e)) => {
+                            let then = self.structure(i + 1, k, lp);
+                            let els = self.structure(t, e, lp);
+                            nodes.push(Node::Nested(i, format!("if {}", c.negate()), then, els));
+                            i = e;
+                        },
+                        None => {
+                            let then = self.structure(i + 1, t, lp);
+                            nodes.push(Node::Nested(i, format!("if {}", c.negate()), then, vec![]));
+                            i = t;
+                        }
+                    }
+                    continue;
+                },
+                Stmt::CondGoto(c, t) => {
+                    let s = self.jump(t, lp);
+                    nodes.push(Node::Line(i, format!("if {} {{ {} }}", c, s)));
+                }
+            }
+            i += 1;
+        }
+        nodes
+    }
+
+    fn write(&self, nodes: &[Node], depth: usize, out: &mut String) {
+        let indent = "    ".repeat(depth);
+        for node in nodes {
+            let addr = match node {
+                Node::Line(addr, _) | Node::Nested(addr, _, _, _) => *addr
+            };
+            if self.labels.contains(&addr) {
+                out.push_str(&format!("{}:\n", addr));
+            }
+            match node {
+                Node::Line(_, s) => out.push_str(&format!("{}{}\n", indent, s)),
+                // Keep conditional jumps on one line like the ones which weren't structured
+                Node::Nested(_, header, body, els) if is_jump(body) && els.is_empty() => {
+                    if let Node::Line(_, s) = &body[0] {
+                        out.push_str(&format!("{}{} {{ {} }}\n", indent, header, s));
+                    }
+                },
+                Node::Nested(_, header, body, els) => {
+                    out.push_str(&format!("{}{} {{\n", indent, header));
+                    self.write(body, depth + 1, out);
+                    if !els.is_empty() {
+                        out.push_str(&format!("{}}} else {{\n", indent));
+                        self.write(els, depth + 1, out);
+                    }
+                    out.push_str(&format!("{}}}\n", indent));
+                }
+            }
+        }
+    }
+}
+
+// Structured pseudo code for the program. Reads of the ip register are replaced by the address of
+// the instruction, gotos that don't form loops or conditionals are kept with numeric labels
+pub fn decompile(program: &Program) -> String {
+    let mut dec = Decompiler::new(program);
+    let nodes = dec.structure(0, program.len(), None);
+    let mut out = String::new();
+    dec.write(&nodes, 0, &mut out);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Counts a up to 5 and then sets c to 9
+    const COUNT: &str =
+       "#ip 5
+        seti 0 0 0
+        addi 0 1 0
+        gtri 0 4 1
+        addr 5 1 5
+        seti 0 0 5
+        seti 9 0 2";
+
+    // Program of day 19, summing the divisors of c
+    const DIVISORS: &str =
+       "#ip 4
+        addi 4 16 4
+        seti 1 5 1
+        seti 1 7 3
+        mulr 1 3 5
+        eqrr 5 2 5
+        addr 5 4 4
+        addi 4 1 4
+        addr 1 0 0
+        addi 3 1 3
+        gtrr 3 2 5
+        addr 4 5 4
+        seti 2 4 4
+        addi 1 1 1
+        gtrr 1 2 5
+        addr 5 4 4
+        seti 1 5 4
+        mulr 4 4 4
+        addi 2 2 2
+        mulr 2 2 2
+        mulr 4 2 2
+        muli 2 11 2
+        addi 5 2 5
+        mulr 5 4 5
+        addi 5 18 5
+        addr 2 5 2
+        addr 4 0 4
+        seti 0 6 4
+        setr 4 3 5
+        mulr 5 4 5
+        addr 4 5 5
+        mulr 4 5 5
+        muli 5 14 5
+        mulr 5 4 5
+        addr 2 5 2
+        seti 0 2 0
+        seti 0 6 4";
+
+    #[test]
+    fn test_flow() {
+        let program = Program::parse(DIVISORS).unwrap();
+        assert_eq!(flow(&program, 0), Flow::Jump(17));
+        assert_eq!(flow(&program, 1), Flow::Next);
+        assert_eq!(flow(&program, 5), Flow::Skip { flag: 5 });
+        assert_eq!(flow(&program, 11), Flow::Jump(3));
+        assert_eq!(flow(&program, 16), Flow::Jump(257));
+        assert_eq!(flow(&program, 25), Flow::Skip { flag: 0 });
+
+        let computed = Program::parse("#ip 1\nsetr 0 0 1").unwrap();
+        assert_eq!(flow(&computed, 0), Flow::Computed);
+    }
+
+    #[test]
+    fn test_cfg() {
+        let cfg = Cfg::build(&Program::parse(COUNT).unwrap());
+        assert_eq!(cfg.blocks, vec![
+            Block { start: 0, end: 1, successors: vec![1], halts: false },
+            Block { start: 1, end: 4, successors: vec![4, 5], halts: false },
+            Block { start: 4, end: 5, successors: vec![1], halts: false },
+            Block { start: 5, end: 6, successors: vec![], halts: true }
+        ]);
+        assert_eq!(cfg.back_edges(), vec![(4, 1)]);
+
+        let cfg = Cfg::build(&Program::parse(DIVISORS).unwrap());
+        assert_eq!(cfg.back_edges(), vec![(11, 3), (15, 2)]);
+        assert!(cfg.block_at(16).unwrap().halts);
+    }
+
+    #[test]
+    fn test_decompile_loop() {
+        assert_eq!(decompile(&Program::parse(COUNT).unwrap()),
+                   "a = 0\n\
+                    loop {\n    \
+                        a = a + 1\n    \
+                        if a > 4 { break }\n\
+                    }\n\
+                    c = 9\n");
+    }
+
+    #[test]
+    fn test_decompile_if_else() {
+        let program = Program::parse("#ip 4\ngtri 0 5 1\naddr 4 1 4\nseti 4 0 4\nseti 1 0 2\nseti 5 0 4\nseti 2 0 2\nseti 3 0 3").unwrap();
+        assert_eq!(decompile(&program),
+                   "if a > 5 {\n    \
+                        c = 1\n\
+                    } else {\n    \
+                        c = 2\n\
+                    }\n\
+                    d = 3\n");
+    }
+
+    #[test]
+    fn test_decompile_nested() {
+        assert_eq!(decompile(&Program::parse(DIVISORS).unwrap()), "\
+goto 17
+1:
+b = 1
+loop {
+    d = 1
+    loop {
+        f = b * d
+        if f == c {
+            a = b + a
+        }
+        d = d + 1
+        if d > c { break }
+    }
+    b = b + 1
+    if b > c { break }
+}
+halt
+17:
+c = c + 2
+c = c * c
+c = 19 * c
+c = c * 11
+f = f + 2
+f = f * 22
+f = f + 18
+c = c + f
+goto 26 + a
+goto 1
+f = 27
+f = f * 28
+f = 29 + f
+f = 30 * f
+f = f * 14
+f = f * 32
+c = c + f
+a = 0
+goto 1
+");
+    }
+}
@@ -0,0 +1,611 @@
+// ElfCode, the instruction set of the wrist device used in days 16, 19 and 21
+use std::error::Error;
+use std::fmt;
+use std::ops::{BitAnd, BitOr};
+use std::str::FromStr;
+use regex::Regex;
+use ParseError;
+
+pub mod asm;
+pub mod debugger;
+pub mod decompile;
+pub mod optimize;
+pub mod trace;
+
+// Register width of the devices used by the solutions and tools
+pub type Word = u64;
+
+// Integer type of the registers of a device
+pub trait Register: Copy + PartialEq + PartialOrd + BitAnd<Output = Self> + BitOr<Output = Self> + fmt::Debug + fmt::Display {
+    const NAME: &'static str;
+
+    fn from_u32(v: u32) -> Self;
+    // The value as an instruction pointer, None if negative or too large
+    fn to_ip(self) -> Option<usize>;
+    fn checked_add(self, other: Self) -> Option<Self>;
+    fn checked_mul(self, other: Self) -> Option<Self>;
+    fn wrapping_add(self, other: Self) -> Self;
+    fn wrapping_mul(self, other: Self) -> Self;
+    fn saturating_add(self, other: Self) -> Self;
+    fn saturating_mul(self, other: Self) -> Self;
+}
+
+macro_rules! impl_register {
+    ($t:ident) => {
+        impl Register for $t {
+            const NAME: &'static str = stringify!($t);
+
+            fn from_u32(v: u32) -> $t { v as $t }
+            fn to_ip(self) -> Option<usize> {
+                if self < 0 as $t || self as u64 > usize::MAX as u64 { None } else { Some(self as usize) }
+            }
+            fn checked_add(self, other: $t) -> Option<$t> { $t::checked_add(self, other) }
+            fn checked_mul(self, other: $t) -> Option<$t> { $t::checked_mul(self, other) }
+            fn wrapping_add(self, other: $t) -> $t { $t::wrapping_add(self, other) }
+            fn wrapping_mul(self, other: $t) -> $t { $t::wrapping_mul(self, other) }
+            fn saturating_add(self, other: $t) -> $t { $t::saturating_add(self, other) }
+            fn saturating_mul(self, other: $t) -> $t { $t::saturating_mul(self, other) }
+        }
+    }
+}
+
+impl_register!(u32);
+impl_register!(u64);
+impl_register!(i64);
+
+// What additions and multiplications do when the result doesn't fit a register
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Overflow {
+    Wrap, Saturate, Trap
+}
+
+impl Overflow {
+    fn add<W: Register>(self, a: W, b: W) -> Option<W> {
+        match self {
+            Overflow::Wrap => Some(a.wrapping_add(b)),
+            Overflow::Saturate => Some(a.saturating_add(b)),
+            Overflow::Trap => a.checked_add(b)
+        }
+    }
+
+    fn mul<W: Register>(self, a: W, b: W) -> Option<W> {
+        match self {
+            Overflow::Wrap => Some(a.wrapping_mul(b)),
+            Overflow::Saturate => Some(a.saturating_mul(b)),
+            Overflow::Trap => a.checked_mul(b)
+        }
+    }
+}
+
+impl FromStr for Overflow {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Overflow, ParseError> {
+        match s {
+            "wrap" => Ok(Overflow::Wrap),
+            "saturate" => Ok(Overflow::Saturate),
+            "trap" => Ok(Overflow::Trap),
+            _ => Err(ParseError::new(format!("Invalid overflow policy {}, expected wrap, saturate or trap", s)))
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum OpCode {
+    Addr, Addi, Mulr, Muli, Banr, Bani, Borr, Bori, Setr, Seti, Gtir, Gtri, Gtrr, Eqir, Eqri, Eqrr
+}
+
+impl OpCode {
+    pub fn all() -> Vec<OpCode> {
+        use self::OpCode::*;
+        vec![Addr, Addi, Mulr, Muli, Banr, Bani, Borr, Bori, Setr, Seti, Gtir, Gtri, Gtrr, Eqir, Eqri, Eqrr]
+    }
+
+    pub fn name(self) -> &'static str {
+        use self::OpCode::*;
+        match self {
+            Addr => "addr",
+            Addi => "addi",
+            Mulr => "mulr",
+            Muli => "muli",
+            Banr => "banr",
+            Bani => "bani",
+            Borr => "borr",
+            Bori => "bori",
+            Setr => "setr",
+            Seti => "seti",
+            Gtir => "gtir",
+            Gtri => "gtri",
+            Gtrr => "gtrr",
+            Eqir => "eqir",
+            Eqri => "eqri",
+            Eqrr => "eqrr"
+        }
+    }
+
+    // Whether operand A and B are register references, C is always a register
+    pub fn reg_operands(self) -> (bool, bool) {
+        use self::OpCode::*;
+        match self {
+            Addr | Mulr | Banr | Borr | Gtrr | Eqrr => (true, true),
+            Addi | Muli | Bani | Bori | Gtri | Eqri => (true, false),
+            Setr => (true, false),
+            Seti => (false, false),
+            Gtir | Eqir => (false, true)
+        }
+    }
+}
+
+impl FromStr for OpCode {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        OpCode::all().into_iter()
+            .find(|oc| oc.name() == s)
+            .ok_or_else(|| ParseError::expected("an opcode like addr", s))
+    }
+}
+
+impl fmt::Display for OpCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Instruction {
+    pub opc: OpCode,
+    pub inp_a: u32,
+    pub inp_b: u32,
+    pub out_c: u32
+}
+
+impl Instruction {
+    pub fn new(opc: OpCode, inp_a: u32, inp_b: u32, out_c: u32) -> Instruction {
+        Instruction { opc, inp_a, inp_b, out_c }
+    }
+
+    // Pseudo code for the instruction, e.g. `c = a + 5`
+    pub fn explain(&self, ip_reg: Option<usize>) -> String {
+        self.render(&|i| register_name(i as usize, ip_reg))
+    }
+
+    // Pseudo code for the instruction with registers named by the given function
+    pub fn render(&self, r: &dyn Fn(u32) -> String) -> String {
+        use self::OpCode::*;
+        let (a, b, c) = (self.inp_a, self.inp_b, self.out_c);
+        match self.opc {
+            Addr => format!("{} = {} + {}", r(c), r(a), r(b)),
+            Addi => format!("{} = {} + {}", r(c), r(a), b),
+            Mulr => format!("{} = {} * {}", r(c), r(a), r(b)),
+            Muli => format!("{} = {} * {}", r(c), r(a), b),
+            Banr => format!("{} = {} & {}", r(c), r(a), r(b)),
+            Bani => format!("{} = {} & {}", r(c), r(a), b),
+            Borr => format!("{} = {} | {}", r(c), r(a), r(b)),
+            Bori => format!("{} = {} | {}", r(c), r(a), b),
+            Setr => format!("{} = {}", r(c), r(a)),
+            Seti => format!("{} = {}", r(c), a),
+            Gtir => format!("{} = if {} > {} {{ 1 }} else {{ 0 }}", r(c), a, r(b)),
+            Gtri => format!("{} = if {} > {} {{ 1 }} else {{ 0 }}", r(c), r(a), b),
+            Gtrr => format!("{} = if {} > {} {{ 1 }} else {{ 0 }}", r(c), r(a), r(b)),
+            Eqir => format!("{} = if {} == {} {{ 1 }} else {{ 0 }}", r(c), a, r(b)),
+            Eqri => format!("{} = if {} == {} {{ 1 }} else {{ 0 }}", r(c), r(a), b),
+            Eqrr => format!("{} = if {} == {} {{ 1 }} else {{ 0 }}", r(c), r(a), r(b))
+        }
+    }
+}
+
+impl FromStr for Instruction {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split_whitespace().collect();
+        if parts.len() != 4 {
+            return Err(ParseError::expected("an opcode and three operands", s));
+        }
+        Ok(Instruction {
+            opc: parts[0].parse()?,
+            inp_a: parts[1].parse()?,
+            inp_b: parts[2].parse()?,
+            out_c: parts[3].parse()?
+        })
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} {} {}", self.opc, self.inp_a, self.inp_b, self.out_c)
+    }
+}
+
+// Registers are named a, b, c... with the one bound to the instruction pointer marked
+pub fn register_name(i: usize, ip_reg: Option<usize>) -> String {
+    let name = if i < 26 { ((b'a' + i as u8) as char).to_string() } else { format!("r{}", i) };
+    if ip_reg == Some(i) { format!("{}(ip)", name) } else { name }
+}
+
+// Inverse of register_name, also accepting a plain register number
+pub fn parse_register(s: &str) -> Result<usize, ParseError> {
+    let s = s.trim_end_matches("(ip)");
+    let bytes = s.as_bytes();
+    if bytes.len() == 1 && bytes[0].is_ascii_lowercase() {
+        Ok((bytes[0] - b'a') as usize)
+    } else {
+        s.strip_prefix('r').unwrap_or(s).parse::<usize>()
+            .map_err(|_| ParseError::new(format!("Invalid register: {}", s)))
+    }
+}
+
+// An instruction in the numeric form of day 16, `<opcode number> <a> <b> <c>`
+pub type RawInstruction = [u32; 4];
+
+// Reads the four numbers of a line, ignoring anything around them, so that both the numeric
+// instructions and the `Before: [3, 2, 1, 1]` register dumps of day 16 are handled
+pub fn parse_numeric_line(s: &str) -> Result<RawInstruction, ParseError> {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r"^\D*(\d+)\D+(\d+)\D+(\d+)\D+(\d+)\D*$").unwrap();
+    }
+    let caps = RE.captures(s)
+        .ok_or_else(|| ParseError::expected("four numbers", s))?;
+    let get = |idx| caps.get(idx).unwrap().as_str().parse::<u32>();
+    Ok([get(1)?, get(2)?, get(3)?, get(4)?])
+}
+
+pub fn decode(raw: &RawInstruction, mapping: &dyn Fn(u32) -> Option<OpCode>) -> Option<Instruction> {
+    mapping(raw[0]).map(|opc| Instruction::new(opc, raw[1], raw[2], raw[3]))
+}
+
+// Registers of the devices of days 19 and 21
+pub const REGISTERS: usize = 6;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Program {
+    pub ip_reg: Option<usize>,
+    pub instructions: Vec<Instruction>
+}
+
+impl Program {
+    pub fn new(ip_reg: Option<usize>, instructions: Vec<Instruction>) -> Program {
+        Program { ip_reg, instructions }
+    }
+
+    // Parses the textual form, an optional `#ip <register>` followed by e.g. `addr 1 2 3`
+    pub fn parse(input: &str) -> Result<Program, ParseError> {
+        let mut ip_reg = None;
+        let mut instructions = vec![];
+
+        for (ln, l) in input.lines().enumerate().map(|(ln, l)| (ln + 1, l.trim())) {
+            let err = |e: ParseError| e.at_line(ln).in_text(l);
+            if let Some(reg) = l.strip_prefix("#ip ") {
+                let r = parse_register(reg.trim()).map_err(err)?;
+                if r >= REGISTERS {
+                    return Err(err(ParseError::expected(format!("an ip register below {}", REGISTERS), reg.trim())));
+                }
+                ip_reg = Some(r);
+            } else if !l.is_empty() {
+                let ins = l.parse::<Instruction>().map_err(err)?;
+                let (reg_a, reg_b) = ins.opc.reg_operands();
+                let operands = [(reg_a, ins.inp_a), (reg_b, ins.inp_b), (true, ins.out_c)];
+                if let Some(&(_, r)) = operands.iter().find(|&&(is_reg, r)| is_reg && r as usize >= REGISTERS) {
+                    return Err(err(ParseError::expected(format!("a register below {}", REGISTERS), &r.to_string())));
+                }
+                instructions.push(ins);
+            }
+        }
+        Ok(Program { ip_reg, instructions })
+    }
+
+    // Parses the numeric form of day 16 given the opcode number to opcode mapping
+    pub fn parse_numeric(input: &str, mapping: &dyn Fn(u32) -> Option<OpCode>) -> Result<Program, ParseError> {
+        let mut instructions = vec![];
+        for (ln, l) in input.lines().enumerate().map(|(ln, l)| (ln + 1, l.trim())) {
+            if l.is_empty() {
+                continue;
+            }
+            let raw = parse_numeric_line(l).map_err(|e| e.at_line(ln))?;
+            let ins = decode(&raw, mapping)
+                .ok_or_else(|| ParseError::new(format!("Unknown opcode number {}", raw[0])).at_line(ln).in_text(l))?;
+            instructions.push(ins);
+        }
+        Ok(Program { ip_reg: None, instructions })
+    }
+
+    pub fn len(&self) -> usize {
+        self.instructions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instructions.is_empty()
+    }
+
+    // Listing of the program with the pseudo code of every instruction
+    pub fn listing(&self) -> String {
+        self.instructions.iter()
+            .enumerate()
+            .map(|(i, ins)| format!("{:3}  {:<20}  {}\n", i, ins.to_string(), ins.explain(self.ip_reg)))
+            .collect()
+    }
+}
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(ip_reg) = self.ip_reg {
+            writeln!(f, "#ip {}", ip_reg)?;
+        }
+        for ins in self.instructions.iter() {
+            writeln!(f, "{}", ins)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub enum Fault {
+    InvalidRegister { ip: usize, register: u32 },
+    // An addition or multiplication overflowed with the trap policy, operands as text to keep
+    // the fault independent of the register width
+    Overflow { ip: usize, ins: Instruction, operands: (String, String), width: &'static str }
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fault::InvalidRegister { ip, register } =>
+                write!(f, "Invalid register {} referenced by instruction at {}", register, ip),
+            Fault::Overflow { ip, ins, operands: (a, b), width } =>
+                write!(f, "Overflow of {} in instruction at {} ({}) with operands {} and {}", width, ip, ins, a, b)
+        }
+    }
+}
+
+impl Error for Fault {}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Device<W: Register = Word> {
+    pub registers: Vec<W>,
+    pub ip: usize,
+    pub ip_reg: Option<usize>,
+    pub overflow: Overflow
+}
+
+impl<W: Register> Device<W> {
+    pub fn new(n_registers: usize, ip_reg: Option<usize>) -> Device<W> {
+        Device { registers: vec![W::from_u32(0); n_registers], ip: 0, ip_reg, overflow: Overflow::Wrap }
+    }
+
+    pub fn with_registers(registers: Vec<W>) -> Device<W> {
+        Device { registers, ip: 0, ip_reg: None, overflow: Overflow::Wrap }
+    }
+
+    // Device with the registers needed for the program, six as in days 19 and 21
+    pub fn for_program(program: &Program) -> Device<W> {
+        Device::new(REGISTERS, program.ip_reg)
+    }
+
+    pub fn with_overflow(self, overflow: Overflow) -> Device<W> {
+        Device { overflow, ..self }
+    }
+
+    fn reg(&self, i: u32) -> Result<W, Fault> {
+        self.registers.get(i as usize).copied().ok_or(Fault::InvalidRegister { ip: self.ip, register: i })
+    }
+
+    // Executes the instruction, writing the instruction pointer to its bound register first and
+    // reading it back afterwards. Nothing changes when it faults
+    pub fn exec(&mut self, ins: &Instruction) -> Result<(), Fault> {
+        let ci = ins.out_c as usize;
+        if ci >= self.registers.len() {
+            return Err(Fault::InvalidRegister { ip: self.ip, register: ins.out_c });
+        }
+        if let Some(ipr) = self.ip_reg.filter(|&ipr| ipr >= self.registers.len()) {
+            return Err(Fault::InvalidRegister { ip: self.ip, register: ipr as u32 });
+        }
+        let saved_ip = self.ip_reg.map(|ipr| self.registers[ipr]);
+        if let Some(ipr) = self.ip_reg {
+            self.registers[ipr] = W::from_u32(self.ip as u32);
+        }
+
+        let res = self.eval(ins);
+        let res = match res {
+            Ok(res) => res,
+            Err(fault) => {
+                if let (Some(ipr), Some(v)) = (self.ip_reg, saved_ip) {
+                    self.registers[ipr] = v;
+                }
+                return Err(fault);
+            }
+        };
+        self.registers[ci] = res;
+
+        self.ip = match self.ip_reg {
+            Some(ipr) => self.registers[ipr].to_ip().map(|ip| ip.saturating_add(1)).unwrap_or(usize::MAX),
+            None => self.ip + 1
+        };
+        Ok(())
+    }
+
+    fn eval(&self, ins: &Instruction) -> Result<W, Fault> {
+        use self::OpCode::*;
+        let a = W::from_u32(ins.inp_a);
+        let b = W::from_u32(ins.inp_b);
+        let ra = || self.reg(ins.inp_a);
+        let rb = || self.reg(ins.inp_b);
+        let flag = |cond: bool| W::from_u32(if cond { 1 } else { 0 });
+        let overflow = |x: W, y: W| Fault::Overflow { ip: self.ip, ins: *ins, operands: (x.to_string(), y.to_string()), width: W::NAME };
+        let add = |x: W, y: W| self.overflow.add(x, y).ok_or_else(|| overflow(x, y));
+        let mul = |x: W, y: W| self.overflow.mul(x, y).ok_or_else(|| overflow(x, y));
+        Ok(match ins.opc {
+            Addr => add(ra()?, rb()?)?,
+            Addi => add(ra()?, b)?,
+            Mulr => mul(ra()?, rb()?)?,
+            Muli => mul(ra()?, b)?,
+            Banr => ra()? & rb()?,
+            Bani => ra()? & b,
+            Borr => ra()? | rb()?,
+            Bori => ra()? | b,
+            Setr => ra()?,
+            Seti => a,
+            Gtir => flag(a > rb()?),
+            Gtri => flag(ra()? > b),
+            Gtrr => flag(ra()? > rb()?),
+            Eqir => flag(a == rb()?),
+            Eqri => flag(ra()? == b),
+            Eqrr => flag(ra()? == rb()?)
+        })
+    }
+
+    pub fn halted(&self, program: &Program) -> bool {
+        self.ip >= program.len()
+    }
+
+    // Executes the instruction at the instruction pointer, returns false if the program has halted
+    pub fn step(&mut self, program: &Program) -> Result<bool, Fault> {
+        match program.instructions.get(self.ip) {
+            Some(ins) => {
+                self.exec(ins)?;
+                Ok(true)
+            },
+            None => Ok(false)
+        }
+    }
+
+    pub fn run(&mut self, program: &Program) -> Result<(), Fault> {
+        while self.step(program)? {}
+        Ok(())
+    }
+
+    // Runs until the instruction pointer reaches the given address or the program halts
+    pub fn run_to(&mut self, to_ip: usize, program: &Program) -> Result<(), Fault> {
+        while self.ip != to_ip && self.step(program)? {}
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM: &str =
+       "#ip 0
+        seti 5 0 1
+        seti 6 0 2
+        addi 0 1 0
+        addr 1 2 3
+        setr 1 0 0
+        seti 8 0 4
+        seti 9 0 5";
+
+    #[test]
+    fn test_parse() {
+        let program = Program::parse(PROGRAM).unwrap();
+        assert_eq!(program.ip_reg, Some(0));
+        assert_eq!(program.len(), 7);
+        assert_eq!(program.instructions[3], Instruction::new(OpCode::Addr, 1, 2, 3));
+        assert_eq!(Program::parse(&program.to_string()).unwrap(), program);
+
+        assert_eq!(Program::parse("seti 1 2").unwrap_err(),
+                   ParseError::expected("an opcode and three operands", "seti 1 2").at_line(1));
+        let e = Program::parse("seti 1 2 3\nfoo 1 2 3").unwrap_err();
+        assert_eq!(e.to_string(), "Parse error on line 2: expected an opcode like addr, found 'foo'");
+        let e = Program::parse("#ip 9\naddr 1 2 3").unwrap_err();
+        assert_eq!(e.to_string(), "Parse error on line 1: expected an ip register below 6, found '9'");
+        assert_eq!(Program::parse("#ip x").unwrap_err().line(), Some(1));
+        let e = Program::parse("#ip 0\naddr 9 0 0").unwrap_err();
+        assert_eq!(e.to_string(), "Parse error on line 2: expected a register below 6, found '9'");
+        assert_eq!(Program::parse("seti 9 0 5\neqir 7 6 0").unwrap_err().line(), Some(2));
+        assert!(Program::parse("seti 9 0 6").is_err());
+    }
+
+    #[test]
+    fn test_parse_numeric() {
+        let mapping = |n| if n == 9 { Some(OpCode::Mulr) } else { None };
+        let program = Program::parse_numeric("9 2 1 2", &mapping).unwrap();
+        assert_eq!(program.instructions, vec![Instruction::new(OpCode::Mulr, 2, 1, 2)]);
+        assert!(Program::parse_numeric("8 2 1 2", &mapping).is_err());
+        assert_eq!(parse_numeric_line("Before: [3, 2, 1, 1]").unwrap(), [3, 2, 1, 1]);
+    }
+
+    #[test]
+    fn test_exec() {
+        let mut d: Device = Device::with_registers(vec![3, 2, 1, 1]);
+        d.exec(&Instruction::new(OpCode::Mulr, 2, 1, 2)).unwrap();
+        assert_eq!(d.registers, vec![3, 2, 2, 1]);
+        d.exec(&Instruction::new(OpCode::Gtir, 4, 0, 3)).unwrap();
+        assert_eq!(d.registers, vec![3, 2, 2, 1]);
+        assert_eq!(d.exec(&Instruction::new(OpCode::Addr, 7, 0, 0)),
+                   Err(Fault::InvalidRegister { ip: 2, register: 7 }));
+
+        // The instruction pointer bound to a register the device doesn't have
+        let mut d: Device = Device::new(4, Some(5));
+        assert_eq!(d.exec(&Instruction::new(OpCode::Seti, 1, 0, 0)),
+                   Err(Fault::InvalidRegister { ip: 0, register: 5 }));
+        assert_eq!(d.registers, vec![0; 4]);
+    }
+
+    #[test]
+    fn test_run_bound_ip() {
+        let program = Program::parse(PROGRAM).unwrap();
+        let mut d: Device = Device::for_program(&program);
+        d.run(&program).unwrap();
+        assert_eq!(d.registers, vec![6, 5, 6, 0, 0, 9]);
+        assert_eq!(d.ip, 7);
+
+        let mut d: Device<i64> = Device::for_program(&program);
+        d.run(&program).unwrap();
+        assert_eq!(d.registers, vec![6, 5, 6, 0, 0, 9]);
+    }
+
+    #[test]
+    fn test_overflow() {
+        let square = Instruction::new(OpCode::Mulr, 0, 0, 0);
+        let mut d = Device::with_registers(vec![70_000u32]);
+        d.exec(&square).unwrap();
+        assert_eq!(d.registers, vec![70_000u32.wrapping_mul(70_000)]);
+
+        let mut d = Device::with_registers(vec![70_000u32]).with_overflow(Overflow::Saturate);
+        d.exec(&square).unwrap();
+        assert_eq!(d.registers, vec![u32::MAX]);
+
+        let mut d = Device::with_registers(vec![70_000u32, 0]).with_overflow(Overflow::Trap);
+        d.ip = 3;
+        let fault = d.exec(&square).unwrap_err();
+        assert_eq!(fault.to_string(), "Overflow of u32 in instruction at 3 (mulr 0 0 0) with operands 70000 and 70000");
+        assert_eq!((d.registers, d.ip), (vec![70_000, 0], 3));
+        // The same computation fits 64 bits
+        let mut d = Device::with_registers(vec![70_000u64]).with_overflow(Overflow::Trap);
+        d.exec(&square).unwrap();
+        assert_eq!(d.registers, vec![4_900_000_000]);
+
+        let mut d = Device::with_registers(vec![i64::MIN, 0]).with_overflow(Overflow::Saturate);
+        d.exec(&Instruction::new(OpCode::Addr, 0, 0, 1)).unwrap();
+        assert_eq!(d.registers[1], i64::MIN);
+        assert_eq!("trap".parse::<Overflow>(), Ok(Overflow::Trap));
+        assert!("panic".parse::<Overflow>().is_err());
+    }
+
+    #[test]
+    fn test_negative_ip_halts() {
+        let program = Program::parse("#ip 0\naddr 0 1 0\nseti 0 0 0").unwrap();
+        let mut d: Device<i64> = Device::for_program(&program);
+        d.registers[1] = -10;
+        d.run(&program).unwrap();
+        assert!(d.halted(&program));
+        assert_eq!(d.registers[0], -10);
+    }
+
+    #[test]
+    fn test_explain() {
+        let ins = Instruction::new(OpCode::Addi, 4, 16, 4);
+        assert_eq!(ins.explain(Some(4)), "e(ip) = e(ip) + 16");
+        assert_eq!(ins.explain(None), "e = e + 16");
+    }
+
+    #[test]
+    fn test_parse_register() {
+        assert_eq!(parse_register("c"), Ok(2));
+        assert_eq!(parse_register("e(ip)"), Ok(4));
+        assert_eq!(parse_register("r30"), Ok(30));
+        assert_eq!(parse_register("3"), Ok(3));
+        assert!(parse_register("ab").is_err());
+    }
+}
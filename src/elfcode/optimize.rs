@@ -0,0 +1,401 @@
+// Optimizing executor running ElfCode a basic block at a time, with known idioms replaced by
+// closed form operations
+use std::fmt;
+use elfcode::decompile::Cfg;
+use elfcode::{register_name, Device, Fault, OpCode, Program, Word};
+
+// Operand of an instruction in an idiom template
+#[derive(Clone, Copy)]
+enum Operand {
+    // Register variable, bound to the same register throughout and distinct from the others
+    Reg(usize),
+    // The register bound to the ip
+    Ip,
+    // Immediate with the given value
+    Imm(u32),
+    // Immediate variable, bound to the same value throughout
+    Val(usize),
+    // Ignored
+    Any
+}
+
+use self::Operand::*;
+
+type Template = [(OpCode, Operand, Operand, Operand)];
+
+// Nested loops adding every b for which some d in 1..=c gives b * d == c, the sum of divisors
+// of day 19
+const DIVISOR_SUM: &Template = &[
+    (OpCode::Seti, Imm(1), Any, Reg(3)),
+    (OpCode::Mulr, Reg(1), Reg(3), Reg(4)),
+    (OpCode::Eqrr, Reg(4), Reg(2), Reg(4)),
+    (OpCode::Addr, Reg(4), Ip, Ip),
+    (OpCode::Addi, Ip, Imm(1), Ip),
+    (OpCode::Addr, Reg(1), Reg(0), Reg(0)),
+    (OpCode::Addi, Reg(3), Imm(1), Reg(3)),
+    (OpCode::Gtrr, Reg(3), Reg(2), Reg(4)),
+    (OpCode::Addr, Ip, Reg(4), Ip),
+    (OpCode::Seti, Val(0), Any, Ip),
+    (OpCode::Addi, Reg(1), Imm(1), Reg(1)),
+    (OpCode::Gtrr, Reg(1), Reg(2), Reg(4)),
+    (OpCode::Addr, Reg(4), Ip, Ip),
+    (OpCode::Seti, Val(1), Any, Ip)
+];
+
+// Loop incrementing e until (e + 1) * k > f, a division of f by k as found in day 21
+const DIVISION: &Template = &[
+    (OpCode::Seti, Imm(0), Any, Reg(0)),
+    (OpCode::Addi, Reg(0), Imm(1), Reg(1)),
+    (OpCode::Muli, Reg(1), Val(0), Reg(1)),
+    (OpCode::Gtrr, Reg(1), Reg(2), Reg(1)),
+    (OpCode::Addr, Reg(1), Ip, Ip),
+    (OpCode::Addi, Ip, Imm(1), Ip),
+    (OpCode::Seti, Val(1), Any, Ip),
+    (OpCode::Addi, Reg(0), Imm(1), Reg(0)),
+    (OpCode::Seti, Val(2), Any, Ip)
+];
+
+#[derive(Clone, Default)]
+struct Bindings {
+    regs: [Option<u32>; 5],
+    vals: [Option<u32>; 3]
+}
+
+impl Bindings {
+    fn bind(&mut self, op: Operand, v: u32, ip_reg: u32) -> bool {
+        match op {
+            Reg(i) => match self.regs[i] {
+                Some(r) => r == v,
+                None if v == ip_reg || self.regs.contains(&Some(v)) => false,
+                None => {
+                    self.regs[i] = Some(v);
+                    true
+                }
+            },
+            Ip => v == ip_reg,
+            Imm(i) => v == i,
+            Val(i) => *self.vals[i].get_or_insert(v) == v,
+            Any => true
+        }
+    }
+
+    fn reg(&self, i: usize) -> usize {
+        self.regs[i].unwrap() as usize
+    }
+
+    fn val(&self, i: usize) -> usize {
+        self.vals[i].unwrap() as usize
+    }
+}
+
+fn commutes(opc: OpCode) -> bool {
+    use elfcode::OpCode::*;
+    matches!(opc, Addr | Mulr | Banr | Borr | Eqrr)
+}
+
+// Matches the template against the instructions at start, trying both operand orders of
+// commutative instructions
+fn match_template(program: &Program, start: usize, template: &Template) -> Option<Bindings> {
+    fn go(program: &Program, addr: usize, template: &Template, b: Bindings, ip_reg: u32) -> Option<Bindings> {
+        let (&(opc, ta, tb, tc), rest) = match template.split_first() {
+            Some(t) => t,
+            None => return Some(b)
+        };
+        let ins = program.instructions.get(addr)?;
+        if ins.opc != opc {
+            return None;
+        }
+        let mut orders = vec![(ins.inp_a, ins.inp_b)];
+        if commutes(opc) {
+            orders.push((ins.inp_b, ins.inp_a));
+        }
+        orders.into_iter().find_map(|(a, bb)| {
+            let mut b = b.clone();
+            if b.bind(ta, a, ip_reg) && b.bind(tb, bb, ip_reg) && b.bind(tc, ins.out_c, ip_reg) {
+                go(program, addr + 1, rest, b, ip_reg)
+            } else {
+                None
+            }
+        })
+    }
+
+    let ip_reg = program.ip_reg? as u32;
+    go(program, start, template, Bindings::default(), ip_reg)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Idiom {
+    // sum += every divisor of c that is at least b, with d and flag as loop registers
+    DivisorSum { sum: usize, b: usize, c: usize, d: usize, flag: usize, exit: usize },
+    // quotient = dividend / divisor, with scratch as loop register
+    Division { quotient: usize, scratch: usize, dividend: usize, divisor: Word, exit: usize }
+}
+
+impl Idiom {
+    // Recognizes an idiom starting at the address
+    pub fn find(program: &Program, start: usize) -> Option<Idiom> {
+        if let Some(b) = match_template(program, start, DIVISOR_SUM) {
+            // The inner loop jumps back to its start, the outer one to the start of the idiom
+            if b.val(0) == start && b.val(1) + 1 == start {
+                return Some(Idiom::DivisorSum {
+                    sum: b.reg(0), b: b.reg(1), c: b.reg(2), d: b.reg(3), flag: b.reg(4), exit: start + DIVISOR_SUM.len()
+                });
+            }
+        }
+        if let Some(b) = match_template(program, start, DIVISION) {
+            let exit = b.val(1) + 1;
+            if b.val(2) == start && b.val(0) > 0 && (exit < start || exit >= start + DIVISION.len()) {
+                return Some(Idiom::Division {
+                    quotient: b.reg(0), scratch: b.reg(1), dividend: b.reg(2), divisor: b.val(0) as Word, exit
+                });
+            }
+        }
+        None
+    }
+
+    fn registers(&self) -> Vec<usize> {
+        match *self {
+            Idiom::DivisorSum { sum, b, c, d, flag, .. } => vec![sum, b, c, d, flag],
+            Idiom::Division { quotient, scratch, dividend, .. } => vec![quotient, scratch, dividend]
+        }
+    }
+
+    // Applies the idiom to the device at its start, None if the registers are missing or the
+    // values would make the instructions wrap around, which the closed form doesn't model
+    fn apply(&self, d: &mut Device) -> Option<()> {
+        if self.registers().iter().any(|&r| r >= d.registers.len()) {
+            return None;
+        }
+        let r = &mut d.registers;
+        let exit = match *self {
+            Idiom::DivisorSum { sum, b, c, d, flag, exit } => {
+                // The loops run at least once, b up to c and d from 1 to c
+                let (b0, n) = (r[b], r[c]);
+                let (last_b, last_d) = (b0.max(n), n.max(1));
+                last_b.checked_mul(last_d)?;
+                let next_b = last_b.checked_add(1)?;
+                r[sum] = r[sum].wrapping_add(sum_divisors_from(n, b0));
+                r[b] = next_b;
+                r[d] = last_d + 1;
+                r[flag] = 1;
+                exit
+            },
+            Idiom::Division { quotient, scratch, dividend, divisor, exit } => {
+                let q = r[dividend] / divisor;
+                (q + 1).checked_mul(divisor)?;
+                r[quotient] = q;
+                r[scratch] = 1;
+                exit
+            }
+        };
+        if let Some(ipr) = d.ip_reg {
+            d.registers[ipr] = (exit - 1) as Word;
+        }
+        d.ip = exit;
+        Some(())
+    }
+}
+
+impl fmt::Display for Idiom {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let r = |i: usize| register_name(i, None);
+        match *self {
+            Idiom::DivisorSum { sum, b, c, exit, .. } =>
+                write!(f, "{} += sum of divisors of {} from {}, goto {}", r(sum), r(c), r(b), exit),
+            Idiom::Division { quotient, dividend, divisor, exit, .. } =>
+                write!(f, "{} = {} / {}, goto {}", r(quotient), r(dividend), divisor, exit)
+        }
+    }
+}
+
+// Sum of the divisors of n that are at least from
+pub fn sum_divisors_from(n: Word, from: Word) -> Word {
+    let mut sum = 0;
+    let mut i = 1;
+    while i * i <= n {
+        if n.is_multiple_of(i) {
+            let j = n / i;
+            if i >= from {
+                sum += i;
+            }
+            if j != i && j >= from {
+                sum += j;
+            }
+        }
+        i += 1;
+    }
+    sum
+}
+
+pub struct Optimized {
+    pub program: Program,
+    // Exclusive end of the basic block every address belongs to
+    block_end: Vec<usize>,
+    // Idiom starting at every address
+    idioms: Vec<Option<Idiom>>
+}
+
+impl Optimized {
+    pub fn compile(program: &Program) -> Optimized {
+        let mut block_end = vec![0; program.len()];
+        for block in Cfg::build(program).blocks {
+            for end in block_end[block.start..block.end].iter_mut() {
+                *end = block.end;
+            }
+        }
+        let idioms = (0..program.len()).map(|i| Idiom::find(program, i)).collect();
+        Optimized { program: program.clone(), block_end, idioms }
+    }
+
+    pub fn idioms(&self) -> Vec<(usize, Idiom)> {
+        self.idioms.iter().enumerate().filter_map(|(i, idiom)| idiom.map(|id| (i, id))).collect()
+    }
+
+    // Executes the idiom or the rest of the basic block at the ip, stopping early when reaching
+    // stop_at, returns false if the program has halted
+    fn advance(&self, d: &mut Device, stop_at: Option<usize>) -> Result<bool, Fault> {
+        if d.halted(&self.program) {
+            return Ok(false);
+        }
+        if let Some(idiom) = self.idioms[d.ip] {
+            if idiom.apply(d).is_some() {
+                return Ok(true);
+            }
+        }
+
+        let end = self.block_end[d.ip];
+        loop {
+            let ip = d.ip;
+            d.exec(&self.program.instructions[ip])?;
+            if d.ip != ip + 1 || d.ip >= end || Some(d.ip) == stop_at {
+                return Ok(true);
+            }
+        }
+    }
+
+    // Executes the idiom or basic block at the ip, returns false if the program has halted
+    pub fn step(&self, d: &mut Device) -> Result<bool, Fault> {
+        self.advance(d, None)
+    }
+
+    pub fn run(&self, d: &mut Device) -> Result<(), Fault> {
+        while self.advance(d, None)? {}
+        Ok(())
+    }
+
+    // Runs until the instruction pointer reaches the given address or the program halts, like
+    // Device::run_to an address inside of an idiom is skipped over
+    pub fn run_to(&self, to_ip: usize, d: &mut Device) -> Result<(), Fault> {
+        while d.ip != to_ip && self.advance(d, Some(to_ip))? {}
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Sums the divisors of c into a, with the idiom at 2
+    const DIVISORS: &str =
+       "#ip 5
+        seti 0 0 0
+        seti 1 0 1
+        seti 1 0 3
+        mulr 1 3 4
+        eqrr 4 2 4
+        addr 4 5 5
+        addi 5 1 5
+        addr 1 0 0
+        addi 3 1 3
+        gtrr 3 2 4
+        addr 5 4 5
+        seti 2 0 5
+        addi 1 1 1
+        gtrr 1 2 4
+        addr 4 5 5
+        seti 1 0 5";
+
+    // Divides c by 7 into a, with the idiom at 1
+    const DIVISION: &str =
+       "#ip 4
+        seti 0 0 3
+        seti 0 0 0
+        addi 0 1 1
+        muli 1 7 1
+        gtrr 1 2 1
+        addr 4 1 4
+        addi 4 1 4
+        seti 9 0 4
+        addi 0 1 0
+        seti 1 0 4
+        seti 1 0 3
+        addi 3 1 3";
+
+    fn run_both(program: &Program, registers: Vec<Word>) -> (Device, Device) {
+        let mut plain = Device::for_program(program);
+        plain.registers = registers.clone();
+        let mut fast = plain.clone();
+        plain.run(program).unwrap();
+        Optimized::compile(program).run(&mut fast).unwrap();
+        (plain, fast)
+    }
+
+    #[test]
+    fn test_find_idioms() {
+        let opt = Optimized::compile(&Program::parse(DIVISORS).unwrap());
+        assert_eq!(opt.idioms(), vec![(2, Idiom::DivisorSum { sum: 0, b: 1, c: 2, d: 3, flag: 4, exit: 16 })]);
+        assert_eq!(opt.idioms()[0].1.to_string(), "a += sum of divisors of c from b, goto 16");
+
+        let opt = Optimized::compile(&Program::parse(DIVISION).unwrap());
+        assert_eq!(opt.idioms(), vec![(1, Idiom::Division { quotient: 0, scratch: 1, dividend: 2, divisor: 7, exit: 10 })]);
+
+        // Changing the back jump breaks the idiom
+        let broken = DIVISION.replace("seti 1 0 4\n", "seti 2 0 4\n");
+        assert_eq!(Optimized::compile(&Program::parse(&broken).unwrap()).idioms(), vec![]);
+    }
+
+    #[test]
+    fn test_divisor_sum() {
+        let program = Program::parse(DIVISORS).unwrap();
+        for &c in [0, 1, 10, 33, 36].iter() {
+            let (plain, fast) = run_both(&program, vec![0, 0, c, 0, 0, 0]);
+            assert_eq!(fast, plain);
+        }
+        let mut fast = Device::for_program(&program);
+        fast.registers[2] = 10551236;
+        Optimized::compile(&program).run(&mut fast).unwrap();
+        assert_eq!(fast.registers[0], sum_divisors_from(10551236, 1));
+    }
+
+    #[test]
+    fn test_division() {
+        let program = Program::parse(DIVISION).unwrap();
+        for &c in [0, 6, 7, 8, 100].iter() {
+            let (plain, fast) = run_both(&program, vec![0, 0, c, 0, 0, 0]);
+            assert_eq!(fast, plain);
+            assert_eq!(fast.registers[0], c / 7);
+        }
+    }
+
+    #[test]
+    fn test_run_to() {
+        let program = Program::parse(DIVISION).unwrap();
+        let opt = Optimized::compile(&program);
+        let mut d = Device::for_program(&program);
+        d.registers[2] = 50;
+        opt.run_to(10, &mut d).unwrap();
+        assert_eq!((d.ip, d.registers[0], d.registers[3]), (10, 7, 0));
+        opt.run_to(10, &mut d).unwrap();
+        assert_eq!(d.ip, 10);
+        assert!(opt.step(&mut d).unwrap());
+        assert_eq!((d.ip, d.registers[3]), (12, 2));
+        assert!(!opt.step(&mut d).unwrap());
+    }
+
+    #[test]
+    fn test_sum_divisors_from() {
+        assert_eq!(sum_divisors_from(10, 1), 1 + 2 + 5 + 10);
+        assert_eq!(sum_divisors_from(36, 4), 4 + 6 + 9 + 12 + 18 + 36);
+        assert_eq!(sum_divisors_from(0, 1), 0);
+    }
+}
@@ -0,0 +1,403 @@
+// Execution traces of ElfCode programs in a compact binary log, with a reader, filters and a diff
+// of two runs
+//
+// The log starts with the header
+//
+//   "ELFT" <version> <number of registers> <ip register + 1, 0 for none> <initial registers>
+//
+// followed by a record per executed instruction
+//
+//   <tag> [<registers before>] <ip> <opcode> <a> <b> <c> <changed mask> <changed registers after>
+//
+// All numbers are LEB128 varints except the tag and opcode bytes. The registers before are only
+// written (tag 2) when they differ from the registers after the previous record, which they don't
+// in a plain run, so a record is usually 8-10 bytes
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+use elfcode::{register_name, Device, Instruction, OpCode, Program, Word};
+
+const MAGIC: &[u8] = b"ELFT";
+const VERSION: u8 = 1;
+const TAG_STEP: u8 = 1;
+const TAG_STEP_WITH_BEFORE: u8 = 2;
+
+fn write_varint<W: Write>(w: &mut W, mut v: u64) -> io::Result<()> {
+    let mut buf = [0; 10];
+    let mut n = 0;
+    loop {
+        let byte = (v & 0x7f) as u8;
+        v >>= 7;
+        if v == 0 {
+            buf[n] = byte;
+            return w.write_all(&buf[..=n]);
+        }
+        buf[n] = byte | 0x80;
+        n += 1;
+    }
+}
+
+fn invalid<S: Into<String>>(msg: S) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+fn read_byte<R: Read>(r: &mut R) -> io::Result<Option<u8>> {
+    let mut b = [0];
+    match r.read(&mut b)? {
+        0 => Ok(None),
+        _ => Ok(Some(b[0]))
+    }
+}
+
+fn read_varint<R: Read>(r: &mut R) -> io::Result<u64> {
+    let mut v = 0;
+    for shift in (0..64).step_by(7) {
+        let b = read_byte(r)?.ok_or_else(|| invalid("Truncated trace"))?;
+        v |= ((b & 0x7f) as u64) << shift;
+        if b & 0x80 == 0 {
+            return Ok(v);
+        }
+    }
+    Err(invalid("Varint too long"))
+}
+
+// Writes the steps of a run, see the module comment for the format
+pub struct TraceWriter<W: Write> {
+    out: W,
+    registers: Vec<Word>,
+    pub steps: u64
+}
+
+impl<W: Write> TraceWriter<W> {
+    // Starts the log with the registers of the device as they are before the first step
+    pub fn new(mut out: W, device: &Device) -> io::Result<TraceWriter<W>> {
+        if device.registers.len() > 64 {
+            return Err(invalid("Traces support at most 64 registers"));
+        }
+        out.write_all(MAGIC)?;
+        out.write_all(&[VERSION])?;
+        write_varint(&mut out, device.registers.len() as u64)?;
+        write_varint(&mut out, device.ip_reg.map(|r| r as u64 + 1).unwrap_or(0))?;
+        for &r in device.registers.iter() {
+            write_varint(&mut out, r)?;
+        }
+        Ok(TraceWriter { out, registers: device.registers.clone(), steps: 0 })
+    }
+
+    pub fn record(&mut self, ip: usize, ins: &Instruction, before: &[Word], after: &[Word]) -> io::Result<()> {
+        if before != &self.registers[..] {
+            self.out.write_all(&[TAG_STEP_WITH_BEFORE])?;
+            for &r in before {
+                write_varint(&mut self.out, r)?;
+            }
+        } else {
+            self.out.write_all(&[TAG_STEP])?;
+        }
+        write_varint(&mut self.out, ip as u64)?;
+        let opc = OpCode::all().iter().position(|&oc| oc == ins.opc).unwrap();
+        self.out.write_all(&[opc as u8])?;
+        for &op in [ins.inp_a, ins.inp_b, ins.out_c].iter() {
+            write_varint(&mut self.out, op as u64)?;
+        }
+
+        let mask = (0..after.len()).filter(|&i| after[i] != before[i]).fold(0u64, |m, i| m | 1 << i);
+        write_varint(&mut self.out, mask)?;
+        for i in (0..after.len()).filter(|&i| mask & 1 << i != 0) {
+            write_varint(&mut self.out, after[i])?;
+        }
+        self.registers = after.to_vec();
+        self.steps += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+// Runs the program on the device, at most limit steps, writing every step to the trace. Returns
+// the number of steps executed
+pub fn trace<W: Write>(program: &Program, device: &mut Device, out: W, limit: Option<u64>) -> Result<u64, Box<dyn Error>> {
+    let mut writer = TraceWriter::new(out, device)?;
+    while !device.halted(program) && limit.is_none_or(|l| writer.steps < l) {
+        let ip = device.ip;
+        let before = device.registers.clone();
+        let ins = program.instructions[ip];
+        device.exec(&ins)?;
+        writer.record(ip, &ins, &before, &device.registers)?;
+    }
+    let steps = writer.steps;
+    writer.finish()?;
+    Ok(steps)
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Step {
+    pub ip: usize,
+    pub ins: Instruction,
+    pub before: Vec<Word>,
+    pub after: Vec<Word>
+}
+
+impl Step {
+    pub fn changed(&self, register: usize) -> bool {
+        self.before.get(register) != self.after.get(register)
+    }
+}
+
+pub struct TraceReader<R: Read> {
+    input: R,
+    pub ip_reg: Option<usize>,
+    // Registers after the last step read
+    registers: Vec<Word>
+}
+
+impl<R: Read> TraceReader<R> {
+    pub fn new(mut input: R) -> io::Result<TraceReader<R>> {
+        let mut magic = [0; 5];
+        input.read_exact(&mut magic)?;
+        if &magic[..4] != MAGIC || magic[4] != VERSION {
+            return Err(invalid("Not an ElfCode trace"));
+        }
+        let n = read_varint(&mut input)? as usize;
+        if n > 64 {
+            return Err(invalid(format!("Invalid number of registers {}", n)));
+        }
+        let ip_reg = match read_varint(&mut input)? {
+            0 => None,
+            r => Some(r as usize - 1)
+        };
+        let registers = (0..n).map(|_| read_varint(&mut input)).collect::<io::Result<_>>()?;
+        Ok(TraceReader { input, ip_reg, registers })
+    }
+
+    // Registers before the first step, or after the steps read so far
+    pub fn registers(&self) -> &[Word] {
+        &self.registers
+    }
+
+    fn read_step(&mut self, tag: u8) -> io::Result<Step> {
+        let r = &mut self.input;
+        let before: Vec<Word> = match tag {
+            TAG_STEP => self.registers.clone(),
+            TAG_STEP_WITH_BEFORE => (0..self.registers.len()).map(|_| read_varint(r)).collect::<io::Result<_>>()?,
+            t => return Err(invalid(format!("Invalid record tag {}", t)))
+        };
+        let ip = read_varint(r)? as usize;
+        let opc = read_byte(r)?
+            .and_then(|b| OpCode::all().get(b as usize).cloned())
+            .ok_or_else(|| invalid("Invalid opcode"))?;
+        let ins = Instruction::new(opc, read_varint(r)? as u32, read_varint(r)? as u32, read_varint(r)? as u32);
+
+        let mask = read_varint(r)?;
+        let mut after = before.clone();
+        for (i, reg) in after.iter_mut().enumerate() {
+            if mask & 1 << i != 0 {
+                *reg = read_varint(r)?;
+            }
+        }
+        self.registers = after.clone();
+        Ok(Step { ip, ins, before, after })
+    }
+}
+
+impl<R: Read> Iterator for TraceReader<R> {
+    type Item = io::Result<Step>;
+
+    fn next(&mut self) -> Option<io::Result<Step>> {
+        match read_byte(&mut self.input) {
+            Ok(Some(tag)) => Some(self.read_step(tag)),
+            Ok(None) => None,
+            Err(e) => Some(Err(e))
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Filter {
+    // Steps executing one of the instructions
+    Addresses(BTreeSet<usize>),
+    // Steps changing the register
+    Changed(usize)
+}
+
+impl Filter {
+    pub fn matches(&self, step: &Step) -> bool {
+        match self {
+            Filter::Addresses(addrs) => addrs.contains(&step.ip),
+            Filter::Changed(r) => step.changed(*r)
+        }
+    }
+}
+
+// First step at which two runs differ, None for either step when that run ended before
+#[derive(PartialEq, Debug)]
+pub struct Divergence {
+    pub index: u64,
+    pub left: Option<Step>,
+    pub right: Option<Step>
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let step = |s: &Option<Step>| match s {
+            Some(s) => format!("ip={} {:<20} {:?} -> {:?}", s.ip, s.ins.to_string(), s.before, s.after),
+            None => "ended".to_string()
+        };
+        write!(f, "Runs diverge at step {}\n< {}\n> {}", self.index, step(&self.left), step(&self.right))
+    }
+}
+
+// Compares two runs step by step, steps are equal when they execute the same instruction at the
+// same address with the same registers before and after, apart from the ignored registers. Runs
+// with different initial values of a register only read by the program diverge where the
+// comparisons on it first give different results when ignoring it
+pub fn diff<A, B>(left: A, right: B, ignore: &[usize]) -> io::Result<Option<Divergence>>
+where A: IntoIterator<Item = io::Result<Step>>, B: IntoIterator<Item = io::Result<Step>> {
+    let same_registers = |l: &[Word], r: &[Word]| l.len() == r.len()
+        && (0..l.len()).all(|i| l[i] == r[i] || ignore.contains(&i));
+    let same = |l: &Step, r: &Step| (l.ip, l.ins) == (r.ip, r.ins)
+        && same_registers(&l.before, &r.before) && same_registers(&l.after, &r.after);
+
+    let mut left = left.into_iter();
+    let mut right = right.into_iter();
+    let mut index = 0;
+    loop {
+        let (l, r) = match (left.next(), right.next()) {
+            (None, None) => return Ok(None),
+            (l, r) => (l.transpose()?, r.transpose()?)
+        };
+        let equal = match (&l, &r) {
+            (Some(l), Some(r)) => same(l, r),
+            _ => false
+        };
+        if !equal {
+            return Ok(Some(Divergence { index, left: l, right: r }));
+        }
+        index += 1;
+    }
+}
+
+// Describes the step with the registers before it and the changed ones, other than the ip, like
+// `  28  eqrr 2 0 4  a=0 b=1 c=123 d=0 e=0 f(ip)=27  e: 0 -> 1`
+pub fn describe(step: &Step, ip_reg: Option<usize>) -> String {
+    let before: Vec<String> = step.before.iter().enumerate()
+        .map(|(r, v)| format!("{}={}", register_name(r, ip_reg), v))
+        .collect();
+    let changes: Vec<String> = (0..step.after.len())
+        .filter(|&r| step.changed(r) && Some(r) != ip_reg)
+        .map(|r| format!("{}: {} -> {}", register_name(r, None), step.before[r], step.after[r]))
+        .collect();
+    format!("{:4}  {:<20}  {}  {}", step.ip, step.ins.to_string(), before.join(" "), changes.join(", ")).trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Counts a up to 5 and then sets c to 9
+    const PROGRAM: &str =
+       "#ip 5
+        seti 0 0 0
+        addi 0 1 0
+        gtri 0 4 1
+        addr 5 1 5
+        seti 0 0 5
+        seti 9 0 2";
+
+    fn record(a: Word) -> (Vec<u8>, u64) {
+        let program = Program::parse(PROGRAM).unwrap();
+        let mut d = Device::for_program(&program);
+        d.registers[2] = a;
+        let mut out = vec![];
+        let steps = trace(&program, &mut d, &mut out, None).unwrap();
+        (out, steps)
+    }
+
+    fn read(log: &[u8]) -> Vec<Step> {
+        TraceReader::new(log).unwrap().collect::<io::Result<_>>().unwrap()
+    }
+
+    #[test]
+    fn test_varint() {
+        for &v in [0, 1, 127, 128, 300, u64::MAX].iter() {
+            let mut buf = vec![];
+            write_varint(&mut buf, v).unwrap();
+            assert_eq!(read_varint(&mut &buf[..]).unwrap(), v);
+        }
+        let mut buf = vec![];
+        write_varint(&mut buf, 300).unwrap();
+        assert_eq!(buf, vec![0xac, 0x02]);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let (log, steps) = record(0);
+        let read = read(&log);
+        assert_eq!(read.len() as u64, steps);
+        assert_eq!(steps, 21);
+
+        // Replaying the trace gives the same steps as running the program
+        let program = Program::parse(PROGRAM).unwrap();
+        let mut d = Device::for_program(&program);
+        for step in read.iter() {
+            assert_eq!((step.ip, step.ins, &step.before), (d.ip, program.instructions[d.ip], &d.registers));
+            d.step(&program).unwrap();
+            assert_eq!(step.after, d.registers);
+        }
+        assert!(log.len() < 12 + 21 * 10);
+    }
+
+    #[test]
+    fn test_explicit_before() {
+        let program = Program::parse(PROGRAM).unwrap();
+        let d = Device::for_program(&program);
+        let mut writer = TraceWriter::new(vec![], &d).unwrap();
+        let ins = program.instructions[1];
+        writer.record(1, &ins, &[4, 0, 0, 0, 0, 0], &[5, 0, 0, 0, 0, 1]).unwrap();
+        let log = writer.finish().unwrap();
+        assert_eq!(read(&log)[0].before, vec![4, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_filters() {
+        let (log, _) = record(0);
+        let at_two = Filter::Addresses(vec![2].into_iter().collect());
+        let values: Vec<Word> = read(&log).iter().filter(|s| at_two.matches(s)).map(|s| s.before[0]).collect();
+        assert_eq!(values, vec![1, 2, 3, 4, 5]);
+
+        let c_changed = Filter::Changed(2);
+        let steps: Vec<Step> = read(&log).into_iter().filter(|s| c_changed.matches(s)).collect();
+        assert_eq!(steps.len(), 1);
+        assert_eq!(describe(&steps[0], Some(5)), "   5  seti 9 0 2            a=5 b=1 c=0 d=0 e=0 f(ip)=4  c: 0 -> 9");
+    }
+
+    #[test]
+    fn test_diff() {
+        let (a, _) = record(0);
+        let (b, _) = record(0);
+        assert_eq!(diff(TraceReader::new(&a[..]).unwrap(), TraceReader::new(&b[..]).unwrap(), &[]).unwrap(), None);
+
+        let (c, _) = record(7);
+        let d = diff(TraceReader::new(&a[..]).unwrap(), TraceReader::new(&c[..]).unwrap(), &[]).unwrap().unwrap();
+        assert_eq!(d.index, 0);
+        assert_eq!(d.left.unwrap().before[2], 0);
+        assert_eq!(d.right.unwrap().before[2], 7);
+        // c is only overwritten at the end, so ignoring it the runs match up to the last step
+        let d = diff(TraceReader::new(&a[..]).unwrap(), TraceReader::new(&c[..]).unwrap(), &[2]).unwrap();
+        assert_eq!(d, None);
+
+        let d = diff(TraceReader::new(&a[..]).unwrap(), read(&a).into_iter().take(5).map(Ok), &[]).unwrap().unwrap();
+        assert_eq!((d.index, d.right), (5, None));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(TraceReader::new(&b"ELFX\x01"[..]).is_err());
+        let (log, _) = record(0);
+        let truncated: Vec<io::Result<Step>> = TraceReader::new(&log[..log.len() - 1]).unwrap().collect();
+        assert!(truncated.last().unwrap().is_err());
+    }
+}
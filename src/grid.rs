@@ -0,0 +1,336 @@
+// Two dimensional grid of cells stored row by row, indexed by (x, y) with y growing downwards
+use std::fmt;
+use std::ops::{Index, IndexMut};
+use point::Point;
+use ParseError;
+
+// Neighbour offsets in reading order, top to bottom and left to right
+const NEIGHBOURS4: [(i64, i64); 4] = [(0, -1), (-1, 0), (1, 0), (0, 1)];
+const NEIGHBOURS8: [(i64, i64); 8] = [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)];
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Grid<T> {
+    w: usize,
+    h: usize,
+    cells: Vec<T>
+}
+
+impl<T: Clone> Grid<T> {
+    pub fn new(w: usize, h: usize, value: T) -> Grid<T> {
+        Grid { w, h, cells: vec![value; w * h] }
+    }
+}
+
+impl<T> Grid<T> {
+    pub fn from_fn<F: FnMut(usize, usize) -> T>(w: usize, h: usize, mut f: F) -> Grid<T> {
+        let cells = (0..h).flat_map(|y| (0..w).map(move |x| (x, y))).map(|(x, y)| f(x, y)).collect();
+        Grid { w, h, cells }
+    }
+
+    // Parses ASCII art, one row per line with leading and trailing whitespace and empty lines
+    // ignored, mapping every character given its position to a cell
+    pub fn parse<F>(input: &str, f: F) -> Result<Grid<T>, ParseError>
+    where F: FnMut(usize, usize, char) -> Result<T, ParseError> {
+        let rows = input.lines().enumerate()
+            .map(|(ln, l)| (ln + 1, l.trim()))
+            .filter(|(_, l)| !l.is_empty());
+        Grid::parse_rows(rows, f)
+    }
+
+    // Parses rows given as lines, which must all have the same number of characters
+    pub fn parse_lines<'a, I, F>(lines: I, f: F) -> Result<Grid<T>, ParseError>
+    where I: IntoIterator<Item = &'a str>, F: FnMut(usize, usize, char) -> Result<T, ParseError> {
+        Grid::parse_rows(lines.into_iter().enumerate().map(|(y, l)| (y + 1, l)), f)
+    }
+
+    // Rows with their line numbers, which are added to errors
+    fn parse_rows<'a, I, F>(rows: I, mut f: F) -> Result<Grid<T>, ParseError>
+    where I: Iterator<Item = (usize, &'a str)>, F: FnMut(usize, usize, char) -> Result<T, ParseError> {
+        let mut cells = vec![];
+        let mut w = None;
+        let mut h = 0;
+        for (y, (ln, l)) in rows.enumerate() {
+            let n = l.chars().count();
+            match w {
+                Some(w) if w != n => return Err(ParseError::new(format!("Row has {} cells, expected {}", n, w)).at_line(ln)),
+                _ => w = Some(n)
+            }
+            for (x, c) in l.chars().enumerate() {
+                cells.push(f(x, y, c).map_err(|e| e.at_line(ln).in_text(l))?);
+            }
+            h += 1;
+        }
+        Ok(Grid { w: w.unwrap_or(0), h, cells })
+    }
+
+    pub fn width(&self) -> usize {
+        self.w
+    }
+
+    pub fn height(&self) -> usize {
+        self.h
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<&T> {
+        if x < self.w && y < self.h { Some(&self.cells[y * self.w + x]) } else { None }
+    }
+
+    pub fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut T> {
+        if x < self.w && y < self.h { Some(&mut self.cells[y * self.w + x]) } else { None }
+    }
+
+    // Cell at a position which may be outside of the grid on any side
+    pub fn get_signed(&self, x: i64, y: i64) -> Option<&T> {
+        self.offset((0, 0), (x, y)).and_then(|(x, y)| self.get(x, y))
+    }
+
+    pub fn get_signed_mut(&mut self, x: i64, y: i64) -> Option<&mut T> {
+        self.offset((0, 0), (x, y)).and_then(move |(x, y)| self.get_mut(x, y))
+    }
+
+    // Position moved by the offset, None if outside of the grid
+    pub fn offset(&self, (x, y): (usize, usize), (dx, dy): (i64, i64)) -> Option<(usize, usize)> {
+        let nx = x as i64 + dx;
+        let ny = y as i64 + dy;
+        if nx >= 0 && ny >= 0 && (nx as usize) < self.w && (ny as usize) < self.h {
+            Some((nx as usize, ny as usize))
+        } else {
+            None
+        }
+    }
+
+    // Positions of all cells in reading order
+    pub fn positions(&self) -> impl Iterator<Item = (usize, usize)> {
+        let w = self.w;
+        (0..self.h).flat_map(move |y| (0..w).map(move |x| (x, y)))
+    }
+
+    fn neighbours(&self, x: usize, y: usize, offsets: &'static [(i64, i64)]) -> impl Iterator<Item = (usize, usize)> {
+        let (w, h) = (self.w as i64, self.h as i64);
+        offsets.iter()
+            .map(move |&(dx, dy)| (x as i64 + dx, y as i64 + dy))
+            .filter(move |&(nx, ny)| nx >= 0 && ny >= 0 && nx < w && ny < h)
+            .map(|(nx, ny)| (nx as usize, ny as usize))
+    }
+
+    // Positions of the orthogonal neighbours within the grid, in reading order
+    pub fn neighbours4(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> {
+        self.neighbours(x, y, &NEIGHBOURS4)
+    }
+
+    // Positions of the orthogonal and diagonal neighbours within the grid, in reading order
+    pub fn neighbours8(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> {
+        self.neighbours(x, y, &NEIGHBOURS8)
+    }
+
+    // Cells in reading order
+    pub fn iter(&self) -> ::std::slice::Iter<'_, T> {
+        self.cells.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> ::std::slice::IterMut<'_, T> {
+        self.cells.iter_mut()
+    }
+
+    pub fn row(&self, y: usize) -> &[T] {
+        &self.cells[y * self.w..(y + 1) * self.w]
+    }
+
+    pub fn map<U, F: FnMut(&T) -> U>(&self, f: F) -> Grid<U> {
+        Grid { w: self.w, h: self.h, cells: self.cells.iter().map(f).collect() }
+    }
+
+    // View of the w x h cells with the top left corner at (x, y)
+    pub fn view(&self, x: usize, y: usize, w: usize, h: usize) -> GridView<'_, T> {
+        assert!(x + w <= self.w && y + h <= self.h,
+                "View {}x{} at {},{} outside of the {}x{} grid", w, h, x, y, self.w, self.h);
+        GridView { grid: self, x, y, w, h }
+    }
+
+    // ASCII art of the grid, a line per row
+    pub fn render<F: FnMut(&T) -> char>(&self, f: F) -> String {
+        self.view(0, 0, self.w, self.h).render(f)
+    }
+}
+
+impl<T> Index<(usize, usize)> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, (x, y): (usize, usize)) -> &T {
+        if x >= self.w || y >= self.h {
+            panic!("Position {},{} outside of the {}x{} grid", x, y, self.w, self.h);
+        }
+        &self.cells[y * self.w + x]
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for Grid<T> {
+    fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut T {
+        if x >= self.w || y >= self.h {
+            panic!("Position {},{} outside of the {}x{} grid", x, y, self.w, self.h);
+        }
+        &mut self.cells[y * self.w + x]
+    }
+}
+
+impl<T> Index<Point<2>> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, p: Point<2>) -> &T {
+        match self.get_signed(p.x(), p.y()) {
+            Some(c) => c,
+            None => panic!("Position {} outside of the {}x{} grid", p, self.w, self.h)
+        }
+    }
+}
+
+impl<T> IndexMut<Point<2>> for Grid<T> {
+    fn index_mut(&mut self, p: Point<2>) -> &mut T {
+        let (w, h) = (self.w, self.h);
+        match self.get_signed_mut(p.x(), p.y()) {
+            Some(c) => c,
+            None => panic!("Position {} outside of the {}x{} grid", p, w, h)
+        }
+    }
+}
+
+impl fmt::Display for Grid<char> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.render(|&c| c))
+    }
+}
+
+// Rectangular part of a grid, positions are relative to its top left corner
+pub struct GridView<'a, T: 'a> {
+    grid: &'a Grid<T>,
+    x: usize,
+    y: usize,
+    w: usize,
+    h: usize
+}
+
+// Not derived as that would require T: Copy
+impl<'a, T> Clone for GridView<'a, T> {
+    fn clone(&self) -> GridView<'a, T> {
+        *self
+    }
+}
+
+impl<'a, T> Copy for GridView<'a, T> {}
+
+impl<'a, T> GridView<'a, T> {
+    pub fn width(&self) -> usize {
+        self.w
+    }
+
+    pub fn height(&self) -> usize {
+        self.h
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<&'a T> {
+        if x < self.w && y < self.h { self.grid.get(self.x + x, self.y + y) } else { None }
+    }
+
+    pub fn row(&self, y: usize) -> &'a [T] {
+        &self.grid.row(self.y + y)[self.x..self.x + self.w]
+    }
+
+    // Cells in reading order
+    pub fn iter(&self) -> impl Iterator<Item = &'a T> {
+        let view = *self;
+        (0..self.h).flat_map(move |y| view.row(y).iter())
+    }
+
+    pub fn render<F: FnMut(&T) -> char>(&self, mut f: F) -> String {
+        let mut s = String::with_capacity((self.w + 1) * self.h);
+        for y in 0..self.h {
+            s.extend(self.row(y).iter().map(&mut f));
+            s.push('\n');
+        }
+        s
+    }
+}
+
+impl<'a, T: Clone> GridView<'a, T> {
+    pub fn to_grid(&self) -> Grid<T> {
+        Grid { w: self.w, h: self.h, cells: self.iter().cloned().collect() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str =
+       "#..#
+        .#..
+        ..#.";
+
+    fn as_grid(s: &str) -> Grid<bool> {
+        Grid::parse(s, |_, _, c| match c {
+            '#' => Ok(true),
+            '.' => Ok(false),
+            _ => Err(ParseError::expected("'#' or '.'", &c.to_string()))
+        }).unwrap()
+    }
+
+    #[test]
+    fn test_parse_render() {
+        let g = as_grid(INPUT);
+        assert_eq!((g.width(), g.height()), (4, 3));
+        assert!(g[(3, 0)] && g[(2, 2)] && !g[(0, 2)]);
+        assert_eq!(g.render(|&b| if b { '#' } else { '.' }), "#..#\n.#..\n..#.\n");
+        assert_eq!(Grid::parse("ab\nc", |_, _, c| Ok(c)).unwrap_err(), ParseError::new("Row has 1 cells, expected 2").at_line(2));
+        let e = Grid::parse("##\n\n#x", |_, _, c| if c == '#' { Ok(c) } else { Err(ParseError::new("Invalid cell")) }).unwrap_err();
+        assert_eq!((e.line(), e.text()), (Some(3), Some("#x")));
+
+        let chars = Grid::parse("ab\ncd", |_, _, c| Ok(c)).unwrap();
+        assert_eq!(chars.to_string(), "ab\ncd\n");
+    }
+
+    #[test]
+    fn test_indexing() {
+        let mut g = Grid::from_fn(3, 2, |x, y| 10 * y + x);
+        assert_eq!(g.row(1), &[10, 11, 12]);
+        assert_eq!(g.get(3, 0), None);
+        assert_eq!(g.get_signed(-1, 0), None);
+        assert_eq!(g.get_signed(2, 1), Some(&12));
+        assert_eq!(g.offset((2, 1), (-2, -1)), Some((0, 0)));
+        assert_eq!(g.offset((2, 1), (1, 0)), None);
+        g[(1, 1)] = 99;
+        *g.get_signed_mut(0, 0).unwrap() = 7;
+        assert_eq!(g.iter().cloned().collect::<Vec<_>>(), vec![7, 1, 2, 10, 99, 12]);
+        assert_eq!(g.positions().nth(4), Some((1, 1)));
+        assert_eq!(g.map(|&v| v % 2 == 0).iter().filter(|&&e| e).count(), 3);
+        g[Point([2, 0])] = 5;
+        assert_eq!(g[Point([2, 0])], 5);
+    }
+
+    #[test]
+    #[should_panic(expected = "Position 3,0 outside of the 3x2 grid")]
+    fn test_index_out_of_bounds() {
+        let g = Grid::new(3, 2, 0);
+        let _ = g[(3, 0)];
+    }
+
+    #[test]
+    fn test_neighbours() {
+        let g = Grid::new(3, 3, ());
+        assert_eq!(g.neighbours4(1, 1).collect::<Vec<_>>(), vec![(1, 0), (0, 1), (2, 1), (1, 2)]);
+        assert_eq!(g.neighbours4(0, 0).collect::<Vec<_>>(), vec![(1, 0), (0, 1)]);
+        assert_eq!(g.neighbours8(1, 1).count(), 8);
+        assert_eq!(g.neighbours8(2, 2).collect::<Vec<_>>(), vec![(1, 1), (2, 1), (1, 2)]);
+    }
+
+    #[test]
+    fn test_view() {
+        let g = Grid::from_fn(4, 3, |x, y| 10 * y + x);
+        let v = g.view(1, 1, 2, 2);
+        assert_eq!((v.width(), v.height()), (2, 2));
+        assert_eq!(v.iter().cloned().collect::<Vec<_>>(), vec![11, 12, 21, 22]);
+        assert_eq!(v.get(1, 0), Some(&12));
+        assert_eq!(v.get(2, 0), None);
+        assert_eq!(v.render(|&c| (b'0' + (c % 10) as u8) as char), "12\n12\n");
+        assert_eq!(v.to_grid().row(1), &[21, 22]);
+    }
+}
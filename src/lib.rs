@@ -1,17 +1,218 @@
+extern crate regex;
+#[macro_use] extern crate lazy_static;
 extern crate time;
 
+// First, so that its macros can be used by all other modules
+#[macro_use] pub mod log;
+
+pub mod answers;
+pub mod bench;
+pub mod cycle;
+pub mod days;
+pub mod elfcode;
+pub mod grid;
+pub mod point;
+pub mod render;
+pub mod search;
+
+// The solutions keep the style they were originally written in, allowing the lints it trips
+#[path = "day01/day01.rs"] #[allow(clippy::ptr_arg)]
+pub mod day01;
+#[path = "day02/day02.rs"] #[allow(clippy::ptr_arg)]
+pub mod day02;
+#[path = "day03/day03.rs"] pub mod day03;
+#[path = "day04/day04.rs"] #[allow(clippy::assign_op_pattern, clippy::redundant_field_names, clippy::redundant_static_lifetimes, clippy::unnecessary_cast)]
+pub mod day04;
+#[path = "day05/day05.rs"] #[allow(clippy::bool_assert_comparison, clippy::iter_kv_map, clippy::manual_ignore_case_cmp, clippy::redundant_static_lifetimes, clippy::unwrap_or_default)]
+pub mod day05;
+#[path = "day06/day06.rs"] #[allow(clippy::needless_borrow, clippy::ptr_arg, clippy::redundant_static_lifetimes, clippy::unnecessary_sort_by)]
+pub mod day06;
+#[path = "day07/day07.rs"] #[allow(clippy::explicit_auto_deref, clippy::filter_next, clippy::len_zero, clippy::needless_range_loop, clippy::redundant_field_names, clippy::redundant_static_lifetimes)]
+pub mod day07;
+#[path = "day08/day08.rs"] #[allow(clippy::explicit_auto_deref, clippy::len_zero, clippy::redundant_static_lifetimes)]
+pub mod day08;
+#[path = "day09/day09.rs"] #[allow(clippy::ptr_arg, clippy::redundant_field_names, clippy::redundant_static_lifetimes)]
+pub mod day09;
+#[path = "day10/day10.rs"] #[allow(clippy::into_iter_on_ref, clippy::needless_borrow, clippy::ptr_arg, clippy::redundant_static_lifetimes)]
+pub mod day10;
+#[path = "day11/day11.rs"] pub mod day11;
+#[path = "day12/day12.rs"] #[allow(clippy::redundant_static_lifetimes)]
+pub mod day12;
+#[path = "day13/day13.rs"] #[allow(clippy::redundant_static_lifetimes)]
+pub mod day13;
+#[path = "day14/day14.rs"] #[allow(clippy::needless_range_loop)]
+pub mod day14;
+#[path = "day15/day15.rs"] pub mod day15;
+#[path = "day16/day16.rs"] #[allow(clippy::len_zero)]
+pub mod day16;
+#[path = "day17/day17.rs"] #[allow(clippy::len_zero, clippy::redundant_field_names, clippy::redundant_static_lifetimes)]
+pub mod day17;
+#[path = "day18/day18.rs"] #[allow(clippy::redundant_static_lifetimes)]
+pub mod day18;
+#[path = "day19/day19.rs"] #[allow(clippy::redundant_static_lifetimes)]
+pub mod day19;
+#[path = "day20/day20.rs"] #[allow(clippy::ptr_arg, clippy::redundant_field_names)]
+pub mod day20;
+#[path = "day21/day21.rs"] pub mod day21;
+#[path = "day22/day22.rs"] #[allow(clippy::match_like_matches_macro, clippy::redundant_field_names, clippy::redundant_static_lifetimes)]
+pub mod day22;
+#[path = "day23/day23.rs"] #[allow(clippy::len_zero, clippy::redundant_field_names)]
+pub mod day23;
+#[path = "day24/day24.rs"] #[allow(clippy::len_zero, clippy::manual_strip, clippy::ptr_arg, clippy::redundant_field_names, clippy::redundant_static_lifetimes)]
+pub mod day24;
+#[path = "day25/day25.rs"] #[allow(clippy::needless_range_loop)]
+pub mod day25;
 
 use std::error::Error;
-use time::*;
+use std::fmt;
+use std::num::ParseIntError;
+
+// What went wrong while parsing an input, with the line, the offending text and what was
+// expected instead where known, e.g. `Parse error on line 3: expected a claim like
+// '#1 @ 1,3: 4x4', found '#1 @ x'`
+#[derive(PartialEq, Debug)]
+pub struct ParseError {
+    msg: String,
+    line: Option<usize>,
+    text: Option<String>,
+    expected: Option<String>
+}
+
+impl ParseError {
+    pub fn new<S: Into<String>>(msg: S) -> ParseError {
+        ParseError { msg: msg.into(), line: None, text: None, expected: None }
+    }
+
+    // Text that doesn't have the expected form
+    pub fn expected<S: Into<String>>(expected: S, text: &str) -> ParseError {
+        ParseError { msg: String::new(), line: None, text: Some(text.to_string()), expected: Some(expected.into()) }
+    }
+
+    // The line the error is on, unless a more precise one is known already
+    pub fn at_line(mut self, line: usize) -> ParseError {
+        self.line = self.line.or(Some(line));
+        self
+    }
+
+    // The offending text, unless a more precise one is known already
+    pub fn in_text(mut self, text: &str) -> ParseError {
+        if self.text.is_none() {
+            self.text = Some(text.to_string());
+        }
+        self
+    }
+
+    pub fn message(&self) -> &str {
+        &self.msg
+    }
+
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+
+    pub fn text(&self) -> Option<&str> {
+        self.text.as_deref()
+    }
+
+    pub fn expectation(&self) -> Option<&str> {
+        self.expected.as_deref()
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Parse error")?;
+        if let Some(line) = self.line {
+            write!(f, " on line {}", line)?;
+        }
+        write!(f, ": {}", self.msg)?;
+        if let Some(expected) = &self.expected {
+            write!(f, "{}expected {}", if !self.msg.is_empty() { ", " } else { "" }, expected)?;
+        }
+        match &self.text {
+            Some(text) if self.expected.is_some() => write!(f, ", found '{}'", text),
+            Some(text) => write!(f, " in '{}'", text),
+            None => Ok(())
+        }
+    }
+}
 
-pub fn measure_exec<F>(f: F) -> Result<(), Box<Error>>
-where F: FnOnce() -> Result<(), Box<Error>> {
-    let start = precise_time_ns();
+impl Error for ParseError {}
+
+impl From<ParseIntError> for ParseError {
+    fn from(e: ParseIntError) -> ParseError {
+        ParseError::new(e.to_string())
+    }
+}
+
+// Parses every non-empty line, trimmed, adding the line number and text to errors
+pub fn parse_lines<T, F>(input: &str, mut parse: F) -> Result<Vec<T>, ParseError>
+where F: FnMut(&str) -> Result<T, ParseError> {
+    input.lines().enumerate()
+        .map(|(ln, l)| (ln + 1, l.trim()))
+        .filter(|(_, l)| !l.is_empty())
+        .map(|(ln, l)| parse(l).map_err(|e| e.at_line(ln).in_text(l)))
+        .collect()
+}
+
+// What a part of a solution returns, the answer or why the input has none, e.g. day 13 carts
+// running off their tracks
+pub trait Answer {
+    fn into_result(self) -> Result<String, Box<dyn Error>>;
+}
+
+macro_rules! answers {
+    ($($t:ty),*) => {
+        $(impl Answer for $t {
+            fn into_result(self) -> Result<String, Box<dyn Error>> {
+                Ok(self.to_string())
+            }
+        })*
+    }
+}
+
+answers!(i32, i64, u32, u64, usize, String, &'static str);
+
+impl<T: Answer, E: Error + 'static> Answer for Result<T, E> {
+    fn into_result(self) -> Result<String, Box<dyn Error>> {
+        match self {
+            Ok(answer) => answer.into_result(),
+            Err(e) => Err(Box::new(e))
+        }
+    }
+}
+
+// A solution for one day, parsing of the input is separated from solving the parts so
+// that each step can be timed, tested and reused on its own
+pub trait Solution {
+    type Input;
+    type Answer1: Answer;
+    type Answer2: Answer;
+
+    fn parse(input: &str) -> Result<Self::Input, ParseError>;
+    fn part1(input: &Self::Input) -> Self::Answer1;
+    fn part2(input: &Self::Input) -> Self::Answer2;
+}
 
-    f()?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let dur_ns = precise_time_ns() - start;
-    println!("Exec time: {}ms", dur_ns / 1_000_000);
+    #[test]
+    fn test_parse_error() {
+        assert_eq!(ParseError::new("Empty input").to_string(), "Parse error: Empty input");
+        assert_eq!(ParseError::expected("a number", "x").at_line(3).to_string(),
+                   "Parse error on line 3: expected a number, found 'x'");
+        let e = ParseError::new("Unknown key").in_text("a = 1").at_line(2).at_line(5).in_text("b");
+        assert_eq!((e.line(), e.text()), (Some(2), Some("a = 1")));
+        assert_eq!(e.to_string(), "Parse error on line 2: Unknown key in 'a = 1'");
+    }
 
-    Ok(())
+    #[test]
+    fn test_parse_lines() {
+        let parse = |input| parse_lines(input, |l| Ok(l.parse::<i32>()?));
+        assert_eq!(parse("1\n\n  -2 \n"), Ok(vec![1, -2]));
+        let e = parse("1\n\n2x").unwrap_err();
+        assert_eq!(e.to_string(), "Parse error on line 3: invalid digit found in string in '2x'");
+    }
 }
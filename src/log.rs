@@ -0,0 +1,182 @@
+// Runtime logging with levels per target, a target being a module path within the crate like
+// day15 or elfcode::optimize. Configured with a spec like `debug,day15=trace`, from the AOC_LOG
+// environment variable or the command line
+use std::fmt;
+use std::env;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::RwLock;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Level {
+    Error, Warn, Info, Debug, Trace
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Level, String> {
+        match s.trim() {
+            "error" => Ok(Level::Error),
+            "warn" => Ok(Level::Warn),
+            "info" => Ok(Level::Info),
+            "debug" => Ok(Level::Debug),
+            "trace" => Ok(Level::Trace),
+            _ => Err(format!("Invalid log level '{}', expected error, warn, info, debug or trace", s))
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE"
+        })
+    }
+}
+
+pub const LOG_VAR: &str = "AOC_LOG";
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Config {
+    default: Level,
+    targets: Vec<(String, Level)>
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config { default: Level::Warn, targets: vec![] }
+    }
+}
+
+impl Config {
+    // Comma separated levels for all targets, or target=level for a target and the ones within it
+    pub fn parse(spec: &str) -> Result<Config, String> {
+        let mut config = Config::default();
+        for part in spec.split(',').map(|p| p.trim()).filter(|p| !p.is_empty()) {
+            match part.split_once('=') {
+                Some((target, level)) => config.targets.push((target.trim().to_string(), level.parse()?)),
+                None => config.default = part.parse()?
+            }
+        }
+        Ok(config)
+    }
+
+    // The level of the most specific target given for the module, otherwise the default
+    pub fn level(&self, target: &str) -> Level {
+        self.targets.iter()
+            .filter(|(t, _)| target == t || (target.starts_with(t.as_str()) && target[t.len()..].starts_with("::")))
+            .max_by_key(|(t, _)| t.len())
+            .map(|&(_, l)| l)
+            .unwrap_or(self.default)
+    }
+
+    pub fn enabled(&self, level: Level, target: &str) -> bool {
+        level <= self.level(target)
+    }
+
+    fn max_level(&self) -> Level {
+        self.targets.iter().map(|&(_, l)| l).fold(self.default, |a, b| a.max(b))
+    }
+}
+
+// Most detailed level enabled for any target, to skip most checks without taking the lock
+const UNSET: usize = usize::MAX;
+static MAX_LEVEL: AtomicUsize = AtomicUsize::new(UNSET);
+
+lazy_static! {
+    static ref CONFIG: RwLock<Config> = {
+        let config = env::var(LOG_VAR).ok()
+            .and_then(|spec| Config::parse(&spec).map_err(|e| eprintln!("Ignoring {}: {}", LOG_VAR, e)).ok())
+            .unwrap_or_default();
+        MAX_LEVEL.store(config.max_level() as usize, Ordering::Relaxed);
+        RwLock::new(config)
+    };
+}
+
+pub fn configure(config: Config) {
+    let mut c = CONFIG.write().unwrap();
+    MAX_LEVEL.store(config.max_level() as usize, Ordering::Relaxed);
+    *c = config;
+}
+
+// Module path without the crate name
+fn target(module: &str) -> &str {
+    module.split_once("::").map(|(_, t)| t).unwrap_or(module)
+}
+
+pub fn enabled(level: Level, module: &str) -> bool {
+    let max = MAX_LEVEL.load(Ordering::Relaxed);
+    if max != UNSET && level as usize > max {
+        return false;
+    }
+    CONFIG.read().unwrap().enabled(level, target(module))
+}
+
+// Messages go to stderr, without the trailing newline of rendered grids
+pub fn write(level: Level, module: &str, args: fmt::Arguments) {
+    let msg = args.to_string();
+    eprintln!("{:5} {}: {}", level, target(module), msg.trim_end_matches('\n'));
+}
+
+#[macro_export]
+macro_rules! log_enabled {
+    ($level:ident) => { $crate::log::enabled($crate::log::Level::$level, module_path!()) }
+}
+
+#[macro_export]
+macro_rules! log {
+    ($level:ident, $($arg:tt)+) => {
+        if log_enabled!($level) {
+            $crate::log::write($crate::log::Level::$level, module_path!(), format_args!($($arg)+));
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)+) => { log!(Info, $($arg)+) }
+}
+
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)+) => { log!(Debug, $($arg)+) }
+}
+
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)+) => { log!(Trace, $($arg)+) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Config::parse(""), Ok(Config::default()));
+        let c = Config::parse("debug, day15=trace,elfcode=info").unwrap();
+        assert_eq!(c.default, Level::Debug);
+        assert_eq!(c.targets, vec![("day15".to_string(), Level::Trace), ("elfcode".to_string(), Level::Info)]);
+        assert_eq!(c.max_level(), Level::Trace);
+        assert!(Config::parse("day15=loud").is_err());
+    }
+
+    #[test]
+    fn test_targets() {
+        let c = Config::parse("day15=trace,elfcode=debug,elfcode::optimize=error").unwrap();
+        assert!(c.enabled(Level::Trace, "day15"));
+        assert!(c.enabled(Level::Trace, "day15::tests"));
+        assert!(!c.enabled(Level::Debug, "day1"));
+        assert!(!c.enabled(Level::Debug, "day150"));
+        assert!(c.enabled(Level::Warn, "day1"));
+        assert!(c.enabled(Level::Debug, "elfcode::debugger"));
+        assert!(!c.enabled(Level::Warn, "elfcode::optimize"));
+        assert_eq!(target("utils::day15::tests"), "day15::tests");
+        assert_eq!(target("aoc"), "aoc");
+    }
+}
@@ -0,0 +1,198 @@
+// Points in N dimensional integer space with Manhattan geometry
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Index, IndexMut, Sub};
+use std::str::FromStr;
+use ParseError;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Point<const N: usize>(pub [i64; N]);
+
+impl<const N: usize> Point<N> {
+    pub fn origin() -> Point<N> {
+        Point([0; N])
+    }
+
+    pub fn x(&self) -> i64 {
+        self.0[0]
+    }
+
+    pub fn y(&self) -> i64 {
+        self.0[1]
+    }
+
+    pub fn z(&self) -> i64 {
+        self.0[2]
+    }
+
+    pub fn manhattan(&self, other: &Point<N>) -> i64 {
+        (0..N).map(|i| (self.0[i] - other.0[i]).abs()).sum()
+    }
+
+    // The 2N points one step away along an axis, in reading order
+    pub fn neighbours(&self) -> Vec<Point<N>> {
+        let mut ns: Vec<Point<N>> = (0..N)
+            .flat_map(|i| [-1, 1].iter().map(move |&d| (i, d)))
+            .map(|(i, d)| {
+                let mut p = *self;
+                p.0[i] += d;
+                p
+            })
+            .collect();
+        ns.sort();
+        ns
+    }
+
+    // The 3^N - 1 points one step away along any number of axes, in reading order
+    pub fn neighbours_diagonal(&self) -> Vec<Point<N>> {
+        let mut ns = vec![*self];
+        for i in 0..N {
+            ns = ns.iter()
+                .flat_map(|p| (-1..=1).map(move |d| {
+                    let mut n = *p;
+                    n.0[i] += d;
+                    n
+                }))
+                .collect();
+        }
+        ns.retain(|p| p != self);
+        ns.sort();
+        ns
+    }
+
+    // Smallest and largest corners of the box containing all points, None when there are none
+    pub fn bounding_box<'a, I: IntoIterator<Item = &'a Point<N>>>(points: I) -> Option<(Point<N>, Point<N>)> {
+        points.into_iter().fold(None, |bb, p| match bb {
+            None => Some((*p, *p)),
+            Some((mut min, mut max)) => {
+                for i in 0..N {
+                    min.0[i] = min.0[i].min(p.0[i]);
+                    max.0[i] = max.0[i].max(p.0[i]);
+                }
+                Some((min, max))
+            }
+        })
+    }
+}
+
+impl<const N: usize> Index<usize> for Point<N> {
+    type Output = i64;
+
+    fn index(&self, i: usize) -> &i64 {
+        &self.0[i]
+    }
+}
+
+impl<const N: usize> IndexMut<usize> for Point<N> {
+    fn index_mut(&mut self, i: usize) -> &mut i64 {
+        &mut self.0[i]
+    }
+}
+
+impl<const N: usize> Add for Point<N> {
+    type Output = Point<N>;
+
+    fn add(mut self, other: Point<N>) -> Point<N> {
+        for i in 0..N {
+            self.0[i] += other.0[i];
+        }
+        self
+    }
+}
+
+impl<const N: usize> Sub for Point<N> {
+    type Output = Point<N>;
+
+    fn sub(mut self, other: Point<N>) -> Point<N> {
+        for i in 0..N {
+            self.0[i] -= other.0[i];
+        }
+        self
+    }
+}
+
+// Reading order, the last axis is the most significant, so y before x in two dimensions
+impl<const N: usize> Ord for Point<N> {
+    fn cmp(&self, other: &Point<N>) -> Ordering {
+        self.0.iter().rev().cmp(other.0.iter().rev())
+    }
+}
+
+impl<const N: usize> PartialOrd for Point<N> {
+    fn partial_cmp(&self, other: &Point<N>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Parses comma separated coordinates, optionally within angle brackets, like `1, 2` or `<1,2,-3>`
+impl<const N: usize> FromStr for Point<N> {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Point<N>, ParseError> {
+        let t = s.trim();
+        let t = t.strip_prefix('<').and_then(|t| t.strip_suffix('>')).unwrap_or(t);
+        let cs: Vec<&str> = t.split(',').map(|c| c.trim()).collect();
+        if cs.len() != N {
+            return Err(ParseError::expected(format!("{} comma separated coordinates", N), s));
+        }
+        let mut p = Point::origin();
+        for (i, c) in cs.iter().enumerate() {
+            p.0[i] = c.parse().map_err(|_| ParseError::expected("an integer coordinate", c))?;
+        }
+        Ok(p)
+    }
+}
+
+impl<const N: usize> fmt::Display for Point<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let cs: Vec<String> = self.0.iter().map(|c| c.to_string()).collect();
+        write!(f, "{}", cs.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("1, 6".parse::<Point<2>>(), Ok(Point([1, 6])));
+        assert_eq!("<-1,2, 3>".parse::<Point<3>>(), Ok(Point([-1, 2, 3])));
+        assert_eq!("-1,2,2,0".parse::<Point<4>>(), Ok(Point([-1, 2, 2, 0])));
+        assert_eq!("1,2".parse::<Point<3>>(), Err(ParseError::expected("3 comma separated coordinates", "1,2")));
+        assert_eq!("1,x".parse::<Point<2>>(), Err(ParseError::expected("an integer coordinate", "x")));
+        assert_eq!(Point([3, -4]).to_string(), "3,-4");
+    }
+
+    #[test]
+    fn test_manhattan() {
+        assert_eq!(Point([1, 1]).manhattan(&Point([8, 3])), 9);
+        assert_eq!(Point([0, 0, 0, 0]).manhattan(&Point([-1, 2, -3, 4])), 10);
+    }
+
+    #[test]
+    fn test_reading_order() {
+        let mut ps = vec![Point([2, 1]), Point([1, 2]), Point([0, 1]), Point([5, 0])];
+        ps.sort();
+        assert_eq!(ps, vec![Point([5, 0]), Point([0, 1]), Point([2, 1]), Point([1, 2])]);
+        assert!(Point([9, 9, 0]) < Point([0, 0, 1]));
+    }
+
+    #[test]
+    fn test_neighbours() {
+        assert_eq!(Point([1, 1]).neighbours(), vec![Point([1, 0]), Point([0, 1]), Point([2, 1]), Point([1, 2])]);
+        assert_eq!(Point([0, 0, 0]).neighbours().len(), 6);
+        let ns = Point([0, 0]).neighbours_diagonal();
+        assert_eq!(ns.len(), 8);
+        assert_eq!((ns[0], ns[7]), (Point([-1, -1]), Point([1, 1])));
+        assert_eq!(Point([0, 0, 0, 0]).neighbours_diagonal().len(), 80);
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let ps = vec![Point([1, 5, 0]), Point([-2, 3, 7]), Point([4, -1, 2])];
+        assert_eq!(Point::bounding_box(&ps), Some((Point([-2, -1, 0]), Point([4, 5, 7]))));
+        assert_eq!(Point::<2>::bounding_box(&vec![]), None);
+        assert_eq!(Point([1, 2]) + Point([3, -4]) - Point([1, 1]), Point([3, -3]));
+    }
+}
@@ -0,0 +1,363 @@
+// Rendering of grids to PPM and PNG images, written as numbered frames for animations
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use grid::Grid;
+use Solution;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<Rgb>
+}
+
+impl Image {
+    // Image of the colours of the grid, each cell as a square of scale pixels
+    pub fn from_grid(grid: &Grid<Rgb>, scale: usize) -> Image {
+        let (width, height) = (grid.width() * scale, grid.height() * scale);
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| grid[(x / scale, y / scale)])
+            .collect();
+        Image { width, height, pixels }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Rgb {
+        self.pixels[y * self.width + x]
+    }
+
+    // Binary PPM, the simplest format most image tools read
+    pub fn write_ppm<W: Write>(&self, mut out: W) -> io::Result<()> {
+        write!(out, "P6\n{} {}\n255\n", self.width, self.height)?;
+        let bytes: Vec<u8> = self.pixels.iter().flat_map(|p| vec![p.0, p.1, p.2]).collect();
+        out.write_all(&bytes)
+    }
+
+    // 8 bit RGB PNG without filtering, compressed as a single deflate block
+    pub fn write_png<W: Write>(&self, mut out: W) -> io::Result<()> {
+        let mut raw = Vec::with_capacity(self.height * (self.width * 3 + 1));
+        for row in self.pixels.chunks(self.width.max(1)).take(self.height) {
+            raw.push(0);
+            for p in row {
+                raw.extend_from_slice(&[p.0, p.1, p.2]);
+            }
+        }
+
+        let mut header = vec![];
+        header.extend_from_slice(&(self.width as u32).to_be_bytes());
+        header.extend_from_slice(&(self.height as u32).to_be_bytes());
+        header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+        let mut data = vec![0x78, 0x01];
+        data.extend(deflate(&raw));
+        data.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+        out.write_all(&[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'])?;
+        write_chunk(&mut out, b"IHDR", &header)?;
+        write_chunk(&mut out, b"IDAT", &data)?;
+        write_chunk(&mut out, b"IEND", &[])
+    }
+
+    pub fn write<W: Write>(&self, out: W, format: ImageFormat) -> io::Result<()> {
+        match format {
+            ImageFormat::Ppm => self.write_ppm(out),
+            ImageFormat::Png => self.write_png(out)
+        }
+    }
+}
+
+fn write_chunk<W: Write>(out: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    out.write_all(kind)?;
+    out.write_all(data)?;
+    let crc = crc32(kind.iter().chain(data.iter()));
+    out.write_all(&crc.to_be_bytes())
+}
+
+fn crc32<'a, I: IntoIterator<Item = &'a u8>>(bytes: I) -> u32 {
+    lazy_static! {
+        static ref TABLE: Vec<u32> = (0..256u32)
+            .map(|n| (0..8).fold(n, |c, _| if c & 1 == 1 { 0xedb8_8320 ^ (c >> 1) } else { c >> 1 }))
+            .collect();
+    }
+    !bytes.into_iter().fold(!0u32, |c, &b| TABLE[((c ^ b as u32) & 0xff) as usize] ^ (c >> 8))
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (a, b) = bytes.iter().fold((1u32, 0u32), |(a, b), &x| {
+        let a = (a + x as u32) % 65521;
+        (a, (b + a) % 65521)
+    });
+    (b << 16) | a
+}
+
+// Writes bits starting from the least significant bit of each byte, as deflate expects
+struct BitWriter {
+    bytes: Vec<u8>,
+    bits: u32,
+    count: u32
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, count: u32) {
+        self.bits |= value << self.count;
+        self.count += count;
+        while self.count >= 8 {
+            self.bytes.push(self.bits as u8);
+            self.bits >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    // Huffman codes are written starting from their most significant bit
+    fn write_code(&mut self, code: u32, count: u32) {
+        let reversed = (0..count).fold(0, |r, i| (r << 1) | ((code >> i) & 1));
+        self.write(reversed, count);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push(self.bits as u8);
+        }
+        self.bytes
+    }
+}
+
+const LENGTH_BASE: [u32; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31,
+                                35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u32; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2,
+                                 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DIST_BASE: [u32; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385,
+                              513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+const DIST_EXTRA: [u32; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7,
+                               8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+// Literal or length symbol with the fixed Huffman code
+fn write_symbol(w: &mut BitWriter, sym: u32) {
+    match sym {
+        0..=143 => w.write_code(0x30 + sym, 8),
+        144..=255 => w.write_code(0x190 + sym - 144, 9),
+        256..=279 => w.write_code(sym - 256, 7),
+        _ => w.write_code(0xc0 + sym - 280, 8)
+    }
+}
+
+// Index of the code for a length or distance given the bases of the codes
+fn find_code(bases: &[u32], value: u32) -> usize {
+    bases.iter().rposition(|&b| b <= value).unwrap()
+}
+
+// Deflate with the fixed Huffman codes, finding repeats with the last position of every
+// three bytes, which is enough for the long runs and repeated rows of rendered grids
+fn deflate(data: &[u8]) -> Vec<u8> {
+    const WINDOW: usize = 32768;
+    let mut w = BitWriter { bytes: vec![], bits: 0, count: 0 };
+    w.write(1, 1);
+    w.write(1, 2);
+
+    let hash = |i: usize| ((data[i] as usize) << 10 ^ (data[i + 1] as usize) << 5 ^ data[i + 2] as usize) & 0x7fff;
+    let mut last = vec![usize::MAX; 0x8000];
+    let mut i = 0;
+    while i < data.len() {
+        let (mut len, mut dist) = (0, 0);
+        if i + 3 <= data.len() {
+            let h = hash(i);
+            let c = last[h];
+            if c != usize::MAX && i - c <= WINDOW {
+                while len < 258 && i + len < data.len() && data[c + len] == data[i + len] {
+                    len += 1;
+                }
+                dist = (i - c) as u32;
+            }
+            last[h] = i;
+        }
+
+        if len >= 3 {
+            let lc = find_code(&LENGTH_BASE, len as u32);
+            write_symbol(&mut w, 257 + lc as u32);
+            w.write(len as u32 - LENGTH_BASE[lc], LENGTH_EXTRA[lc]);
+            let dc = find_code(&DIST_BASE, dist);
+            w.write_code(dc as u32, 5);
+            w.write(dist - DIST_BASE[dc], DIST_EXTRA[dc]);
+            for j in i + 1..(i + len).min(data.len().saturating_sub(2)) {
+                last[hash(j)] = j;
+            }
+            i += len;
+        } else {
+            write_symbol(&mut w, data[i] as u32);
+            i += 1;
+        }
+    }
+    write_symbol(&mut w, 256);
+    w.finish()
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ImageFormat {
+    Ppm, Png
+}
+
+impl ImageFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Ppm => "ppm",
+            ImageFormat::Png => "png"
+        }
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ImageFormat, String> {
+        match s.trim() {
+            "ppm" => Ok(ImageFormat::Ppm),
+            "png" => Ok(ImageFormat::Png),
+            _ => Err(format!("Invalid image format '{}', expected png or ppm", s))
+        }
+    }
+}
+
+// Writes the ticks of a simulation as numbered images in a directory, like day17_00042.png,
+// skipping all but every nth tick to keep long simulations manageable
+pub struct Frames {
+    dir: PathBuf,
+    prefix: String,
+    format: ImageFormat,
+    scale: usize,
+    every: usize,
+    ticks: usize,
+    written: usize
+}
+
+impl Frames {
+    pub fn new<P: AsRef<Path>>(dir: P, prefix: &str, format: ImageFormat) -> io::Result<Frames> {
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(Frames {
+            dir: dir.as_ref().to_path_buf(),
+            prefix: prefix.to_string(),
+            format,
+            scale: 1,
+            every: 1,
+            ticks: 0,
+            written: 0
+        })
+    }
+
+    pub fn with_scale(self, scale: usize) -> Frames {
+        Frames { scale: scale.max(1), ..self }
+    }
+
+    pub fn with_every(self, every: usize) -> Frames {
+        Frames { every: every.max(1), ..self }
+    }
+
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    fn write(&mut self, grid: &Grid<Rgb>) -> io::Result<()> {
+        let path = self.dir.join(format!("{}_{:05}.{}", self.prefix, self.written, self.format.extension()));
+        let mut out = BufWriter::new(File::create(path)?);
+        Image::from_grid(grid, self.scale).write(&mut out, self.format)?;
+        out.flush()?;
+        self.written += 1;
+        Ok(())
+    }
+
+    // A tick of the simulation, only rendered when it's one of the written ones
+    pub fn tick<F: FnOnce() -> Grid<Rgb>>(&mut self, render: F) -> io::Result<()> {
+        self.ticks += 1;
+        if (self.ticks - 1).is_multiple_of(self.every) {
+            self.write(&render())?;
+        }
+        Ok(())
+    }
+
+    // The final state of the simulation, which is always written
+    pub fn last<F: FnOnce() -> Grid<Rgb>>(&mut self, render: F) -> io::Result<()> {
+        self.ticks += 1;
+        self.write(&render())
+    }
+}
+
+// Solutions that can draw the states of their simulation
+pub trait Animation: Solution {
+    fn animate(input: &Self::Input, frames: &mut Frames) -> io::Result<()>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    const RED: Rgb = Rgb(255, 0, 0);
+    const BLUE: Rgb = Rgb(0, 0, 255);
+
+    fn checkers() -> Grid<Rgb> {
+        Grid::from_fn(3, 2, |x, y| if (x + y) % 2 == 0 { RED } else { BLUE })
+    }
+
+    #[test]
+    fn test_image() {
+        let image = Image::from_grid(&checkers(), 2);
+        assert_eq!((image.width(), image.height()), (6, 4));
+        assert_eq!((image.pixel(1, 1), image.pixel(2, 1), image.pixel(2, 2)), (RED, BLUE, RED));
+
+        let mut ppm = vec![];
+        Image::from_grid(&checkers(), 1).write_ppm(&mut ppm).unwrap();
+        assert_eq!(&ppm[..11], b"P6\n3 2\n255\n");
+        assert_eq!(&ppm[11..17], &[255, 0, 0, 0, 0, 255]);
+        assert_eq!(ppm.len(), 11 + 3 * 2 * 3);
+    }
+
+    #[test]
+    fn test_checksums() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn test_deflate() {
+        // The empty fixed block, and a run that becomes a literal and a single match
+        assert_eq!(deflate(&[]), vec![0x03, 0x00]);
+        let run = deflate(&[7; 200]);
+        assert!(run.len() < 8);
+        assert_eq!(deflate(b"abc").len(), 5);
+
+        let mut png = vec![];
+        Image::from_grid(&checkers(), 10).write_png(&mut png).unwrap();
+        assert_eq!(&png[..8], &[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n']);
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..24], &[0, 0, 0, 30, 0, 0, 0, 20]);
+        assert_eq!(&png[png.len() - 8..], &[b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]);
+    }
+
+    #[test]
+    fn test_frames() {
+        let dir = env::temp_dir().join(format!("aoc-frames-{}", std::process::id()));
+        let mut frames = Frames::new(&dir, "test", ImageFormat::Ppm).unwrap().with_every(3);
+        for _ in 0..4 {
+            frames.tick(checkers).unwrap();
+        }
+        frames.last(checkers).unwrap();
+        assert_eq!(frames.written(), 3);
+        assert!(dir.join("test_00002.ppm").exists());
+        assert!(!dir.join("test_00003.ppm").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,165 @@
+// Graph searches over arbitrary states given by a successor function
+use std::cmp::Ordering;
+use std::collections::hash_map::Entry;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::hash::Hash;
+use std::ops::Add;
+
+// Costs of the states reached from a start state, and the state each was reached from
+pub struct Paths<S, C> {
+    reached: HashMap<S, (C, Option<S>)>
+}
+
+impl<S: Hash + Eq + Clone, C: Copy> Paths<S, C> {
+    pub fn cost(&self, state: &S) -> Option<C> {
+        self.reached.get(state).map(|&(c, _)| c)
+    }
+
+    // States from the start to the given one, both included
+    pub fn path(&self, to: &S) -> Option<Vec<S>> {
+        let mut path = vec![];
+        let mut s = Some(to.clone());
+        while let Some(p) = s {
+            s = self.reached.get(&p)?.1.clone();
+            path.push(p);
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    pub fn reached(&self) -> impl Iterator<Item = (&S, C)> {
+        self.reached.iter().map(|(s, &(c, _))| (s, c))
+    }
+}
+
+// Number of steps to every state reachable from the start
+pub fn bfs<S, F, I>(start: S, mut successors: F) -> Paths<S, usize>
+where S: Hash + Eq + Clone, F: FnMut(&S) -> I, I: IntoIterator<Item = S> {
+    let mut reached = HashMap::new();
+    reached.insert(start.clone(), (0, None));
+    let mut to_eval = VecDeque::new();
+    to_eval.push_back((start, 0));
+    while let Some((s, d)) = to_eval.pop_front() {
+        for n in successors(&s) {
+            if let Entry::Vacant(e) = reached.entry(n.clone()) {
+                e.insert((d + 1, Some(s.clone())));
+                to_eval.push_back((n, d + 1));
+            }
+        }
+    }
+    Paths { reached }
+}
+
+// Queue entry ordered so that the lowest estimate is popped first from the max heap
+struct Queued<S, C> {
+    estimate: C,
+    cost: C,
+    state: S
+}
+
+impl<S, C: Ord> Ord for Queued<S, C> {
+    fn cmp(&self, other: &Queued<S, C>) -> Ordering {
+        other.estimate.cmp(&self.estimate)
+    }
+}
+
+impl<S, C: Ord> PartialOrd for Queued<S, C> {
+    fn partial_cmp(&self, other: &Queued<S, C>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<S, C: Ord> PartialEq for Queued<S, C> {
+    fn eq(&self, other: &Queued<S, C>) -> bool {
+        self.estimate == other.estimate
+    }
+}
+
+impl<S, C: Ord> Eq for Queued<S, C> {}
+
+// Cheapest path from the start to a goal state, with successors given together with the cost
+// of the step to them. The default value of the cost type is taken as zero
+pub fn dijkstra<S, C, F, I, G>(start: S, successors: F, goal: G) -> Option<(C, Vec<S>)>
+where S: Hash + Eq + Clone, C: Copy + Ord + Add<Output = C> + Default,
+      F: FnMut(&S) -> I, I: IntoIterator<Item = (S, C)>, G: FnMut(&S) -> bool {
+    astar(start, successors, |_| C::default(), goal)
+}
+
+// Like dijkstra, but expanding states in order of cost plus the heuristic, which must never
+// overestimate the remaining cost to a goal
+pub fn astar<S, C, F, I, H, G>(start: S, mut successors: F, mut heuristic: H, mut goal: G) -> Option<(C, Vec<S>)>
+where S: Hash + Eq + Clone, C: Copy + Ord + Add<Output = C> + Default,
+      F: FnMut(&S) -> I, I: IntoIterator<Item = (S, C)>, H: FnMut(&S) -> C, G: FnMut(&S) -> bool {
+    let mut paths = Paths { reached: HashMap::new() };
+    paths.reached.insert(start.clone(), (C::default(), None));
+    let mut queue = BinaryHeap::new();
+    queue.push(Queued { estimate: heuristic(&start), cost: C::default(), state: start });
+
+    while let Some(Queued { cost, state, .. }) = queue.pop() {
+        // Skip states already reached cheaper after being queued
+        if paths.cost(&state).is_some_and(|c| c < cost) {
+            continue;
+        }
+        if goal(&state) {
+            let path = paths.path(&state).unwrap();
+            return Some((cost, path));
+        }
+        for (n, step) in successors(&state) {
+            let nc = cost + step;
+            if paths.cost(&n).is_some_and(|c| c <= nc) {
+                continue;
+            }
+            paths.reached.insert(n.clone(), (nc, Some(state.clone())));
+            queue.push(Queued { estimate: nc + heuristic(&n), cost: nc, state: n });
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grid::Grid;
+
+    const MAZE: &str =
+       "#######
+        #S..#.#
+        #.#.#.#
+        #.#...#
+        #...#E#
+        #######";
+
+    fn open_neighbours(maze: &Grid<char>, (x, y): (usize, usize)) -> Vec<(usize, usize)> {
+        maze.neighbours4(x, y).filter(|&p| maze[p] != '#').collect()
+    }
+
+    #[test]
+    fn test_bfs() {
+        let maze = Grid::parse(MAZE, |_, _, c| Ok(c)).unwrap();
+        let paths = bfs((1, 1), |&p| open_neighbours(&maze, p));
+        assert_eq!(paths.cost(&(5, 4)), Some(7));
+        assert_eq!(paths.cost(&(5, 1)), Some(8));
+        assert_eq!(paths.cost(&(0, 0)), None);
+        assert_eq!(paths.reached().count(), 15);
+        let path = paths.path(&(5, 4)).unwrap();
+        assert_eq!((path.len(), path[0], path[7]), (8, (1, 1), (5, 4)));
+    }
+
+    #[test]
+    fn test_dijkstra() {
+        // Moving down is cheap, which doesn't pay off for the longer way down the left side
+        let maze = Grid::parse(MAZE, |_, _, c| Ok(c)).unwrap();
+        let successors = |&(x, y): &(usize, usize)| {
+            open_neighbours(&maze, (x, y)).into_iter()
+                .map(move |(nx, ny)| ((nx, ny), if ny > y { 1 } else { 5 }))
+        };
+        let (cost, path) = dijkstra((1, 1), successors, |&p| p == (5, 4)).unwrap();
+        assert_eq!(cost, 3 + 5 * 4);
+        assert_eq!(path, vec![(1, 1), (2, 1), (3, 1), (3, 2), (3, 3), (4, 3), (5, 3), (5, 4)]);
+        assert_eq!(dijkstra((1, 1), successors, |&p| p == (0, 0)), None);
+
+        let manhattan = |&(x, y): &(usize, usize)| (5 - x) + (4 - y);
+        let (ac, _) = astar((1, 1), successors, manhattan, |&p| p == (5, 4)).unwrap();
+        assert_eq!(ac, cost);
+    }
+}
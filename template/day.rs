@@ -1,46 +1,27 @@
-extern crate regex;
-#[macro_use] extern crate lazy_static;
-extern crate utils;
-
 use std::cmp;
 use std::collections::HashSet;
 use std::collections::HashMap;
-use std::str::FromStr;
-use std::error::Error;
-use std::num::ParseIntError;
-use std::io::{self, BufReader};
-use std::io::prelude::*;
-use std::fs::File;
 use regex::Regex;
-use utils::*;
+use {ParseError, Solution};
 
+pub struct xDAYTx;
 
-fn part1(input: &Vec<String>) -> i32 {
-    0
-}
+impl Solution for xDAYTx {
+    type Input = Vec<String>;
+    type Answer1 = i32;
+    type Answer2 = i32;
 
-fn part2(input: &Vec<String>) -> i32 {
-    0
-}
+    fn parse(input: &str) -> Result<Vec<String>, ParseError> {
+        Ok(input.lines().map(|l| l.trim().to_string()).collect())
+    }
 
-fn main() -> Result<(), Box<Error>> {
-    measure_exec(|| {
-        let result = part1(&input()?);
-        println!("Part1 result: {}", result);
-        Ok(())
-    })?;
-    measure_exec(|| {
-        let result = part2(&input()?);
-        println!("Part2 result: {}", result);
-        Ok(())
-    })?;
-    Ok(())
-}
+    fn part1(input: &Vec<String>) -> i32 {
+        0
+    }
 
-fn input() -> io::Result<Vec<String>> {
-    let f = File::open("src/xDAYx/input")?;
-    let f = BufReader::new(f);
-    Ok(f.lines().map(|l| l.unwrap()).collect())
+    fn part2(input: &Vec<String>) -> i32 {
+        0
+    }
 }
 
 #[cfg(test)]
@@ -51,16 +32,16 @@ mod tests {
        "";
 
     fn as_input(s: &str) -> Vec<String> {
-        s.split('\n').map(|s| s.trim().into()).collect()
+        xDAYTx::parse(s).unwrap()
     }
 
     #[test]
     fn test_part1() {
-        assert_eq!(part1(&as_input(INPUT)), 1337);
+        assert_eq!(xDAYTx::part1(&as_input(INPUT)), 1337);
     }
 
     #[test]
     fn test_part2() {
-        assert_eq!(part2(&as_input(INPUT)), 1337);
+        assert_eq!(xDAYTx::part2(&as_input(INPUT)), 1337);
     }
-}
\ No newline at end of file
+}
@@ -9,10 +9,12 @@ fi
 
 DST="src/day$DAY/day$DAY.rs"
 mkdir -p "src/day$DAY/"
-sed "s/xDAYx/day$DAY/g" < template/day.rs > "$DST"
+sed "s/xDAYTx/Day$DAY/g" < template/day.rs > "$DST"
 echo "$DST created"
 
 echo "
-[[bin]]
-name = \"day$DAY\"
-path = \"$DST\"" >> Cargo.toml
\ No newline at end of file
+Register the new day by adding it to src/lib.rs:
+#[path = \"day$DAY/day$DAY.rs\"] pub mod day$DAY;
+
+and to DAYS in src/days.rs:
+Day::new::<day$DAY::Day$DAY>($((10#$DAY)), \"\"),"